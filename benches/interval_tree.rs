@@ -0,0 +1,80 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use programming_rust::interval::{Interval, IntervalTree};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Time windows of up to a minute, scattered over a day in seconds
+fn windows(count: usize, seed: u64) -> Vec<Interval<u32>> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..count)
+        .map(|_| {
+            let start = rng.gen_range(0..86_400);
+            Interval::new(start, start + rng.gen_range(0..60))
+        })
+        .collect()
+}
+
+fn queries(c: &mut Criterion) {
+    let points = windows(1000, 2)
+        .into_iter()
+        .map(|it| *it.lower())
+        .collect::<Vec<_>>();
+    let ranges = windows(1000, 3)
+        .into_iter()
+        .map(|it| Interval::new(*it.lower(), it.lower() + 600))
+        .collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("interval_stab");
+    for count in [1_000, 10_000, 100_000] {
+        let intervals = windows(count, 1);
+        let tree = intervals.iter().copied().collect::<IntervalTree<_>>();
+        group.bench_with_input(BenchmarkId::new("tree", count), &tree, |b, tree| {
+            b.iter(|| points.iter().map(|p| tree.stab(p).len()).sum::<usize>())
+        });
+        group.bench_with_input(BenchmarkId::new("scan", count), &intervals, |b, v| {
+            b.iter(|| {
+                points
+                    .iter()
+                    .map(|p| v.iter().filter(|it| it.contains(p)).count())
+                    .sum::<usize>()
+            })
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("interval_overlap");
+    for count in [1_000, 10_000, 100_000] {
+        let intervals = windows(count, 1);
+        let tree = intervals.iter().copied().collect::<IntervalTree<_>>();
+        group.bench_with_input(BenchmarkId::new("tree", count), &tree, |b, tree| {
+            b.iter(|| {
+                ranges
+                    .iter()
+                    .map(|r| tree.overlapping(r).len())
+                    .sum::<usize>()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("scan", count), &intervals, |b, v| {
+            b.iter(|| {
+                ranges
+                    .iter()
+                    .map(|r| v.iter().filter(|it| it.overlaps(r)).count())
+                    .sum::<usize>()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn building(c: &mut Criterion) {
+    let intervals = windows(100_000, 1);
+    let mut group = c.benchmark_group("interval_build");
+    group.sample_size(10);
+    group.bench_function("tree", |b| {
+        b.iter(|| intervals.iter().copied().collect::<IntervalTree<_>>())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, queries, building);
+criterion_main!(benches);
@@ -0,0 +1,77 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use programming_rust::prim_future::executor::{self, Executor};
+use programming_rust::prim_future::work_stealing::{self, ThreadPool};
+
+/// Spawn `tasks` small tasks, each of which spawns a child, and wait for all of them
+fn spawn_heavy(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spawn_heavy");
+    let threads = std::thread::available_parallelism().map_or(4, |n| n.get());
+    let pool = ThreadPool::new(threads);
+
+    for tasks in [1_000_u64, 10_000] {
+        group.bench_with_input(BenchmarkId::new("work_stealing", tasks), &tasks, |b, &n| {
+            b.iter(|| {
+                pool.block_on(async move {
+                    let handles = (0..n)
+                        .map(|i| {
+                            work_stealing::spawn(async move {
+                                work_stealing::spawn(async move { i * 2 }).await
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    let mut total = 0;
+                    for handle in handles {
+                        total += handle.await;
+                    }
+                    total
+                })
+            })
+        });
+
+        group.bench_with_input(
+            BenchmarkId::new("single_threaded", tasks),
+            &tasks,
+            |b, &n| {
+                b.iter(|| {
+                    Executor::new().block_on(async move {
+                        let handles = (0..n)
+                            .map(|i| {
+                                executor::spawn(async move {
+                                    executor::spawn(async move { i * 2 }).await
+                                })
+                            })
+                            .collect::<Vec<_>>();
+                        let mut total = 0;
+                        for handle in handles {
+                            total += handle.await;
+                        }
+                        total
+                    })
+                })
+            },
+        );
+
+        group.bench_with_input(BenchmarkId::new("async_std", tasks), &tasks, |b, &n| {
+            b.iter(|| {
+                async_std::task::block_on(async move {
+                    let handles = (0..n)
+                        .map(|i| {
+                            async_std::task::spawn(async move {
+                                async_std::task::spawn(async move { i * 2 }).await
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    let mut total = 0;
+                    for handle in handles {
+                        total += handle.await;
+                    }
+                    total
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, spawn_heavy);
+criterion_main!(benches);
@@ -0,0 +1,83 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use programming_rust::string_set::{
+    find_unknown, BloomFilter, SortedVec, StringSet, StringSetHash, Trie,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const WORDS: usize = 200_000;
+
+/// A reproducible list of word-like strings, since there's no dictionary to rely on
+fn word_list(count: usize, seed: u64) -> Vec<String> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..count)
+        .map(|_| {
+            let len = rng.gen_range(3..12);
+            (0..len)
+                .map(|_| rng.gen_range(b'a'..=b'z') as char)
+                .collect()
+        })
+        .collect()
+}
+
+fn build<'a, S: StringSet<'a>>(words: &'a [String]) -> S {
+    let mut set = S::new();
+    for word in words {
+        set.add(word);
+    }
+    set
+}
+
+fn bench_set<'a, S: StringSet<'a>>(c: &mut Criterion, name: &str, set: S, queries: &'a [&str]) {
+    let mut group = c.benchmark_group("string_set");
+    group.bench_with_input(BenchmarkId::new("find_unknown", name), &set, |b, set| {
+        b.iter(|| find_unknown(queries, set))
+    });
+    group.finish();
+}
+
+/// Look up a mix of words which are in the set and words which (almost certainly) aren't
+fn lookups(c: &mut Criterion) {
+    let words = word_list(WORDS, 1);
+    let others = word_list(10_000, 2);
+    let queries = words
+        .iter()
+        .take(10_000)
+        .chain(&others)
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+
+    bench_set(c, "hash", build::<StringSetHash>(&words), &queries);
+    bench_set(c, "trie", build::<Trie>(&words), &queries);
+    let sorted = SortedVec::from_unsorted(words.iter().map(String::as_str).collect());
+    bench_set(c, "sorted_vec", sorted, &queries);
+    let mut bloom = BloomFilter::with_rate(WORDS, 0.01);
+    for word in &words {
+        bloom.add(word);
+    }
+    bench_set(c, "bloom", bloom, &queries);
+}
+
+fn building(c: &mut Criterion) {
+    let words = word_list(WORDS, 1);
+    let mut group = c.benchmark_group("string_set_build");
+    group.sample_size(10);
+    group.bench_function("hash", |b| b.iter(|| build::<StringSetHash>(&words)));
+    group.bench_function("trie", |b| b.iter(|| build::<Trie>(&words)));
+    group.bench_function("sorted_vec", |b| {
+        b.iter(|| SortedVec::from_unsorted(words.iter().map(String::as_str).collect()))
+    });
+    group.bench_function("bloom", |b| {
+        b.iter(|| {
+            let mut bloom = BloomFilter::with_rate(WORDS, 0.01);
+            for word in &words {
+                bloom.add(word);
+            }
+            bloom
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, lookups, building);
+criterion_main!(benches);
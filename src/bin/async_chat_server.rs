@@ -0,0 +1,15 @@
+use programming_rust::chat_server::{AsyncChatServer, DEFAULT_MAX_LINE};
+use programming_rust::echo_server::ServerConfig;
+use std::io;
+
+/// Run the async-std chat server. It speaks the same protocol as the `chat_server` binary:
+/// >> `socat - TCP4:localhost:8082`
+#[async_std::main]
+async fn main() -> io::Result<()> {
+    // The same connection limit as the threaded server
+    let max_connections = ServerConfig::default().max_connections;
+    let server = AsyncChatServer::bind("127.0.0.1:8082", DEFAULT_MAX_LINE, max_connections).await?;
+    let handle = server.shutdown_handle();
+    ctrlc::set_handler(move || handle.shutdown()).map_err(io::Error::other)?;
+    server.run().await
+}
@@ -10,6 +10,12 @@ async fn main() {
     let path = "/api/timezone/Europe/London";
     let response = send_request(host, 80, path).await;
     println!("Output is `{}`", response.unwrap());
+
+    #[cfg(feature = "tls")]
+    {
+        let response = send_request_tls(host, 443, path).await;
+        println!("Output over https is `{}`", response.unwrap());
+    }
 }
 
 async fn send_request(host: &str, port: u16, path: &str) -> io::Result<String> {
@@ -26,3 +32,25 @@ async fn send_request(host: &str, port: u16, path: &str) -> io::Result<String> {
 
     Ok(response)
 }
+
+#[cfg(feature = "tls")]
+async fn send_request_tls(host: &str, port: u16, path: &str) -> io::Result<String> {
+    use programming_rust::tls;
+
+    let config = tls::client_config(&[])?;
+    let mut socket = tls::connect_async((host, port), host, config).await?;
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        path, host
+    );
+    socket.write_all(request.as_bytes()).await?;
+
+    let mut response = vec![];
+    match socket.read_to_end(&mut response).await {
+        Ok(_) => {}
+        Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => {}
+        Err(e) => return Err(e),
+    }
+
+    Ok(String::from_utf8_lossy(&response).into_owned())
+}
@@ -7,6 +7,12 @@ fn main() {
     let path = "/api/timezone/America/Argentina/Salta";
     let response = send_request(host, 80, path);
     println!("{} - Output is `{}`", host, response.unwrap());
+
+    #[cfg(feature = "tls")]
+    {
+        let response = send_request_tls(host, 443, path);
+        println!("{} (https) - Output is `{}`", host, response.unwrap());
+    }
 }
 
 fn send_request(host: &str, port: u16, path: &str) -> io::Result<String> {
@@ -22,3 +28,27 @@ fn send_request(host: &str, port: u16, path: &str) -> io::Result<String> {
 
     Ok(response)
 }
+
+/// TLS has no half-close, so instead of shutting down our side we ask the server to hang up
+#[cfg(feature = "tls")]
+fn send_request_tls(host: &str, port: u16, path: &str) -> io::Result<String> {
+    use programming_rust::tls;
+
+    let config = tls::client_config(&[])?;
+    let mut socket = tls::connect((host, port), host, config)?;
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        path, host
+    );
+    socket.write_all(request.as_bytes())?;
+
+    let mut response = vec![];
+    match socket.read_to_end(&mut response) {
+        Ok(_) => {}
+        // Plenty of servers close the socket without a TLS close_notify
+        Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => {}
+        Err(e) => return Err(e),
+    }
+
+    Ok(String::from_utf8_lossy(&response).into_owned())
+}
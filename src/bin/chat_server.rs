@@ -0,0 +1,17 @@
+use programming_rust::chat_server::{self, DEFAULT_MAX_LINE};
+use programming_rust::echo_server::ServerConfig;
+use std::io;
+
+/// Run the threaded chat server. Connect a few clients using `socat` and try out the commands:
+/// >> `socat - TCP4:localhost:8081`
+/// >> `/nick rand`, `/join lobby`, `/list`, `/leave`
+fn main() {
+    run_server("127.0.0.1:8081").expect("Failed to start server");
+}
+
+fn run_server(addr: &str) -> io::Result<()> {
+    let server = chat_server::bind(addr, ServerConfig::default())?;
+    let handle = server.shutdown_handle();
+    ctrlc::set_handler(move || handle.shutdown()).map_err(io::Error::other)?;
+    chat_server::run(server, DEFAULT_MAX_LINE)
+}
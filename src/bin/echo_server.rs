@@ -1,25 +1,53 @@
-use std::net::TcpListener;
-use std::{io, thread};
+use programming_rust::cli_error::{self, CliError};
+use programming_rust::echo_server::{EchoServer, ServerConfig};
+use std::process::ExitCode;
+use std::time::Duration;
 
 /// Run the echo server. This can be easily tested using `socat` as shown below:
 /// >> `socat - TCP4:localhost:8080`
-fn main() {
-    run_server("127.0.0.1:8080").expect("Failed to start server");
+///
+/// When built with the `tls` feature, `echo_server --tls cert.pem key.pem` serves TLS instead:
+/// >> `socat - OPENSSL:localhost:8080,verify=0`
+///
+/// Hitting Ctrl-C shuts the server down gracefully and prints the final stats.
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match run_server("127.0.0.1:8080", &args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => cli_error::report(&e),
+    }
 }
 
-fn run_server(addr: &str) -> io::Result<()> {
-    let listener = TcpListener::bind(addr)?;
-    loop {
-        let (mut stream, client_addr) = listener.accept()?;
-        println!("Accepted connection from client {}", client_addr);
-        let mut writer = stream.try_clone()?;
+fn run_server(addr: &str, args: &[String]) -> Result<(), CliError> {
+    let config = ServerConfig {
+        idle_timeout: Some(Duration::from_secs(300)),
+        ..ServerConfig::default()
+    };
+    let server = EchoServer::bind(addr, config)
+        .map_err(|e| CliError::io(format!("Couldn't listen on {}", addr), e))?;
+    let handle = server.shutdown_handle();
+    let stats = server.stats();
+    ctrlc::set_handler(move || handle.shutdown())
+        .map_err(|e| CliError::unavailable("Couldn't install the Ctrl-C handler", e))?;
 
-        thread::spawn(move || {
-            let bytes_copied = io::copy(&mut stream, &mut writer).expect("Error in client thread");
-            println!(
-                "Transferred total {} bytes back to the client",
-                bytes_copied
-            );
-        });
+    let failed = |e| CliError::io("The server failed", e);
+    match args {
+        [] => server.run().map_err(failed)?,
+        #[cfg(feature = "tls")]
+        [flag, cert, key] if flag == "--tls" => {
+            use programming_rust::tls;
+            let load = |e| CliError::io("Couldn't set up TLS", e);
+            let certs = tls::load_certs(cert).map_err(load)?;
+            let key = tls::load_private_key(key).map_err(load)?;
+            let tls = tls::server_config(certs, key).map_err(load)?;
+            server.run_tls(tls).map_err(failed)?
+        }
+        _ => {
+            return Err(CliError::Usage(
+                "Usage: echo_server [--tls <cert.pem> <key.pem>]".to_string(),
+            ))
+        }
     }
+    println!("Server stopped with stats {:?}", stats.snapshot());
+    Ok(())
 }
@@ -0,0 +1,71 @@
+use programming_rust::cli_error::{self, CliError};
+use programming_rust::image::ImageError;
+use programming_rust::mandelbrot::{self, parse_pair, Region};
+use std::process::ExitCode;
+use std::time::Instant;
+
+const USAGE: &str = "Usage: mandelbrot [--size <width>x<height>] \
+[--region <re>,<im>:<re>,<im>] [--limit <iterations>] [--threads <n>] <output.ppm>";
+
+/// Render the Mandelbrot set to a PPM file, or to a PNG when built with the `png` feature:
+/// >> `mandelbrot --size 4000x3000 --region -1.20,0.35:-1,0.20 mandel.ppm`
+///
+/// The region is given as its upper left and lower right corners in the complex plane.
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => cli_error::report(&e),
+    }
+}
+
+fn run(args: &[String]) -> Result<(), CliError> {
+    let usage = |reason: &str| CliError::Usage(format!("{}\n{}", reason, USAGE));
+
+    let mut size = (1000, 750);
+    let mut region = Region::default();
+    let mut limit = 255;
+    let mut threads = std::thread::available_parallelism().map_or(4, |n| n.get());
+    let mut output = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| usage(&format!("{} needs a value", arg)))
+        };
+        match arg.as_str() {
+            "--size" => size = parse_pair(value()?, 'x').ok_or_else(|| usage("Bad size"))?,
+            "--region" => region = value()?.parse().map_err(|e| usage(&format!("{}", e)))?,
+            "--limit" => limit = value()?.parse().map_err(|_| usage("Bad limit"))?,
+            "--threads" => threads = value()?.parse().map_err(|_| usage("Bad thread count"))?,
+            _ if output.is_none() && !arg.starts_with("--") => output = Some(arg),
+            _ => return Err(usage(&format!("Unexpected argument {}", arg))),
+        }
+    }
+    let output = output.ok_or_else(|| usage("No output file"))?;
+
+    let started = Instant::now();
+    let escapes = mandelbrot::render(&region, size.0, size.1, limit, threads);
+    let image = mandelbrot::colorize(&escapes, limit);
+    println!(
+        "Rendered {}x{} on {} threads in {:?}",
+        size.0,
+        size.1,
+        threads,
+        started.elapsed()
+    );
+
+    #[cfg(feature = "png")]
+    if output.ends_with(".png") {
+        return image.save_png(output).map_err(|e| save_failed(output, e));
+    }
+    image.save_pnm(output).map_err(|e| save_failed(output, e))
+}
+
+fn save_failed(output: &str, error: ImageError) -> CliError {
+    let context = format!("Couldn't save {}", output);
+    match error {
+        ImageError::Io(e) => CliError::io(context, e),
+        e => CliError::data(context, e),
+    }
+}
@@ -1,10 +1,13 @@
+use programming_rust::prim_future::executor::{self, join_all, Executor};
+
+/// Fans the requests out over our own single-threaded executor rather than async-std's runtime
 fn main() {
     let urls = [
         "https://www.google.co.uk".to_string(),
         "https://www.bhalor.co.uk".to_string(),
         "https://sanjayts.net".to_string(),
     ];
-    let results = async_std::task::block_on(request_many(&urls));
+    let results = Executor::new().block_on(request_many(&urls));
     for (res, req) in results.iter().zip(urls.iter()) {
         println!("URL: {} and response: {:?}", req, res);
     }
@@ -16,13 +19,9 @@ async fn request_many(urls: &[String]) -> Vec<Result<String, surf::Error>> {
 
     for url in urls {
         let req = client.get(url).recv_string();
-        let handle = async_std::task::spawn(req);
+        let handle = executor::spawn(req);
         handles.push(handle);
     }
 
-    let mut results = vec![];
-    for handle in handles {
-        results.push(handle.await);
-    }
-    results
+    join_all(handles).await
 }
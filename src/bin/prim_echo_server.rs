@@ -0,0 +1,32 @@
+use programming_rust::prim_future::executor::{spawn, Executor};
+use programming_rust::prim_future::reactor::Async;
+use std::io;
+use std::net::{TcpListener, TcpStream};
+
+/// Run an echo server on the hand-rolled runtime: one thread, with the epoll reactor waking
+/// whichever connection has data. Try it out with:
+/// >> `socat - TCP4:localhost:8083`
+fn main() -> io::Result<()> {
+    Executor::new().block_on(async {
+        let listener = Async::<TcpListener>::bind("127.0.0.1:8083")?;
+        println!("Listening on {}", listener.local_addr()?);
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            spawn(async move {
+                if let Err(e) = echo(stream).await {
+                    eprintln!("Connection from {} failed: {}", peer, e);
+                }
+            });
+        }
+    })
+}
+
+async fn echo(stream: Async<TcpStream>) -> io::Result<()> {
+    let mut buf = vec![0_u8; 8 * 1024];
+    loop {
+        match stream.read(&mut buf).await? {
+            0 => return Ok(()),
+            len => stream.write_all(&buf[..len]).await?,
+        }
+    }
+}
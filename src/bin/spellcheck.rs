@@ -0,0 +1,131 @@
+use programming_rust::spellcheck::{self, normalize};
+use programming_rust::string_set::{StringSet, Trie};
+use std::fs::OpenOptions;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "Usage: spellcheck [--dict <words>] [--personal <words>] [--learn] \
+[--suggestions <n>] [file...]";
+
+struct Options {
+    dictionary: PathBuf,
+    personal: Option<PathBuf>,
+    learn: bool,
+    suggestions: usize,
+    files: Vec<String>,
+}
+
+/// Check files against a dictionary with one word per line, printing each unknown word as
+/// `file:line:col` along with the closest dictionary words:
+/// >> `spellcheck --dict /usr/share/dict/words --personal ~/.spelling README.md`
+///
+/// Words in the personal dictionary count as known too, and `--learn` adds every unknown word
+/// to it. With no files, standard input is checked. Exits with 1 if anything was unknown.
+fn main() -> ExitCode {
+    match parse_args(std::env::args().skip(1)).and_then(run) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("spellcheck: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> io::Result<Options> {
+    let usage = || io::Error::new(io::ErrorKind::InvalidInput, USAGE);
+    let mut options = Options {
+        dictionary: PathBuf::from("/usr/share/dict/words"),
+        personal: None,
+        learn: false,
+        suggestions: 3,
+        files: vec![],
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dict" => options.dictionary = args.next().ok_or_else(usage)?.into(),
+            "--personal" => options.personal = Some(args.next().ok_or_else(usage)?.into()),
+            "--learn" => options.learn = true,
+            "--suggestions" => {
+                let n = args.next().ok_or_else(usage)?;
+                options.suggestions = n.parse().map_err(|_| usage())?;
+            }
+            "-h" | "--help" => return Err(usage()),
+            _ => options.files.push(arg),
+        }
+    }
+    if options.learn && options.personal.is_none() {
+        return Err(usage());
+    }
+    Ok(options)
+}
+
+/// One normalized word per non-blank line
+fn load_words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(normalize)
+}
+
+/// Returns whether every word was known
+fn run(options: Options) -> io::Result<bool> {
+    let mut words = load_words(&std::fs::read_to_string(&options.dictionary)?).collect::<Vec<_>>();
+    if let Some(personal) = &options.personal {
+        // A personal dictionary which doesn't exist yet is just empty
+        match std::fs::read_to_string(personal) {
+            Ok(text) => words.extend(load_words(&text)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    let mut set = Trie::new();
+    for word in &words {
+        set.add(word);
+    }
+
+    let inputs = if options.files.is_empty() {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        vec![("<stdin>".to_string(), text)]
+    } else {
+        options
+            .files
+            .iter()
+            .map(|file| Ok((file.clone(), std::fs::read_to_string(file)?)))
+            .collect::<io::Result<Vec<_>>>()?
+    };
+
+    let mut learned = Vec::<String>::new();
+    let mut stdout = io::stdout().lock();
+    for (name, text) in &inputs {
+        for unknown in spellcheck::check(text, &set) {
+            let suggestions = spellcheck::suggest(&unknown.word, &words, options.suggestions);
+            write!(
+                stdout,
+                "{}:{}:{}: {}",
+                name, unknown.line, unknown.column, unknown.word
+            )?;
+            if !suggestions.is_empty() {
+                write!(stdout, " (did you mean {}?)", suggestions.join(", "))?;
+            }
+            writeln!(stdout)?;
+            let word = normalize(&unknown.word);
+            if !learned.contains(&word) {
+                learned.push(word);
+            }
+        }
+    }
+
+    if let (true, Some(personal)) = (options.learn, &options.personal) {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(personal)?;
+        for word in &learned {
+            writeln!(file, "{}", word)?;
+        }
+    }
+    Ok(learned.is_empty())
+}
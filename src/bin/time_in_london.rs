@@ -1,12 +1,28 @@
+use programming_rust::cli_error::{self, CliError};
 use serde_json::Value;
+use std::process::ExitCode;
 
 const URL: &str = "http://www.worldtimeapi.org/api/timezone/Europe/London";
 
-fn main() {
-    let result = reqwest::blocking::get(URL);
-    let response = result.unwrap();
-    let text = response.text().unwrap();
-    let text = text.as_str();
-    let json: Value = serde_json::from_str(text).unwrap();
-    println!("Time in London now is {}", json["datetime"]);
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => cli_error::report(&e),
+    }
+}
+
+fn run() -> Result<(), CliError> {
+    let fetch_failed = |e| CliError::unavailable(format!("Couldn't fetch {}", URL), e);
+    let response = reqwest::blocking::get(URL)
+        .and_then(|response| response.error_for_status())
+        .map_err(fetch_failed)?;
+    let text = response.text().map_err(fetch_failed)?;
+    let json: Value = serde_json::from_str(&text)
+        .map_err(|e| CliError::data("The time service didn't send JSON", e))?;
+    let datetime = json["datetime"].as_str().ok_or_else(|| CliError::Data {
+        context: "The time service's reply has no datetime".to_string(),
+        source: None,
+    })?;
+    println!("Time in London now is {}", datetime);
+    Ok(())
 }
@@ -1,10 +1,8 @@
 extern crate core;
 
-use std::collections::HashSet;
+use crate::numeric;
+use crate::string_set::{find_unknown, StringSet, StringSetHash};
 use std::fmt::Debug;
-use std::fs::File;
-use std::io;
-use std::io::{stdout, BufWriter, Write};
 use std::ops::{Add, Mul};
 
 #[test]
@@ -18,34 +16,41 @@ fn test_dot_product() {
     assert_eq!(dot(v1.as_slice(), v2.as_slice()), 32);
 }
 
-fn dot<T>(v1: &[T], v2: &[T]) -> T
-where
-    T: Add<Output = T> + Mul<Output = T> + Default + Copy,
-{
-    let mut sum = T::default();
+pub(crate) fn dot<T: numeric::Num>(v1: &[T], v2: &[T]) -> T {
+    let mut sum = T::ZERO;
     for idx in 0..v1.len() {
         sum = sum + (v1[idx] * v2[idx]);
     }
     sum
 }
 
+#[test]
+fn test_checked_dot() {
+    assert_eq!(checked_dot(&[1, 2, 3], &[4, 5, 6]), Some(32));
+    assert_eq!(checked_dot(&[1, 2, 3], &[4, 5]), None);
+}
+
+/// Like `dot`, but `None` when the lengths differ instead of quietly using the first one's
+pub(crate) fn checked_dot<T: numeric::Num>(v1: &[T], v2: &[T]) -> Option<T> {
+    if v1.len() != v2.len() {
+        return None;
+    }
+    Some(dot(v1, v2))
+}
+
 #[test]
 fn test_add() {
     let out = fib::<f64>(4);
     assert_eq!(out, 3.0);
 }
 
-trait MyFloat {
-    const ZERO: Self;
-    const ONE: Self;
-}
-
-impl MyFloat for f64 {
-    const ZERO: Self = 0.0;
-    const ONE: Self = 1.0;
+#[test]
+fn test_fib_is_generic() {
+    assert_eq!(fib::<u8>(10), 55);
+    assert_eq!(fib::<f32>(10), 55.0);
 }
 
-fn fib<T: MyFloat + Add<Output = T>>(n: usize) -> T {
+fn fib<T: numeric::Num>(n: usize) -> T {
     match n {
         0 => T::ZERO,
         1 => T::ONE,
@@ -67,13 +72,6 @@ fn test_custom_mul() {
     assert_eq!(4 * a1, AppleBasket { count: 60 });
 }
 
-fn open(path: &str) -> io::Result<Box<dyn Write>> {
-    match path {
-        "-" => Ok(Box::new(BufWriter::new(stdout()))),
-        _ => Ok(Box::new(BufWriter::new(File::create(path)?))),
-    }
-}
-
 impl Mul for AppleBasket {
     type Output = Self;
 
@@ -110,6 +108,7 @@ struct AppleBasket {
 }
 
 #[test]
+#[allow(clippy::useless_vec)]
 fn test_dump() {
     let v = vec![Num(1), Num(2)];
     dump(v.iter());
@@ -135,63 +134,3 @@ where
         println!("Index={} and value={:?}", idx, val);
     }
 }
-
-fn find_unknown<'a, S: StringSet<'a>>(words: &[&'a str], set: &S) -> S {
-    let mut u = S::new();
-    for w in words {
-        if !set.contains(w) {
-            u.add(*w);
-        }
-    }
-    u
-}
-
-#[derive(Debug)]
-struct StringSetHash<'a> {
-    names: HashSet<&'a str>,
-}
-
-impl<'a> StringSet<'a> for StringSetHash<'a> {
-    fn new() -> Self {
-        StringSetHash {
-            names: HashSet::new(),
-        }
-    }
-
-    fn add(&mut self, name: &'a str) -> &mut Self {
-        self.names.insert(name);
-        self
-    }
-
-    fn contains(&self, name: &str) -> bool {
-        self.names.contains(name)
-    }
-}
-
-trait StringSet<'a> {
-    /// Create a new string set
-    fn new() -> Self;
-
-    /// Add a new item to the existing string set
-    fn add(&mut self, name: &'a str) -> &mut Self;
-
-    /// Check if teh string set contains a given string
-    fn contains(&self, name: &str) -> bool;
-}
-
-fn write<T: Write>(data: &str, writer: &mut T) -> io::Result<()> {
-    writer.write_all(data.as_bytes())
-}
-
-#[derive(Debug)]
-struct Sink;
-
-impl Write for Sink {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        Ok(buf.len())
-    }
-
-    fn flush(&mut self) -> io::Result<()> {
-        Ok(())
-    }
-}
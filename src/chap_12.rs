@@ -1,5 +1,7 @@
-use std::cmp::{Ordering, Reverse};
-use std::ops::{Add, AddAssign, Index, IndexMut, Neg};
+use crate::complex::Complex;
+use crate::image::Image;
+use crate::interval::Interval;
+use std::cmp::Reverse;
 
 #[test]
 fn test_index() {
@@ -13,41 +15,6 @@ fn test_index() {
     assert_eq!([0, 1, 0, 9, 0], slice);
 }
 
-struct Image<P> {
-    width: usize,
-    pixels: Vec<P>,
-}
-
-impl<P> Image<P>
-where
-    P: Copy + Default,
-{
-    fn new(height: usize, width: usize) -> Self {
-        Image {
-            width,
-            pixels: vec![P::default(); height * width],
-        }
-    }
-}
-
-impl<P> Index<usize> for Image<P> {
-    type Output = [P];
-
-    fn index(&self, index: usize) -> &Self::Output {
-        let start_pos = index * self.width;
-        let end_pos = start_pos + self.width;
-        &self.pixels[start_pos..end_pos]
-    }
-}
-
-impl<P> IndexMut<usize> for Image<P> {
-    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        let start_pos = index * self.width;
-        let end_pos = start_pos + self.width;
-        &mut self.pixels[start_pos..end_pos]
-    }
-}
-
 #[test]
 fn test_interval() {
     let t1 = Interval::new(5, 10);
@@ -61,39 +28,13 @@ fn test_interval() {
     assert!(t2 <= t2);
 
     let mut v = vec![t1, t2, t3, t4];
-    v.sort_by_key(|it| it.lower);
+    v.sort_by_key(|it| *it.lower());
     assert_eq!(v, vec![t3, t1, t2, t4]);
 
-    v.sort_unstable_by_key(|it| Reverse(it.lower));
+    v.sort_unstable_by_key(|it| Reverse(*it.lower()));
     assert_eq!(v, vec![t4, t2, t1, t3]);
 }
 
-#[derive(PartialEq, Debug, Clone, Copy)]
-struct Interval<T> {
-    lower: T,
-    upper: T,
-}
-
-impl<T> Interval<T> {
-    fn new(lower: T, upper: T) -> Interval<T> {
-        Interval { lower, upper }
-    }
-}
-
-impl<T: PartialOrd> PartialOrd for Interval<T> {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        if self == other {
-            Some(Ordering::Equal)
-        } else if self.lower > other.upper {
-            Some(Ordering::Greater)
-        } else if self.upper < other.lower {
-            Some(Ordering::Less)
-        } else {
-            None
-        }
-    }
-}
-
 #[test]
 fn test_complex() {
     let c1 = Complex { re: 1, im: 4 };
@@ -113,49 +54,12 @@ fn test_complex() {
     assert_eq!(c1, Complex { re: 5.7, im: 6.5 });
 }
 
-#[derive(Debug)]
-struct Complex<T> {
-    re: T,
-    im: T,
-}
-
-impl<T: PartialEq> PartialEq for Complex<T> {
-    fn eq(&self, other: &Self) -> bool {
-        self.re == other.re && self.im == other.im
-    }
-}
-
-impl<T: AddAssign> AddAssign for Complex<T> {
-    fn add_assign(&mut self, rhs: Self) {
-        self.im += rhs.im;
-        self.re += rhs.re;
-    }
-}
-
-impl<T> Neg for Complex<T>
-where
-    T: Neg<Output = T>,
-{
-    type Output = Self;
-
-    fn neg(self) -> Self::Output {
-        Complex {
-            re: -self.re,
-            im: -self.im,
-        }
-    }
-}
-
-impl<T> Add for Complex<T>
-where
-    T: Add<Output = T>,
-{
-    type Output = Self;
-
-    fn add(self, rhs: Self) -> Self::Output {
-        Complex {
-            re: self.re + rhs.re,
-            im: self.im + rhs.im,
-        }
-    }
+#[test]
+fn test_complex_num() {
+    let i = Complex::<i32>::i();
+    assert_eq!(i * Complex::i(), Complex { re: -1, im: 0 });
+    let c = Complex { re: 3, im: 4 } * Complex::one();
+    assert_eq!(c.norm_sqr(), 25);
+    assert_eq!(Complex { re: 3.0, im: 4.0 }.norm(), 5.0);
+    assert_eq!(Complex::<u8>::zero(), Complex { re: 0, im: 0 });
 }
@@ -1,3 +1,4 @@
+use crate::cli_error::CliError;
 use std::borrow::Cow;
 use std::collections::HashSet;
 
@@ -14,28 +15,18 @@ fn test_cow() {
     assert_eq!(msg, "Invalid size 23");
 
     let mut errors: Vec<String> = vec![];
-    let msg = describe(&CliError::NonExistentFile("a.txt"));
+    let msg = describe(&CliError::NonExistentFile("a.txt".into()));
     errors.push(msg.into_owned());
     assert_eq!(errors, vec!["Non-existent file a.txt".to_string()]);
 }
 
+/// The wording belongs to `CliError`'s `Display` impl, so this only wraps it up as a `Cow`
 fn describe(e: &CliError) -> Cow<'static, str> {
-    match *e {
-        CliError::TooFewArguments => "Too Few Arguments".into(),
-        CliError::TooManyArguments => "Too many arguments".into(),
-        CliError::InvalidSize(sz) => format!("Invalid size {}", sz).into(),
-        CliError::NonExistentFile(file) => format!("Non-existent file {}", file).into(),
-    }
-}
-
-enum CliError<'a> {
-    TooFewArguments,
-    TooManyArguments,
-    InvalidSize(usize),
-    NonExistentFile(&'a str),
+    e.to_string().into()
 }
 
 #[test]
+#[allow(clippy::inconsistent_digit_grouping)]
 fn test_from_into() {
     let s = "HI";
     let u: String = From::from(s);
@@ -96,6 +87,7 @@ impl AsRef<Vec<u8>> for MyBuffer {
 }
 
 #[test]
+#[allow(clippy::useless_vec)]
 fn test_default() {
     let numbers = vec![1, 2, 3, 4, 5, 6, 7, 8];
     let (p1, p2): (HashSet<i32>, HashSet<i32>) = numbers.iter().partition(|&n| n & (n - 1) == 0);
@@ -52,7 +52,7 @@ enum BinaryTree<T> {
 }
 
 impl<T: Ord> BinaryTree<T> {
-    fn iter(&self) -> TreeIter<T> {
+    fn iter(&self) -> TreeIter<'_, T> {
         let mut iter = TreeIter {
             unvisited: Vec::new(),
         };
@@ -112,6 +112,7 @@ impl Iterator for I32Range {
 }
 
 #[test]
+#[allow(clippy::string_extend_chars)]
 fn test_extend() {
     let mut s = "HI ".to_string();
     s.extend("HELLO".chars());
@@ -119,6 +120,7 @@ fn test_extend() {
 }
 
 #[test]
+#[allow(clippy::iter_cloned_collect)]
 fn test_from_iter() {
     let v = vec![1, 2, 3];
 
@@ -161,6 +163,7 @@ fn test_find() {
 }
 
 #[test]
+#[allow(clippy::double_ended_iterator_last, clippy::useless_vec)]
 fn test_last() {
     let squares = (1..11).map(|n| n * n);
     assert_eq!(squares.last(), Some(100));
@@ -176,6 +179,7 @@ fn test_last() {
 }
 
 #[test]
+#[allow(clippy::iter_nth_zero, clippy::useless_vec)]
 fn test_nth() {
     let v = vec![1, 2, 3, 4, 5];
     let mut iter = v.iter();
@@ -190,6 +194,7 @@ fn test_nth() {
 }
 
 #[test]
+#[allow(clippy::useless_vec)]
 fn test_try_folds() {
     let values = vec!["1", "2", "3", "abcd", "100"];
     let res = values
@@ -205,6 +210,7 @@ fn test_try_folds() {
 }
 
 #[test]
+#[allow(clippy::unnecessary_fold, clippy::useless_vec)]
 fn test_folds() {
     let v = vec![1, 2, 3, 4, 5];
 
@@ -218,6 +224,7 @@ fn test_folds() {
 }
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn test_any_all() {
     let s = "Hello guys!";
 
@@ -239,6 +246,7 @@ fn test_lt_gt_eq() {
 
 #[test]
 #[should_panic]
+#[allow(clippy::useless_vec)]
 fn test_min_max_by() {
     let v = vec![1.2, 23.2, 1232.2, -12.1];
 
@@ -269,6 +277,7 @@ fn test_min_max() {
 }
 
 #[test]
+#[allow(clippy::useless_conversion)]
 fn test_sum_product() {
     assert_eq!((1..100).into_iter().sum::<u64>(), 4950);
 
@@ -276,6 +285,7 @@ fn test_sum_product() {
 }
 
 #[test]
+#[allow(clippy::manual_repeat_n)]
 fn test_fizzbuzz() {
     let fizzes = std::iter::repeat("").take(2).chain(once("fizz")).cycle();
     let buzzes = std::iter::repeat("").take(4).chain(once("buzz")).cycle();
@@ -296,6 +306,7 @@ fn test_fizzbuzz() {
 }
 
 #[test]
+#[allow(clippy::useless_vec)]
 fn test_cycle() {
     let v = vec!["yes", "no"];
     let mut vicious_cycle = v.iter().cycle();
@@ -306,6 +317,7 @@ fn test_cycle() {
 }
 
 #[test]
+#[allow(clippy::iter_next_slice, clippy::useless_vec)]
 fn test_cloned() {
     let v = vec![1, 2, 3, 4];
 
@@ -368,6 +380,7 @@ fn test_inspect() {
 }
 
 #[test]
+#[allow(clippy::useless_vec)]
 fn test_rev() {
     let parts = vec!["head", "shoulders", "knees", "toes"];
     let mut iter = parts.iter();
@@ -504,6 +517,7 @@ fn test_flatten() {
 }
 
 #[test]
+#[allow(clippy::needless_borrow)]
 fn test_flat_map() {
     let mut cities = HashMap::new();
     cities.insert("Japan", vec!["Tokyo", "Kyoto"]);
@@ -593,7 +607,7 @@ fn test_successors() {
     assert_eq!(complex_successors(c1, 2000), None);
 }
 
-fn complex_successors(c: Complex<f64>, limit: usize) -> Option<usize> {
+pub(crate) fn complex_successors(c: Complex<f64>, limit: usize) -> Option<usize> {
     let zero = Complex::zero();
     std::iter::successors(Some(zero), |&z: &Complex<f64>| Some(z * z + c))
         .take(limit)
@@ -632,6 +646,7 @@ where
 }
 
 #[test]
+#[allow(clippy::into_iter_on_ref)]
 fn test_btreeset_iter() {
     let mut bts = BTreeSet::new();
     bts.insert("Wheel Of Time");
@@ -670,6 +685,7 @@ fn test_path_iter() {
 }
 
 #[test]
+#[allow(clippy::into_iter_on_ref, clippy::while_let_on_iterator)]
 fn test_vec_into_iter() {
     let v = vec!["abba", "dabba", "jabba"];
     let mut iter = (&v).into_iter();
@@ -684,6 +700,7 @@ fn test_triangle_num() {
     assert_eq!(6, triangle_number_fold(3));
 }
 
+#[allow(clippy::unnecessary_fold)]
 fn triangle_number_fold(n: u32) -> u32 {
     (1..=n).fold(0, |sum, n| sum + n)
 }
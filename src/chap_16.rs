@@ -2,6 +2,7 @@ use std::cmp::Reverse;
 use std::collections::{BTreeMap, BinaryHeap, HashSet, VecDeque};
 
 #[test]
+#[allow(clippy::useless_vec)]
 fn test_first() {
     let v = vec![1, 2, 3, 4];
     assert_eq!(v.first(), Some(&1));
@@ -15,6 +15,7 @@ fn test_string_collect() {
 }
 
 #[test]
+#[allow(clippy::write_literal)]
 fn test_write() {
     let mut s = String::new();
 
@@ -35,6 +36,7 @@ fn test_drain() {
 }
 
 #[test]
+#[allow(clippy::manual_pattern_char_comparison)]
 fn test_replace() {
     let s = "Dungeons and Dragons".to_string();
     let s = s.replace('D', "G");
@@ -63,6 +65,7 @@ fn test_trim() {
 }
 
 #[test]
+#[allow(clippy::unnecessary_cast)]
 fn test_from_string() {
     assert_eq!(usize::from_str("123"), Ok(123 as usize));
     assert_eq!(f64::from_str("123"), Ok(123_f64));
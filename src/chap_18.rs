@@ -84,6 +84,7 @@ fn test_subprocess() {
 }
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn test_path_ops() {
     let p = Path::new("src/lib.rs/");
 
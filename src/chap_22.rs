@@ -91,6 +91,7 @@ fn test_json_null() {
 }
 
 #[test]
+#[allow(clippy::clone_on_copy, clippy::useless_vec)]
 fn test_json_array() {
     let array = json!([1, 2, 3]);
     assert_eq!(
@@ -117,6 +118,7 @@ fn test_json_object() {
 }
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn test_matches() {
     let closure = || 1200;
     assert!(my_matches!(closure(), 1200));
@@ -94,12 +94,12 @@ impl<T> GapBuffer<T> {
 
     /// Retrieve the const pointer to a 'index'th element inside the buffer
     unsafe fn ptr_for(&self, index: usize) -> *const T {
-        self.storage.as_ptr().offset(index as isize)
+        self.storage.as_ptr().add(index)
     }
 
     /// Retrieve the mut pointer to a 'index'th element inside the buffer
     unsafe fn mut_ptr_for(&mut self, index: usize) -> *mut T {
-        self.storage.as_mut_ptr().offset(index as isize)
+        self.storage.as_mut_ptr().add(index)
     }
 
     /// Retrieve the offset to a given 'index'th element inside the buffer taking into
@@ -185,7 +185,7 @@ impl<T> GapBuffer<T> {
         unsafe {
             std::ptr::copy_nonoverlapping(self.ptr_for(0), new.as_mut_ptr(), self.gap.start);
 
-            let last_seg_dst = new.as_mut_ptr().offset(new_gap.end as isize);
+            let last_seg_dst = new.as_mut_ptr().add(new_gap.end);
             std::ptr::copy_nonoverlapping(
                 self.ptr_for(self.gap.end),
                 new.as_mut_ptr(),
@@ -262,6 +262,7 @@ fn test_size_align() {
 }
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn test_ref_with_flag() {
     let v = vec![1, 2, 3, 4];
     let ref_wflag = RefWithFlag::new(&v, true);
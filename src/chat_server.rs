@@ -0,0 +1,744 @@
+use crate::echo_server::{Close, Connection, EchoServer, Received, ServerConfig, ShutdownHandle};
+#[cfg(test)]
+use async_std::channel::unbounded;
+use async_std::channel::{bounded, Receiver, Sender, TrySendError};
+use async_std::io::{ReadExt, WriteExt};
+use async_std::net::{TcpListener, TcpStream};
+use async_std::task;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use std::io::{self, ErrorKind::ConnectionAborted, ErrorKind::Interrupted};
+use std::net::{Shutdown, SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Longest line (in bytes, excluding the terminator) a client may send
+pub const DEFAULT_MAX_LINE: usize = 1024;
+
+/// Messages queued up for a client before it's considered too slow to keep up and is hung up on
+pub const OUTBOX_CAPACITY: usize = 256;
+
+/// Clients start out as `guest-<id>`, so nobody may pick a nickname like that themselves
+const GUEST_PREFIX: &str = "guest-";
+
+#[test]
+fn test_parse_command() {
+    assert_eq!(parse_command("/join rust"), Ok(Command::Join("rust")));
+    assert_eq!(parse_command("/nick  Rand "), Ok(Command::Nick("Rand")));
+    assert_eq!(parse_command("/list"), Ok(Command::List));
+    assert_eq!(parse_command("/leave"), Ok(Command::Leave));
+    assert_eq!(parse_command("hello all"), Ok(Command::Say("hello all")));
+    assert_eq!(
+        parse_command("//not a command"),
+        Ok(Command::Say("/not a command"))
+    );
+
+    assert_eq!(
+        parse_command("/join"),
+        Err(ProtocolError::MissingArgument("/join"))
+    );
+    assert_eq!(
+        parse_command("/join a b"),
+        Err(ProtocolError::BadName("a b".to_string()))
+    );
+    assert_eq!(
+        parse_command("/nick guest-7"),
+        Err(ProtocolError::ReservedName("guest-7".to_string()))
+    );
+    assert_eq!(
+        parse_command("/dance"),
+        Err(ProtocolError::UnknownCommand("/dance".to_string()))
+    );
+    assert_eq!(parse_command(""), Err(ProtocolError::Empty));
+}
+
+#[test]
+fn test_line_framer() {
+    let mut framer = LineFramer::new(8);
+    framer.push(b"hi\r\nthe");
+    assert_eq!(framer.next_line(), Some(Ok("hi".to_string())));
+    assert_eq!(framer.next_line(), None);
+
+    framer.push(b"re\n");
+    assert_eq!(framer.next_line(), Some(Ok("there".to_string())));
+
+    // Overlong lines are reported once and then skipped up to the next terminator
+    framer.push(b"0123456789");
+    assert_eq!(framer.next_line(), Some(Err(ProtocolError::LineTooLong(8))));
+    framer.push(b"abc\nok\n");
+    assert_eq!(framer.next_line(), Some(Ok("ok".to_string())));
+    assert_eq!(framer.next_line(), None);
+
+    framer.push(b"12345678\r");
+    assert_eq!(framer.next_line(), None);
+    framer.push(b"\n");
+    assert_eq!(framer.next_line(), Some(Ok("12345678".to_string())));
+    framer.push(b"12345678\rx");
+    assert_eq!(framer.next_line(), Some(Err(ProtocolError::LineTooLong(8))));
+    framer.push(b"\n");
+    assert_eq!(framer.next_line(), None);
+
+    framer.push(b"\xff\n");
+    assert_eq!(framer.next_line(), Some(Err(ProtocolError::InvalidUtf8)));
+}
+
+#[test]
+fn test_hub() {
+    let hub = Hub::new();
+    let (a_tx, a_rx) = unbounded();
+    let (b_tx, b_rx) = unbounded();
+    hub.connect(1, a_tx);
+    hub.connect(2, b_tx);
+    let drain = |rx: &Receiver<String>| {
+        let mut lines = vec![];
+        while let Ok(line) = rx.try_recv() {
+            lines.push(line);
+        }
+        lines
+    };
+    drain(&a_rx);
+    drain(&b_rx);
+
+    hub.handle_line(1, "/nick rand");
+    hub.handle_line(1, "/join tv");
+    hub.handle_line(2, "/join tv");
+    hub.handle_line(2, "hello");
+    assert_eq!(
+        drain(&a_rx),
+        vec![
+            "* you are now rand",
+            "* you joined #tv",
+            "* guest-2 joined #tv",
+            "[tv] guest-2: hello"
+        ]
+    );
+    assert_eq!(
+        drain(&b_rx),
+        vec!["* you joined #tv", "[tv] guest-2: hello"]
+    );
+
+    hub.handle_line(2, "/nick rand");
+    assert_eq!(drain(&b_rx), vec!["! nickname rand is taken"]);
+
+    hub.handle_line(2, "/join books");
+    hub.handle_line(1, "/list");
+    assert_eq!(
+        drain(&a_rx),
+        vec!["* guest-2 left #tv", "* rooms: books(1), tv(1)"]
+    );
+
+    hub.handle_line(1, "/leave");
+    hub.handle_line(1, "anyone?");
+    assert_eq!(
+        drain(&a_rx),
+        vec!["* you left #tv", "! join a room first with /join <room>"]
+    );
+
+    hub.disconnect(2);
+    hub.handle_line(1, "/list");
+    assert_eq!(drain(&a_rx), vec!["* no rooms yet"]);
+}
+
+#[test]
+fn test_lagging_client() {
+    let hub = Hub::new();
+    let (fast_tx, fast_rx) = unbounded();
+    let (slow_tx, slow_rx) = bounded(3);
+    hub.connect(1, fast_tx);
+    hub.connect(2, slow_tx);
+    hub.handle_line(1, "/join tv");
+    hub.handle_line(2, "/join tv");
+    hub.handle_line(1, "one");
+    assert!(!slow_rx.is_closed());
+
+    // The slow client hasn't read anything, so the next message is one too many
+    hub.handle_line(1, "two");
+    assert!(slow_rx.is_closed());
+    assert_eq!(slow_rx.len(), 3);
+    assert_eq!(fast_rx.len(), 5);
+
+    // Everyone else still hears about it leaving once its connection is gone
+    hub.disconnect(2);
+    assert_eq!(fast_rx.len(), 6);
+}
+
+#[test]
+fn test_threaded_chat() {
+    let server = bind("127.0.0.1:0", ServerConfig::default()).unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = server.shutdown_handle();
+    let runner = thread::spawn(move || run(server, DEFAULT_MAX_LINE));
+
+    exercise_server(addr);
+
+    handle.shutdown();
+    runner.join().unwrap().unwrap();
+}
+
+#[test]
+fn test_async_chat() {
+    let server = task::block_on(AsyncChatServer::bind("127.0.0.1:0", DEFAULT_MAX_LINE, 8)).unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = server.shutdown_handle();
+    let runner = task::spawn(server.run());
+
+    exercise_server(addr);
+
+    handle.shutdown();
+    task::block_on(runner).unwrap();
+}
+
+#[test]
+fn test_async_connection_limit() {
+    use std::io::{BufRead, BufReader, Read};
+
+    let server = task::block_on(AsyncChatServer::bind("127.0.0.1:0", DEFAULT_MAX_LINE, 1)).unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = server.shutdown_handle();
+    let runner = task::spawn(server.run());
+
+    let first = std::net::TcpStream::connect(addr).unwrap();
+    let mut greeting = String::new();
+    BufReader::new(&first).read_line(&mut greeting).unwrap();
+    assert!(greeting.starts_with("* welcome guest-"));
+
+    // The server is full, so the second client is hung up on without a greeting
+    let mut second = std::net::TcpStream::connect(addr).unwrap();
+    let mut reply = vec![];
+    second.read_to_end(&mut reply).unwrap();
+    assert!(reply.is_empty());
+
+    handle.shutdown();
+    task::block_on(runner).unwrap();
+}
+
+#[cfg(test)]
+fn exercise_server(addr: SocketAddr) {
+    use std::io::{BufRead, BufReader, Write};
+
+    let connect = || {
+        let stream = std::net::TcpStream::connect(addr).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut greeting = String::new();
+        reader.read_line(&mut greeting).unwrap();
+        assert!(greeting.starts_with("* welcome guest-"));
+        (stream, reader)
+    };
+    let read_line = |reader: &mut BufReader<std::net::TcpStream>| {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        line.trim_end().to_string()
+    };
+
+    let (mut alice, mut alice_in) = connect();
+    let (mut bob, mut bob_in) = connect();
+
+    alice.write_all(b"/nick alice\n/join lobby\n").unwrap();
+    assert_eq!(read_line(&mut alice_in), "* you are now alice");
+    assert_eq!(read_line(&mut alice_in), "* you joined #lobby");
+
+    bob.write_all(b"/nick bob\r\n/join lobby\r\n").unwrap();
+    assert_eq!(read_line(&mut bob_in), "* you are now bob");
+    assert_eq!(read_line(&mut bob_in), "* you joined #lobby");
+    assert_eq!(read_line(&mut alice_in), "* bob joined #lobby");
+
+    bob.write_all(b"hey alice\n").unwrap();
+    assert_eq!(read_line(&mut alice_in), "[lobby] bob: hey alice");
+    assert_eq!(read_line(&mut bob_in), "[lobby] bob: hey alice");
+
+    let long_line = vec![b'x'; DEFAULT_MAX_LINE + 1];
+    alice.write_all(&long_line).unwrap();
+    alice.write_all(b"\n/list\n").unwrap();
+    assert_eq!(
+        read_line(&mut alice_in),
+        format!("! line longer than {} bytes", DEFAULT_MAX_LINE)
+    );
+    assert_eq!(read_line(&mut alice_in), "* rooms: lobby(2)");
+
+    drop(bob);
+    drop(bob_in);
+    assert_eq!(read_line(&mut alice_in), "* bob left #lobby");
+}
+
+/// A single line sent by a chat client
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command<'a> {
+    Join(&'a str),
+    Nick(&'a str),
+    List,
+    Leave,
+    Say(&'a str),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ProtocolError {
+    Empty,
+    LineTooLong(usize),
+    InvalidUtf8,
+    UnknownCommand(String),
+    MissingArgument(&'static str),
+    BadName(String),
+    ReservedName(String),
+}
+
+impl Display for ProtocolError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProtocolError::Empty => write!(f, "empty line"),
+            ProtocolError::LineTooLong(max) => write!(f, "line longer than {} bytes", max),
+            ProtocolError::InvalidUtf8 => write!(f, "line is not valid UTF-8"),
+            ProtocolError::UnknownCommand(cmd) => write!(f, "unknown command {}", cmd),
+            ProtocolError::MissingArgument(cmd) => write!(f, "{} needs an argument", cmd),
+            ProtocolError::BadName(name) => write!(f, "invalid name `{}`", name),
+            ProtocolError::ReservedName(name) => write!(f, "nickname `{}` is reserved", name),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+/// Parse a line (without its terminator) into a command. Lines starting with `/` are commands;
+/// a leading `//` escapes a message which itself starts with a slash.
+pub fn parse_command(line: &str) -> Result<Command<'_>, ProtocolError> {
+    if line.is_empty() {
+        return Err(ProtocolError::Empty);
+    }
+    if line.starts_with("//") {
+        return Ok(Command::Say(&line[1..]));
+    }
+    if !line.starts_with('/') {
+        return Ok(Command::Say(line));
+    }
+
+    let (cmd, arg) = match line.split_once(char::is_whitespace) {
+        Some((cmd, arg)) => (cmd, arg.trim()),
+        None => (line, ""),
+    };
+    match cmd {
+        "/join" => Ok(Command::Join(parse_name("/join", arg)?)),
+        "/nick" => match parse_name("/nick", arg)? {
+            nick if nick.starts_with(GUEST_PREFIX) => {
+                Err(ProtocolError::ReservedName(nick.to_string()))
+            }
+            nick => Ok(Command::Nick(nick)),
+        },
+        "/list" => Ok(Command::List),
+        "/leave" => Ok(Command::Leave),
+        _ => Err(ProtocolError::UnknownCommand(cmd.to_string())),
+    }
+}
+
+fn parse_name<'a>(cmd: &'static str, arg: &'a str) -> Result<&'a str, ProtocolError> {
+    if arg.is_empty() {
+        Err(ProtocolError::MissingArgument(cmd))
+    } else if arg.chars().any(char::is_whitespace) || arg.len() > 32 {
+        Err(ProtocolError::BadName(arg.to_string()))
+    } else {
+        Ok(arg)
+    }
+}
+
+/// Splits a byte stream into `\n` (or `\r\n`) terminated lines, refusing lines longer than a cap
+/// so that a client can't make the server buffer unbounded amounts of data
+pub struct LineFramer {
+    buffer: Vec<u8>,
+    max_line: usize,
+    discarding: bool,
+}
+
+impl LineFramer {
+    pub fn new(max_line: usize) -> Self {
+        LineFramer {
+            buffer: vec![],
+            max_line,
+            discarding: false,
+        }
+    }
+
+    /// Feed freshly read bytes into the framer
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Retrieve the next complete line, if there is one
+    pub fn next_line(&mut self) -> Option<Result<String, ProtocolError>> {
+        loop {
+            // A line of exactly `max_line` bytes may still be waiting on the `\n` after its `\r`
+            let pending_cr = usize::from(self.buffer.last() == Some(&b'\r'));
+            let end = match self.buffer.iter().position(|&b| b == b'\n') {
+                Some(end) => end,
+                None if self.discarding => {
+                    self.buffer.clear();
+                    return None;
+                }
+                None if self.buffer.len() > self.max_line + pending_cr => {
+                    self.buffer.clear();
+                    self.discarding = true;
+                    return Some(Err(ProtocolError::LineTooLong(self.max_line)));
+                }
+                None => return None,
+            };
+            let mut line = self.buffer.drain(..=end).collect::<Vec<_>>();
+            if self.discarding {
+                self.discarding = false;
+                continue;
+            }
+            line.pop();
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            if line.len() > self.max_line {
+                return Some(Err(ProtocolError::LineTooLong(self.max_line)));
+            }
+            return Some(String::from_utf8(line).map_err(|_| ProtocolError::InvalidUtf8));
+        }
+    }
+}
+
+struct Client {
+    nick: String,
+    room: Option<String>,
+    outbox: Sender<String>,
+}
+
+/// Room membership and message routing, shared by the threaded and async servers. Every client
+/// is identified by its connection id and is written to through its outbox channel.
+pub struct Hub {
+    clients: Mutex<HashMap<u64, Client>>,
+}
+
+impl Hub {
+    pub fn new() -> Self {
+        Hub {
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn connect(&self, id: u64, outbox: Sender<String>) {
+        let nick = format!("{}{}", GUEST_PREFIX, id);
+        let _ = outbox.try_send(format!(
+            "* welcome {}, use /join <room> to start chatting",
+            nick
+        ));
+        let client = Client {
+            nick,
+            room: None,
+            outbox,
+        };
+        self.clients
+            .lock()
+            .expect("Failed to lock")
+            .insert(id, client);
+    }
+
+    /// Drop the client, closing its outbox and telling its room that it left
+    pub fn disconnect(&self, id: u64) {
+        let mut clients = self.clients.lock().expect("Failed to lock");
+        if let Some(client) = clients.remove(&id) {
+            if let Some(room) = client.room {
+                broadcast(&clients, &room, format!("* {} left #{}", client.nick, room));
+            }
+        }
+    }
+
+    /// Act on a single framed line from the client
+    pub fn handle_line(&self, id: u64, line: &str) {
+        match parse_command(line) {
+            Ok(command) => self.handle(id, command),
+            Err(ProtocolError::Empty) => {}
+            Err(e) => self.reply(id, format!("! {}", e)),
+        }
+    }
+
+    /// Report a framing error back to the client
+    pub fn reject(&self, id: u64, error: ProtocolError) {
+        self.reply(id, format!("! {}", error));
+    }
+
+    fn handle(&self, id: u64, command: Command) {
+        let mut clients = self.clients.lock().expect("Failed to lock");
+        let (nick, current) = match clients.get(&id) {
+            Some(client) => (client.nick.clone(), client.room.clone()),
+            None => return,
+        };
+        match command {
+            Command::Join(room) => {
+                if current.as_deref() == Some(room) {
+                    return;
+                }
+                if let Some(old) = current {
+                    leave(&mut clients, id, &nick, &old);
+                }
+                broadcast(&clients, room, format!("* {} joined #{}", nick, room));
+                let client = clients.get_mut(&id).expect("Client vanished");
+                client.room = Some(room.to_string());
+                deliver(client, format!("* you joined #{}", room));
+            }
+            Command::Nick(new_nick) => {
+                if clients
+                    .iter()
+                    .any(|(&other, c)| other != id && c.nick == new_nick)
+                {
+                    send(&clients, id, format!("! nickname {} is taken", new_nick));
+                    return;
+                }
+                let client = clients.get_mut(&id).expect("Client vanished");
+                client.nick = new_nick.to_string();
+                deliver(client, format!("* you are now {}", new_nick));
+                if let Some(room) = current {
+                    let msg = format!("* {} is now known as {}", nick, new_nick);
+                    broadcast_except(&clients, &room, id, msg);
+                }
+            }
+            Command::List => {
+                let mut rooms = BTreeMap::new();
+                for room in clients.values().filter_map(|c| c.room.as_ref()) {
+                    *rooms.entry(room.as_str()).or_insert(0) += 1;
+                }
+                let msg = if rooms.is_empty() {
+                    "* no rooms yet".to_string()
+                } else {
+                    let listing = rooms
+                        .iter()
+                        .map(|(room, count)| format!("{}({})", room, count))
+                        .collect::<Vec<_>>();
+                    format!("* rooms: {}", listing.join(", "))
+                };
+                send(&clients, id, msg);
+            }
+            Command::Leave => match current {
+                Some(room) => {
+                    leave(&mut clients, id, &nick, &room);
+                    send(&clients, id, format!("* you left #{}", room));
+                }
+                None => send(&clients, id, "! you are not in a room".to_string()),
+            },
+            Command::Say(text) => match current {
+                Some(room) => broadcast(&clients, &room, format!("[{}] {}: {}", room, nick, text)),
+                None => send(
+                    &clients,
+                    id,
+                    "! join a room first with /join <room>".to_string(),
+                ),
+            },
+        }
+    }
+
+    fn reply(&self, id: u64, msg: String) {
+        send(&self.clients.lock().expect("Failed to lock"), id, msg);
+    }
+}
+
+impl Default for Hub {
+    fn default() -> Self {
+        Hub::new()
+    }
+}
+
+fn leave(clients: &mut HashMap<u64, Client>, id: u64, nick: &str, room: &str) {
+    if let Some(client) = clients.get_mut(&id) {
+        client.room = None;
+    }
+    broadcast(clients, room, format!("* {} left #{}", nick, room));
+}
+
+/// Queue `msg` for `client`. A full outbox means the client isn't reading fast enough to keep up,
+/// so rather than buffering without bound its outbox is closed, which makes its writer hang up.
+fn deliver(client: &Client, msg: String) {
+    if let Err(TrySendError::Full(_)) = client.outbox.try_send(msg) {
+        client.outbox.close();
+    }
+}
+
+fn send(clients: &HashMap<u64, Client>, id: u64, msg: String) {
+    if let Some(client) = clients.get(&id) {
+        deliver(client, msg);
+    }
+}
+
+fn broadcast(clients: &HashMap<u64, Client>, room: &str, msg: String) {
+    broadcast_except(clients, room, u64::MAX, msg)
+}
+
+fn broadcast_except(clients: &HashMap<u64, Client>, room: &str, skip: u64, msg: String) {
+    for (id, client) in clients {
+        if *id != skip && client.room.as_deref() == Some(room) {
+            deliver(client, msg.clone());
+        }
+    }
+}
+
+/// Bind a thread-per-connection chat server. Each chat client occupies a worker for as long as
+/// it stays connected, so whatever `config.workers` says, there are `config.max_connections`.
+pub fn bind<A: ToSocketAddrs>(addr: A, config: ServerConfig) -> io::Result<EchoServer> {
+    let config = ServerConfig {
+        workers: config.max_connections,
+        ..config
+    };
+    EchoServer::bind(addr, config)
+}
+
+/// Serve chat clients on `server` until it is shut down
+pub fn run(server: EchoServer, max_line: usize) -> io::Result<()> {
+    let hub = Arc::new(Hub::new());
+    server.run_with(move |conn| serve_threaded(conn, &hub, max_line))
+}
+
+fn serve_threaded(conn: &Connection, hub: &Hub, max_line: usize) -> io::Result<Close> {
+    let (outbox, inbox) = bounded::<String>(OUTBOX_CAPACITY);
+    hub.connect(conn.id(), outbox);
+
+    thread::scope(|scope| {
+        // Writes happen on their own thread so that a broadcast never waits on our reads
+        scope.spawn(|| {
+            while let Ok(msg) = futures_lite::future::block_on(inbox.recv()) {
+                if conn.send(format!("{}\n", msg).as_bytes()).is_err() {
+                    break;
+                }
+            }
+            // The outbox is closed once we've disconnected, or early if we fell behind, in which
+            // case this stops the reads too
+            let _ = conn.stream().shutdown(Shutdown::Both);
+        });
+
+        let mut framer = LineFramer::new(max_line);
+        let mut buf = [0_u8; 1024];
+        let result = loop {
+            match conn.receive(&mut buf) {
+                Ok(Received::Bytes(len)) => framer.push(&buf[..len]),
+                Ok(Received::Closed(reason)) => break Ok(reason),
+                Err(e) => break Err(e),
+            }
+            while let Some(line) = framer.next_line() {
+                match line {
+                    Ok(line) => hub.handle_line(conn.id(), &line),
+                    Err(e) => hub.reject(conn.id(), e),
+                }
+            }
+        };
+        // Dropping our outbox ends the writer thread
+        hub.disconnect(conn.id());
+        result
+    })
+}
+
+/// The async-std flavour of the chat server, speaking the same protocol through the same `Hub`
+pub struct AsyncChatServer {
+    listener: TcpListener,
+    max_line: usize,
+    max_connections: usize,
+    shutdown: ShutdownHandle,
+}
+
+impl AsyncChatServer {
+    pub async fn bind<A: async_std::net::ToSocketAddrs>(
+        addr: A,
+        max_line: usize,
+        max_connections: usize,
+    ) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let shutdown = ShutdownHandle::new(listener.local_addr()?);
+        Ok(AsyncChatServer {
+            listener,
+            max_line,
+            max_connections,
+            shutdown,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    /// Accept clients until shut down, serving each one on its own task. Clients beyond
+    /// `max_connections` are hung up on straight away. If accepting fails for good, the
+    /// connected clients are hung up on before the error is returned.
+    pub async fn run(self) -> io::Result<()> {
+        let hub = Arc::new(Hub::new());
+        let live = Arc::new(Mutex::new(HashMap::new()));
+        let mut next_id = 0_u64;
+        let result = loop {
+            let (stream, client_addr) = match self.listener.accept().await {
+                Ok(pair) => pair,
+                Err(ref e) if matches!(e.kind(), Interrupted | ConnectionAborted) => continue,
+                Err(e) => break Err(e),
+            };
+            if self.shutdown.is_shutdown() {
+                break Ok(());
+            }
+            let mut clients = live.lock().expect("Failed to lock");
+            if clients.len() >= self.max_connections {
+                println!("Rejecting connection from {} -- server full", client_addr);
+                continue;
+            }
+            println!("Accepted connection from client {}", client_addr);
+            next_id += 1;
+            clients.insert(next_id, stream.clone());
+            drop(clients);
+            task::spawn(serve_async(
+                stream,
+                next_id,
+                hub.clone(),
+                live.clone(),
+                self.max_line,
+            ));
+        };
+
+        // Hanging up on the remaining clients makes their tasks wind down on their own
+        for (_, stream) in live.lock().expect("Failed to lock").drain() {
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+        }
+        result
+    }
+}
+
+async fn serve_async(
+    stream: TcpStream,
+    id: u64,
+    hub: Arc<Hub>,
+    live: Arc<Mutex<HashMap<u64, TcpStream>>>,
+    max_line: usize,
+) {
+    let (outbox, inbox) = bounded::<String>(OUTBOX_CAPACITY);
+    hub.connect(id, outbox);
+
+    let writer = task::spawn(write_outbox(stream.clone(), inbox));
+    let result = read_lines(stream, id, &hub, max_line).await;
+    hub.disconnect(id);
+    let result = result.and(writer.await);
+    live.lock().expect("Failed to lock").remove(&id);
+    if let Err(e) = result {
+        eprintln!("Error in chat client {}: {}", id, e);
+    }
+}
+
+async fn read_lines(mut stream: TcpStream, id: u64, hub: &Hub, max_line: usize) -> io::Result<()> {
+    let mut framer = LineFramer::new(max_line);
+    let mut buf = [0_u8; 1024];
+    loop {
+        let len = stream.read(&mut buf).await?;
+        if len == 0 {
+            return Ok(());
+        }
+        framer.push(&buf[..len]);
+        while let Some(line) = framer.next_line() {
+            match line {
+                Ok(line) => hub.handle_line(id, &line),
+                Err(e) => hub.reject(id, e),
+            }
+        }
+    }
+}
+
+async fn write_outbox(mut stream: TcpStream, inbox: Receiver<String>) -> io::Result<()> {
+    while let Ok(msg) = inbox.recv().await {
+        stream.write_all(format!("{}\n", msg).as_bytes()).await?;
+    }
+    // Hang up in case the outbox was closed because the client fell behind
+    let _ = stream.shutdown(Shutdown::Both);
+    Ok(())
+}
@@ -0,0 +1,215 @@
+//! The `CliError` from `chap_13`, grown into something binaries can return from `main`. Errors
+//! keep whatever caused them, map onto the exit codes from BSD's `sysexits.h`, and `report`
+//! prints the whole chain of causes.
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[test]
+fn test_display_and_source() {
+    assert_eq!(CliError::InvalidSize(23).to_string(), "Invalid size 23");
+    assert!(CliError::TooFewArguments.source().is_none());
+
+    let missing = io::Error::new(io::ErrorKind::NotFound, "no such file");
+    let error = CliError::io("Couldn't read config.toml", missing);
+    assert_eq!(error.to_string(), "Couldn't read config.toml");
+    assert_eq!(error.source().unwrap().to_string(), "no such file");
+}
+
+#[test]
+fn test_exit_codes() {
+    assert_eq!(CliError::Usage("no".into()).exit_code(), Sysexit::Usage);
+    assert_eq!(
+        CliError::NonExistentFile("a.txt".into()).exit_code(),
+        Sysexit::NoInput
+    );
+    let refused = io::Error::from(io::ErrorKind::ConnectionRefused);
+    assert_eq!(
+        CliError::io("Couldn't connect", refused).exit_code(),
+        Sysexit::Unavailable
+    );
+    let parse = "x".parse::<i32>().unwrap_err();
+    assert_eq!(
+        CliError::data("Bad number", parse).exit_code(),
+        Sysexit::DataErr
+    );
+    assert_eq!(Sysexit::Usage as u8, 64);
+}
+
+#[test]
+fn test_render() {
+    let inner = io::Error::other("disk on fire");
+    let middle = CliError::io("Couldn't save", inner);
+    let outer = CliError::unavailable("Upload failed", middle);
+    assert_eq!(
+        render(&outer, false),
+        "error: Upload failed\n  caused by: Couldn't save\n  caused by: disk on fire\n"
+    );
+    let coloured = render(&CliError::TooManyArguments, true);
+    assert_eq!(coloured, "\x1b[1;31merror:\x1b[0m Too many arguments\n");
+}
+
+/// Exit codes from `sysexits.h`, for the failures a command line tool usually runs into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sysexit {
+    /// The command was used incorrectly
+    Usage = 64,
+    /// The input data was incorrect
+    DataErr = 65,
+    /// An input file didn't exist or wasn't readable
+    NoInput = 66,
+    /// A service or remote host is unavailable
+    Unavailable = 69,
+    /// An internal error
+    Software = 70,
+    /// An output file couldn't be created
+    CantCreat = 73,
+    /// An error while doing I/O
+    IoErr = 74,
+    /// A temporary failure; trying again later might work
+    TempFail = 75,
+    /// Not enough permission
+    NoPerm = 77,
+}
+
+impl From<Sysexit> for ExitCode {
+    fn from(code: Sysexit) -> Self {
+        ExitCode::from(code as u8)
+    }
+}
+
+pub type GenericError = Box<dyn Error + Send + Sync + 'static>;
+
+#[derive(Debug)]
+pub enum CliError {
+    TooFewArguments,
+    TooManyArguments,
+    InvalidSize(usize),
+    NonExistentFile(PathBuf),
+    /// Bad arguments, with a message which usually includes the usage
+    Usage(String),
+    Io {
+        context: String,
+        source: io::Error,
+    },
+    /// Input which couldn't be understood
+    Data {
+        context: String,
+        source: Option<GenericError>,
+    },
+    /// Something the tool depends on, usually over the network, isn't working
+    Unavailable {
+        context: String,
+        source: Option<GenericError>,
+    },
+}
+
+impl CliError {
+    pub fn io(context: impl Into<String>, source: io::Error) -> Self {
+        CliError::Io {
+            context: context.into(),
+            source,
+        }
+    }
+
+    pub fn data(context: impl Into<String>, source: impl Into<GenericError>) -> Self {
+        CliError::Data {
+            context: context.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    pub fn unavailable(context: impl Into<String>, source: impl Into<GenericError>) -> Self {
+        CliError::Unavailable {
+            context: context.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    /// I/O errors are sorted by their kind, since a missing file and a refused connection call
+    /// for different codes
+    pub fn exit_code(&self) -> Sysexit {
+        use io::ErrorKind::*;
+        match self {
+            CliError::TooFewArguments
+            | CliError::TooManyArguments
+            | CliError::InvalidSize(_)
+            | CliError::Usage(_) => Sysexit::Usage,
+            CliError::NonExistentFile(_) => Sysexit::NoInput,
+            CliError::Data { .. } => Sysexit::DataErr,
+            CliError::Unavailable { .. } => Sysexit::Unavailable,
+            CliError::Io { source, .. } => match source.kind() {
+                NotFound => Sysexit::NoInput,
+                PermissionDenied => Sysexit::NoPerm,
+                AlreadyExists => Sysexit::CantCreat,
+                InvalidInput => Sysexit::Usage,
+                InvalidData | UnexpectedEof => Sysexit::DataErr,
+                ConnectionRefused | ConnectionReset | ConnectionAborted | NotConnected
+                | AddrInUse | AddrNotAvailable => Sysexit::Unavailable,
+                TimedOut | WouldBlock | Interrupted => Sysexit::TempFail,
+                _ => Sysexit::IoErr,
+            },
+        }
+    }
+}
+
+/// Only this error's own message; use `render` or `report` for the causes too
+impl Display for CliError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::TooFewArguments => write!(f, "Too Few Arguments"),
+            CliError::TooManyArguments => write!(f, "Too many arguments"),
+            CliError::InvalidSize(size) => write!(f, "Invalid size {}", size),
+            CliError::NonExistentFile(file) => write!(f, "Non-existent file {}", file.display()),
+            CliError::Usage(message) => write!(f, "{}", message),
+            CliError::Io { context, .. }
+            | CliError::Data { context, .. }
+            | CliError::Unavailable { context, .. } => write!(f, "{}", context),
+        }
+    }
+}
+
+impl Error for CliError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CliError::Io { source, .. } => Some(source),
+            CliError::Data { source, .. } | CliError::Unavailable { source, .. } => {
+                source.as_deref().map(|e| e as &(dyn Error + 'static))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// An error followed by each of its causes on a line of its own, with the headings in colour
+/// if `colour` is set
+pub fn render(error: &dyn Error, colour: bool) -> String {
+    let paint = |code: &str, text: &str| {
+        if colour {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    };
+    let mut rendered = format!("{} {}\n", paint("1;31", "error:"), error);
+    let mut cause = error.source();
+    while let Some(error) = cause {
+        rendered += &format!("  {} {}\n", paint("33", "caused by:"), error);
+        cause = error.source();
+    }
+    rendered
+}
+
+/// Print `error` and its causes to stderr, and return the exit code to finish with. Colour is
+/// used when stderr is a terminal, unless `NO_COLOR` is set. It's deliberately stderr that gets
+/// checked rather than stdout, since that's where the report goes: `tool > out.txt` still gets a
+/// coloured error on the terminal, and `tool 2> errors.log` gets none in the log.
+pub fn report(error: &CliError) -> ExitCode {
+    let colour = io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+    // There's nowhere left to report a failure to write the report
+    let _ = io::stderr().write_all(render(error, colour).as_bytes());
+    error.exit_code().into()
+}
@@ -0,0 +1,499 @@
+//! The `Complex` type from `chap_12`, with the full set of operators, polar form, the
+//! exponential family and text conversion
+
+use crate::numeric::{Float, Num};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+#[cfg(test)]
+fn assert_close(a: Complex<f64>, b: Complex<f64>) {
+    assert!((a - b).norm() < 1e-9, "{} isn't close to {}", a, b);
+}
+
+#[test]
+fn test_arithmetic() {
+    let a = Complex::new(3, 4);
+    let b = Complex::new(1, -2);
+    assert_eq!(a - b, Complex::new(2, 6));
+    assert_eq!(a * b, Complex::new(11, -2));
+    assert_eq!(
+        Complex::new(11.0, -2.0) / Complex::new(1.0, -2.0),
+        Complex::new(3.0, 4.0)
+    );
+    assert_eq!(a.conj(), Complex::new(3, -4));
+
+    assert_eq!(a * 2, Complex::new(6, 8));
+    assert_eq!(2 * a, Complex::new(6, 8));
+    assert_eq!(a + 1, Complex::new(4, 4));
+    assert_eq!(1 - a, Complex::new(-2, -4));
+    assert_eq!(Complex::new(3.0, 4.0) / 2.0, Complex::new(1.5, 2.0));
+    assert_eq!(1.0 / Complex::new(0.0, 2.0), Complex::new(0.0, -0.5));
+
+    let mut c = a;
+    c -= b;
+    c *= Complex::i();
+    c /= Complex::new(0, 1);
+    c *= 3;
+    assert_eq!(c, Complex::new(6, 18));
+    // The last squaring would overflow if it happened
+    assert_eq!(Complex::new(2_i32, 0).powi(16), Complex::new(65536, 0));
+    assert_eq!(Complex::new(0_i32, 1).powi(0), Complex::one());
+    assert_eq!(
+        [a, b, c].into_iter().sum::<Complex<i32>>(),
+        Complex::new(10, 20)
+    );
+}
+
+#[test]
+fn test_polar_and_exp() {
+    let z = Complex::new(3.0, 4.0);
+    assert_eq!(z.norm(), 5.0);
+    let (r, theta) = z.to_polar();
+    assert_close(Complex::from_polar(r, theta), z);
+    assert_eq!(Complex::new(0.0, 1.0).arg(), std::f64::consts::FRAC_PI_2);
+
+    // Euler's identity
+    let pi = Complex::new(0.0, std::f64::consts::PI);
+    assert_close(pi.exp(), Complex::new(-1.0, 0.0));
+    assert_close(z.ln().exp(), z);
+    assert_close(z.powi(3), z * z * z);
+    assert_close(z.powi(-2), Complex::one() / (z * z));
+    assert_close(z.powf(0.5) * z.powf(0.5), z);
+    assert_close(
+        Complex::i().powc(Complex::i()),
+        Complex::new((-pi.im / 2.0).exp(), 0.0),
+    );
+    assert_close(z.sqrt(), Complex::new(2.0, 1.0));
+
+    let zero = Complex::<f64>::zero();
+    assert_eq!(zero.powf(0.0), Complex::one());
+    assert_eq!(zero.powc(zero), Complex::one());
+    assert_eq!(zero.powf(2.0), zero);
+}
+
+#[test]
+fn test_display_and_parse() {
+    assert_eq!(Complex::new(3, 4).to_string(), "3+4i");
+    assert_eq!(Complex::new(3, -4).to_string(), "3-4i");
+    assert_eq!(format!("{:.2}", Complex::new(0.5, -1.0)), "0.50-1.00i");
+
+    assert_eq!("3+4i".parse(), Ok(Complex::new(3, 4)));
+    assert_eq!(" -3 - 4i ".parse(), Ok(Complex::new(-3, -4)));
+    assert_eq!("2.5".parse(), Ok(Complex::new(2.5, 0.0)));
+    assert_eq!("-i".parse(), Ok(Complex::new(0.0, -1.0)));
+    assert_eq!("1e-3+2E+2i".parse(), Ok(Complex::new(0.001, 200.0)));
+    assert!("3+4j".parse::<Complex<i32>>().is_err());
+    assert!("3+i4".parse::<Complex<i32>>().is_err());
+    assert!("".parse::<Complex<i32>>().is_err());
+    for z in [Complex::new(-1.25, 0.0), Complex::new(1e-7, -3e9)] {
+        assert_eq!(z.to_string().parse(), Ok(z));
+    }
+}
+
+#[test]
+fn test_num_interop() {
+    let ours = Complex::new(1.5, -2.0);
+    let theirs = num::Complex::from(ours);
+    assert_eq!(theirs, num::Complex::new(1.5, -2.0));
+    assert_eq!(Complex::from(theirs * theirs), ours * ours);
+    assert_eq!(Complex::from(7), Complex::new(7, 0));
+}
+
+/// A complex number `re + im·i`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Complex<T> {
+    pub re: T,
+    pub im: T,
+}
+
+impl<T> Complex<T> {
+    pub const fn new(re: T, im: T) -> Self {
+        Complex { re, im }
+    }
+}
+
+impl<T: Num> Complex<T> {
+    pub fn zero() -> Self {
+        Complex::new(T::ZERO, T::ZERO)
+    }
+
+    pub fn one() -> Self {
+        Complex::new(T::ONE, T::ZERO)
+    }
+
+    pub fn i() -> Self {
+        Complex::new(T::ZERO, T::ONE)
+    }
+
+    pub fn norm_sqr(&self) -> T {
+        self.re * self.re + self.im * self.im
+    }
+
+    /// Scale both parts by `k`
+    pub fn scale(&self, k: T) -> Self {
+        Complex::new(self.re * k, self.im * k)
+    }
+
+    /// Divide both parts by `k`
+    pub fn unscale(&self, k: T) -> Self {
+        Complex::new(self.re / k, self.im / k)
+    }
+}
+
+impl<T: Num + Neg<Output = T>> Complex<T> {
+    /// The complex conjugate, `re - im·i`
+    pub fn conj(&self) -> Self {
+        Complex::new(self.re, -self.im)
+    }
+
+    /// Raise to an integer power by repeated squaring. Negative powers need division to mean
+    /// anything, so for integer parts they only work for units.
+    pub fn powi(&self, exponent: i32) -> Self {
+        let mut base = if exponent < 0 {
+            Complex::one() / *self
+        } else {
+            *self
+        };
+        let mut exponent = exponent.unsigned_abs();
+        let mut result = Complex::one();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result *= base;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base *= base;
+            }
+        }
+        result
+    }
+}
+
+impl<T: Float> Complex<T> {
+    /// The absolute value, `|z|`
+    pub fn norm(&self) -> T {
+        self.re.hypot(self.im)
+    }
+
+    /// The angle from the positive real axis, in `(-π, π]`
+    pub fn arg(&self) -> T {
+        self.im.atan2(self.re)
+    }
+
+    /// `(norm, arg)`
+    pub fn to_polar(&self) -> (T, T) {
+        (self.norm(), self.arg())
+    }
+
+    pub fn from_polar(r: T, theta: T) -> Self {
+        Complex::new(r * theta.cos(), r * theta.sin())
+    }
+
+    /// `e` raised to this power
+    pub fn exp(&self) -> Self {
+        Complex::from_polar(self.re.exp(), self.im)
+    }
+
+    /// The principal natural logarithm, with its imaginary part in `(-π, π]`
+    pub fn ln(&self) -> Self {
+        let (r, theta) = self.to_polar();
+        Complex::new(r.ln(), theta)
+    }
+
+    /// The principal square root, with a non-negative real part
+    pub fn sqrt(&self) -> Self {
+        let (r, theta) = self.to_polar();
+        let two = T::ONE + T::ONE;
+        Complex::from_polar(r.sqrt(), theta / two)
+    }
+
+    /// Raise to a real power, using the principal logarithm. Like `f64::powf`, zero to the power
+    /// of zero is one.
+    pub fn powf(&self, exponent: T) -> Self {
+        if exponent == T::ZERO {
+            return Complex::one();
+        }
+        if *self == Complex::zero() {
+            return Complex::zero();
+        }
+        let (r, theta) = self.to_polar();
+        Complex::from_polar(r.powf(exponent), theta * exponent)
+    }
+
+    /// Raise to a complex power, using the principal logarithm, with zero to the power of zero
+    /// being one
+    pub fn powc(&self, exponent: Self) -> Self {
+        if exponent == Complex::zero() {
+            return Complex::one();
+        }
+        if *self == Complex::zero() {
+            return Complex::zero();
+        }
+        (self.ln() * exponent).exp()
+    }
+}
+
+impl<T: AddAssign> AddAssign for Complex<T> {
+    fn add_assign(&mut self, rhs: Self) {
+        self.re += rhs.re;
+        self.im += rhs.im;
+    }
+}
+
+impl<T: SubAssign> SubAssign for Complex<T> {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.re -= rhs.re;
+        self.im -= rhs.im;
+    }
+}
+
+impl<T: Num> MulAssign for Complex<T> {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl<T: Num> DivAssign for Complex<T> {
+    fn div_assign(&mut self, rhs: Self) {
+        *self = *self / rhs;
+    }
+}
+
+impl<T> Neg for Complex<T>
+where
+    T: Neg<Output = T>,
+{
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Complex {
+            re: -self.re,
+            im: -self.im,
+        }
+    }
+}
+
+impl<T> Add for Complex<T>
+where
+    T: Add<Output = T>,
+{
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Complex {
+            re: self.re + rhs.re,
+            im: self.im + rhs.im,
+        }
+    }
+}
+
+impl<T> Sub for Complex<T>
+where
+    T: Sub<Output = T>,
+{
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Complex {
+            re: self.re - rhs.re,
+            im: self.im - rhs.im,
+        }
+    }
+}
+
+impl<T: Num> Mul for Complex<T> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Complex {
+            re: self.re * rhs.re - self.im * rhs.im,
+            im: self.re * rhs.im + self.im * rhs.re,
+        }
+    }
+}
+
+/// Multiplies through by the conjugate of `rhs`, so that the denominator is real
+impl<T: Num> Div for Complex<T> {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        let denominator = rhs.norm_sqr();
+        Complex {
+            re: (self.re * rhs.re + self.im * rhs.im) / denominator,
+            im: (self.im * rhs.re - self.re * rhs.im) / denominator,
+        }
+    }
+}
+
+impl<T: Num> Add<T> for Complex<T> {
+    type Output = Self;
+
+    fn add(self, rhs: T) -> Self::Output {
+        Complex::new(self.re + rhs, self.im)
+    }
+}
+
+impl<T: Num> Sub<T> for Complex<T> {
+    type Output = Self;
+
+    fn sub(self, rhs: T) -> Self::Output {
+        Complex::new(self.re - rhs, self.im)
+    }
+}
+
+impl<T: Num> Mul<T> for Complex<T> {
+    type Output = Self;
+
+    fn mul(self, rhs: T) -> Self::Output {
+        self.scale(rhs)
+    }
+}
+
+impl<T: Num> Div<T> for Complex<T> {
+    type Output = Self;
+
+    fn div(self, rhs: T) -> Self::Output {
+        self.unscale(rhs)
+    }
+}
+
+impl<T: Num> MulAssign<T> for Complex<T> {
+    fn mul_assign(&mut self, rhs: T) {
+        *self = self.scale(rhs);
+    }
+}
+
+impl<T: Num> DivAssign<T> for Complex<T> {
+    fn div_assign(&mut self, rhs: T) {
+        *self = self.unscale(rhs);
+    }
+}
+
+/// Scalars on the left, as in `Mul<AppleBasket> for usize`. Like `linalg`'s, these have to be
+/// spelled out per type.
+macro_rules! impl_scalar_ops {
+    ( $( $t:ident )* ) => {
+        $(
+            impl Add<Complex<$t>> for $t {
+                type Output = Complex<$t>;
+
+                fn add(self, rhs: Complex<$t>) -> Self::Output {
+                    Complex::new(self + rhs.re, rhs.im)
+                }
+            }
+
+            impl Sub<Complex<$t>> for $t {
+                type Output = Complex<$t>;
+
+                fn sub(self, rhs: Complex<$t>) -> Self::Output {
+                    Complex::new(self - rhs.re, <$t as Num>::ZERO - rhs.im)
+                }
+            }
+
+            impl Mul<Complex<$t>> for $t {
+                type Output = Complex<$t>;
+
+                fn mul(self, rhs: Complex<$t>) -> Self::Output {
+                    rhs.scale(self)
+                }
+            }
+
+            impl Div<Complex<$t>> for $t {
+                type Output = Complex<$t>;
+
+                fn div(self, rhs: Complex<$t>) -> Self::Output {
+                    Complex::new(self, <$t as Num>::ZERO) / rhs
+                }
+            }
+        )*
+    };
+}
+
+impl_scalar_ops!(u8 i8 u16 i16 u32 i32 u64 i64 f32 f64 usize isize i128 u128);
+
+impl<T: Num> Sum for Complex<T> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Complex::zero(), |total, z| total + z)
+    }
+}
+
+impl<T: Num> From<T> for Complex<T> {
+    fn from(re: T) -> Self {
+        Complex::new(re, T::ZERO)
+    }
+}
+
+impl<T> From<num::Complex<T>> for Complex<T> {
+    fn from(z: num::Complex<T>) -> Self {
+        Complex::new(z.re, z.im)
+    }
+}
+
+impl<T> From<Complex<T>> for num::Complex<T> {
+    fn from(z: Complex<T>) -> Self {
+        num::Complex::new(z.re, z.im)
+    }
+}
+
+/// Written as `3+4i` or `3-4i`, passing any precision on to both parts
+impl<T: Display> Display for Complex<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (re, im) = match f.precision() {
+            Some(precision) => (
+                format!("{:.*}", precision, self.re),
+                format!("{:.*}", precision, self.im),
+            ),
+            None => (self.re.to_string(), self.im.to_string()),
+        };
+        match im.strip_prefix('-') {
+            Some(magnitude) => write!(f, "{}-{}i", re, magnitude),
+            None => write!(f, "{}+{}i", re, im),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseComplexError(String);
+
+impl Display for ParseComplexError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for ParseComplexError {}
+
+/// Parses `a+bi`, `a-bi`, `a`, `bi` and `i`, with optional spaces
+impl<T: Num + FromStr> FromStr for Complex<T> {
+    type Err = ParseComplexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseComplexError(format!("{:?} isn't a complex number", s));
+        let part = |text: &str| text.parse::<T>().map_err(|_| error());
+        let compact = s.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+
+        let Some(rest) = compact.strip_suffix('i') else {
+            return Ok(Complex::from(part(&compact)?));
+        };
+        // The imaginary part starts at the last sign which isn't leading or in an exponent
+        let split = rest
+            .char_indices()
+            .filter(|&(at, c)| {
+                (c == '+' || c == '-') && at > 0 && !rest[..at].ends_with(['e', 'E'])
+            })
+            .map(|(at, _)| at)
+            .next_back()
+            .unwrap_or(0);
+        let (re, im) = rest.split_at(split);
+        let re = if re.is_empty() { T::ZERO } else { part(re)? };
+        let im = match im {
+            "" | "+" => T::ONE,
+            "-" => part("-1")?,
+            _ => part(im.strip_prefix('+').unwrap_or(im))?,
+        };
+        Ok(Complex::new(re, im))
+    }
+}
@@ -0,0 +1,666 @@
+use std::collections::HashMap;
+use std::io::ErrorKind::{Interrupted, TimedOut, WouldBlock};
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How often a connection thread wakes up from a blocking read to check for shutdown and idleness
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[test]
+fn test_echo_roundtrip() {
+    let server = EchoServer::bind("127.0.0.1:0", ServerConfig::default()).unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = server.shutdown_handle();
+    let stats = server.stats();
+    let runner = thread::spawn(move || server.run());
+
+    let mut client = TcpStream::connect(addr).unwrap();
+    client.write_all(b"Life before death").unwrap();
+    client.shutdown(std::net::Shutdown::Write).unwrap();
+    let mut reply = String::new();
+    client.read_to_string(&mut reply).unwrap();
+    assert_eq!(reply, "Life before death");
+
+    handle.shutdown();
+    runner.join().unwrap().unwrap();
+
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.accepted, 1);
+    assert_eq!(snapshot.active, 0);
+    assert_eq!(snapshot.bytes_read, 17);
+    assert_eq!(snapshot.bytes_written, 17);
+}
+
+#[test]
+fn test_many_clients() {
+    let config = ServerConfig {
+        workers: 4,
+        ..ServerConfig::default()
+    };
+    let server = EchoServer::bind("127.0.0.1:0", config).unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = server.shutdown_handle();
+    let stats = server.stats();
+    let runner = thread::spawn(move || server.run());
+
+    let clients = (0..8)
+        .map(|i| {
+            thread::spawn(move || {
+                let mut client = TcpStream::connect(addr).unwrap();
+                let msg = format!("client-{}", i);
+                client.write_all(msg.as_bytes()).unwrap();
+                client.shutdown(std::net::Shutdown::Write).unwrap();
+                let mut reply = String::new();
+                client.read_to_string(&mut reply).unwrap();
+                assert_eq!(reply, msg);
+            })
+        })
+        .collect::<Vec<_>>();
+    for client in clients {
+        client.join().unwrap();
+    }
+
+    handle.shutdown();
+    runner.join().unwrap().unwrap();
+    assert_eq!(stats.snapshot().accepted, 8);
+    assert_eq!(stats.snapshot().bytes_written, 8 * 8);
+}
+
+#[test]
+fn test_max_connections() {
+    let config = ServerConfig {
+        max_connections: 1,
+        ..ServerConfig::default()
+    };
+    let server = EchoServer::bind("127.0.0.1:0", config).unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = server.shutdown_handle();
+    let stats = server.stats();
+    let runner = thread::spawn(move || server.run());
+
+    let mut first = TcpStream::connect(addr).unwrap();
+    first.write_all(b"ping").unwrap();
+    let mut buf = [0_u8; 4];
+    first.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"ping");
+
+    // The server is at capacity so the second client is hung up on straight away
+    let mut second = TcpStream::connect(addr).unwrap();
+    let mut reply = vec![];
+    second.read_to_end(&mut reply).unwrap();
+    assert!(reply.is_empty());
+
+    handle.shutdown();
+    runner.join().unwrap().unwrap();
+    assert_eq!(stats.snapshot().rejected, 1);
+}
+
+#[test]
+fn test_busy_workers() {
+    let config = ServerConfig {
+        workers: 1,
+        ..ServerConfig::default()
+    };
+    let server = EchoServer::bind("127.0.0.1:0", config).unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = server.shutdown_handle();
+    let stats = server.stats();
+    let runner = thread::spawn(move || server.run());
+
+    let mut first = TcpStream::connect(addr).unwrap();
+    first.write_all(b"ping").unwrap();
+    let mut buf = [0_u8; 4];
+    first.read_exact(&mut buf).unwrap();
+
+    // The only worker is busy, so the second client is accepted but queued until it's free
+    let mut second = TcpStream::connect(addr).unwrap();
+    second.write_all(b"pong").unwrap();
+    second
+        .set_read_timeout(Some(Duration::from_millis(200)))
+        .unwrap();
+    assert!(second.read_exact(&mut buf).is_err());
+    assert_eq!(stats.snapshot().accepted, 2);
+    assert_eq!(stats.snapshot().active, 2);
+
+    drop(first);
+    second.set_read_timeout(None).unwrap();
+    second.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"pong");
+
+    handle.shutdown();
+    runner.join().unwrap().unwrap();
+    assert_eq!(stats.snapshot().rejected, 0);
+}
+
+#[test]
+fn test_default_connection_limit() {
+    let config = ServerConfig::default();
+    let limit = config.max_connections;
+    let server = EchoServer::bind("127.0.0.1:0", config).unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = server.shutdown_handle();
+    let stats = server.stats();
+    let runner = thread::spawn(move || server.run());
+
+    let mut clients = (0..limit)
+        .map(|_| TcpStream::connect(addr).unwrap())
+        .collect::<Vec<_>>();
+    while stats.snapshot().accepted < limit as u64 {
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(stats.snapshot().active, limit);
+
+    // Every worker is busy and the queue is full, so the next client is turned away
+    let mut extra = TcpStream::connect(addr).unwrap();
+    let mut reply = vec![];
+    extra.read_to_end(&mut reply).unwrap();
+    assert!(reply.is_empty());
+    assert_eq!(stats.snapshot().rejected, 1);
+
+    // Queued clients are served once the ones ahead of them leave
+    let mut queued = clients.pop().unwrap();
+    queued.write_all(b"ping").unwrap();
+    drop(clients);
+    let mut buf = [0_u8; 4];
+    queued.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"ping");
+
+    handle.shutdown();
+    runner.join().unwrap().unwrap();
+    assert_eq!(stats.snapshot().active, 0);
+}
+
+#[test]
+fn test_panicking_handler() {
+    let config = ServerConfig {
+        workers: 1,
+        ..ServerConfig::default()
+    };
+    let server = EchoServer::bind("127.0.0.1:0", config).unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = server.shutdown_handle();
+    let stats = server.stats();
+    let runner = thread::spawn(move || {
+        server.run_with(|conn| {
+            let mut buf = [0_u8; 4];
+            match conn.receive(&mut buf)? {
+                Received::Bytes(_) if buf.starts_with(b"boom") => panic!("boom"),
+                Received::Bytes(len) => conn.send(&buf[..len])?,
+                Received::Closed(reason) => return Ok(reason),
+            }
+            Ok(Close::Finished)
+        })
+    });
+
+    // The lone worker outlives every panic and its connection's slot is given back each time
+    for _ in 0..3 {
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.write_all(b"boom").unwrap();
+        let mut reply = vec![];
+        client.read_to_end(&mut reply).unwrap();
+        assert!(reply.is_empty());
+    }
+    let mut client = TcpStream::connect(addr).unwrap();
+    client.write_all(b"ping").unwrap();
+    let mut buf = [0_u8; 4];
+    client.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"ping");
+    drop(client);
+
+    handle.shutdown();
+    runner.join().unwrap().unwrap();
+    assert_eq!(stats.snapshot().active, 0);
+}
+
+#[test]
+fn test_idle_timeout() {
+    let config = ServerConfig {
+        idle_timeout: Some(Duration::from_millis(100)),
+        ..ServerConfig::default()
+    };
+    let server = EchoServer::bind("127.0.0.1:0", config).unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = server.shutdown_handle();
+    let stats = server.stats();
+    let runner = thread::spawn(move || server.run());
+
+    let mut client = TcpStream::connect(addr).unwrap();
+    let mut reply = vec![];
+    client.read_to_end(&mut reply).unwrap();
+    assert!(reply.is_empty());
+
+    handle.shutdown();
+    runner.join().unwrap().unwrap();
+    assert_eq!(stats.snapshot().timed_out, 1);
+}
+
+#[test]
+fn test_shutdown_closes_open_connections() {
+    let server = EchoServer::bind("127.0.0.1:0", ServerConfig::default()).unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = server.shutdown_handle();
+    let stats = server.stats();
+    let runner = thread::spawn(move || server.run());
+
+    let mut client = TcpStream::connect(addr).unwrap();
+    client.write_all(b"hi").unwrap();
+    let mut buf = [0_u8; 2];
+    client.read_exact(&mut buf).unwrap();
+    assert_eq!(stats.connections().len(), 1);
+    assert_eq!(stats.connections()[0].bytes_read, 2);
+
+    handle.shutdown();
+    runner.join().unwrap().unwrap();
+    assert!(stats.connections().is_empty());
+    assert_eq!(stats.snapshot().active, 0);
+}
+
+/// Knobs for the echo server. The defaults are suitable for local experiments.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Number of worker threads serving connections. Connections beyond this many are queued
+    /// until a worker is free. `chat_server::bind` ignores this and starts `max_connections`
+    /// workers instead, since a chat client keeps its worker for as long as it's connected.
+    pub workers: usize,
+    /// The most connections open at once, whether being served or queued. Any more are closed
+    /// as soon as they're accepted.
+    pub max_connections: usize,
+    /// Close a connection when the client has been silent for this long
+    pub idle_timeout: Option<Duration>,
+    /// Size of the per-connection copy buffer
+    pub buffer_size: usize,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            workers: 8,
+            max_connections: 64,
+            idle_timeout: None,
+            buffer_size: 8 * 1024,
+        }
+    }
+}
+
+pub struct EchoServer {
+    listener: TcpListener,
+    config: ServerConfig,
+    stats: Arc<ServerStats>,
+    shutdown: ShutdownHandle,
+}
+
+impl EchoServer {
+    pub fn bind<A: ToSocketAddrs>(addr: A, config: ServerConfig) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let shutdown = ShutdownHandle::new(listener.local_addr()?);
+        Ok(EchoServer {
+            listener,
+            config,
+            stats: Arc::new(ServerStats::default()),
+            shutdown,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Retrieve a handle which can be used to stop the server from another thread
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    /// Retrieve the live statistics of this server; they remain readable after `run` returns
+    pub fn stats(&self) -> Arc<ServerStats> {
+        self.stats.clone()
+    }
+
+    /// Accept connections until shut down, echoing back whatever each client sends. Returns once
+    /// every worker has finished with its connection, so all the stats are final by then.
+    pub fn run(self) -> io::Result<()> {
+        self.run_with(echo)
+    }
+
+    /// Like `run`, but every client has to speak TLS using the given server configuration
+    #[cfg(feature = "tls")]
+    pub fn run_tls(self, tls: Arc<rustls::ServerConfig>) -> io::Result<()> {
+        self.run_with(move |conn| crate::tls::echo(conn, tls.clone()))
+    }
+
+    /// Accept connections until shut down, serving each of them with `handler` on one of the
+    /// worker threads. This is what lets other line based services reuse the echo server's
+    /// pool, limits, stats and shutdown handling.
+    pub fn run_with<H>(self, handler: H) -> io::Result<()>
+    where
+        H: Fn(&Connection) -> io::Result<Close> + Send + Sync + 'static,
+    {
+        let handler = Arc::new(handler);
+        let (sender, receiver) = channel::<(u64, TcpStream)>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..self.config.workers.max(1))
+            .map(|_| self.spawn_worker(receiver.clone(), handler.clone()))
+            .collect::<Vec<_>>();
+
+        let result = self.accept_loop(|id, stream| {
+            sender
+                .send((id, stream))
+                .map_err(|_| io::Error::other("All workers have exited"))
+        });
+
+        drop(sender);
+        for worker in workers {
+            if worker.join().is_err() {
+                eprintln!("Echo worker thread panicked");
+            }
+        }
+        result
+    }
+
+    fn accept_loop<F>(&self, mut dispatch: F) -> io::Result<()>
+    where
+        F: FnMut(u64, TcpStream) -> io::Result<()>,
+    {
+        let mut next_id = 0_u64;
+        loop {
+            let (stream, client_addr) = match self.listener.accept() {
+                Ok(pair) => pair,
+                Err(ref e) if e.kind() == Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if self.shutdown.is_shutdown() {
+                return Ok(());
+            }
+            self.stats.accepted.fetch_add(1, Ordering::SeqCst);
+            if self.stats.active.load(Ordering::SeqCst) >= self.config.max_connections {
+                println!("Rejecting connection from {} -- server full", client_addr);
+                self.stats.rejected.fetch_add(1, Ordering::SeqCst);
+                continue;
+            }
+            println!("Accepted connection from client {}", client_addr);
+            next_id += 1;
+            self.stats.open(next_id, client_addr);
+            dispatch(next_id, stream)?;
+        }
+    }
+
+    fn spawn_worker<H>(
+        &self,
+        receiver: Arc<Mutex<Receiver<(u64, TcpStream)>>>,
+        handler: Arc<H>,
+    ) -> JoinHandle<()>
+    where
+        H: Fn(&Connection) -> io::Result<Close> + Send + Sync + 'static,
+    {
+        let config = self.config.clone();
+        let stats = self.stats.clone();
+        let shutdown = self.shutdown.clone();
+        thread::spawn(move || loop {
+            let next = receiver.lock().expect("Failed to lock").recv();
+            let (id, stream) = match next {
+                Ok(pair) => pair,
+                Err(_) => return,
+            };
+            // Closes the connection's slot however the handler finishes
+            let _closing = Closing { stats: &stats, id };
+            let conn = Connection {
+                id,
+                stream: &stream,
+                config: &config,
+                stats: &stats,
+                shutdown: &shutdown,
+            };
+            // A panicking handler only loses its own connection, not the worker
+            match panic::catch_unwind(AssertUnwindSafe(|| handler(&conn))) {
+                Ok(Ok(Close::TimedOut)) => {
+                    stats.timed_out.fetch_add(1, Ordering::SeqCst);
+                }
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => eprintln!("Error in connection {}: {}", id, e),
+                Err(_) => eprintln!("Handler for connection {} panicked", id),
+            }
+        })
+    }
+}
+
+/// Removes a connection from the stats when dropped
+struct Closing<'a> {
+    stats: &'a ServerStats,
+    id: u64,
+}
+
+impl Drop for Closing<'_> {
+    fn drop(&mut self) {
+        self.stats.close(self.id);
+    }
+}
+
+/// Why a connection handler stopped serving its client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Close {
+    /// The client hung up or the handler was done with it
+    Finished,
+    /// The client was silent for longer than the configured idle timeout
+    TimedOut,
+    /// The server is shutting down
+    Shutdown,
+}
+
+/// Outcome of waiting for data on a `Connection`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Received {
+    Bytes(usize),
+    Closed(Close),
+}
+
+/// A client connection as seen by a handler passed to `EchoServer::run_with`. Reads and writes
+/// going through it are accounted for in the server stats.
+pub struct Connection<'a> {
+    id: u64,
+    stream: &'a TcpStream,
+    config: &'a ServerConfig,
+    stats: &'a ServerStats,
+    shutdown: &'a ShutdownHandle,
+}
+
+impl<'a> Connection<'a> {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn stream(&self) -> &'a TcpStream {
+        self.stream
+    }
+
+    pub fn config(&self) -> &'a ServerConfig {
+        self.config
+    }
+
+    pub fn is_shutdown(&self) -> bool {
+        self.shutdown.is_shutdown()
+    }
+
+    /// Wait for the next chunk of data from the client. The wait wakes up periodically so that
+    /// idle clients and a server shutdown are noticed even while the client is silent.
+    pub fn receive(&self, buf: &mut [u8]) -> io::Result<Received> {
+        let mut stream = self.stream;
+        stream.set_read_timeout(Some(POLL_INTERVAL))?;
+        let started = Instant::now();
+        loop {
+            if self.is_shutdown() {
+                return Ok(Received::Closed(Close::Shutdown));
+            }
+            match stream.read(buf) {
+                Ok(0) => return Ok(Received::Closed(Close::Finished)),
+                Ok(len) => {
+                    self.stats.record_read(self.id, len);
+                    return Ok(Received::Bytes(len));
+                }
+                Err(ref e) if e.kind() == WouldBlock || e.kind() == TimedOut => {
+                    match self.config.idle_timeout {
+                        Some(limit) if started.elapsed() >= limit => {
+                            return Ok(Received::Closed(Close::TimedOut))
+                        }
+                        _ => continue,
+                    }
+                }
+                Err(ref e) if e.kind() == Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Write all of `data` back to the client
+    pub fn send(&self, data: &[u8]) -> io::Result<()> {
+        let mut stream = self.stream;
+        stream.write_all(data)?;
+        self.stats.record_written(self.id, data.len());
+        Ok(())
+    }
+}
+
+/// Echo everything read from the connection back to it
+fn echo(conn: &Connection) -> io::Result<Close> {
+    let mut buf = vec![0_u8; conn.config().buffer_size.max(1)];
+    loop {
+        match conn.receive(&mut buf)? {
+            Received::Bytes(len) => conn.send(&buf[..len])?,
+            Received::Closed(reason) => return Ok(reason),
+        }
+    }
+}
+
+/// Stops a running `EchoServer`. Cloning the handle is cheap and all clones control the same server.
+#[derive(Debug, Clone)]
+pub struct ShutdownHandle {
+    flag: Arc<AtomicBool>,
+    addr: SocketAddr,
+}
+
+impl ShutdownHandle {
+    /// Create a handle for a server listening on `addr`
+    pub(crate) fn new(addr: SocketAddr) -> Self {
+        ShutdownHandle {
+            flag: Arc::new(AtomicBool::new(false)),
+            addr,
+        }
+    }
+
+    /// Ask the server to stop accepting and to close its open connections
+    pub fn shutdown(&self) {
+        if !self.flag.swap(true, Ordering::SeqCst) {
+            // The accept loop is blocked in `accept`, so poke it with a throwaway connection
+            let _ = TcpStream::connect(self.addr);
+        }
+    }
+
+    pub fn is_shutdown(&self) -> bool {
+        self.flag.load(Ordering::SeqCst)
+    }
+}
+
+/// Byte counters for a single connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionStats {
+    pub id: u64,
+    pub peer: SocketAddr,
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+}
+
+/// Aggregate counters across the lifetime of the server
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StatsSnapshot {
+    pub accepted: u64,
+    pub rejected: u64,
+    pub timed_out: u64,
+    pub active: usize,
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct ServerStats {
+    accepted: AtomicU64,
+    rejected: AtomicU64,
+    timed_out: AtomicU64,
+    active: AtomicUsize,
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+    connections: Mutex<HashMap<u64, ConnectionStats>>,
+}
+
+impl ServerStats {
+    pub fn snapshot(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            accepted: self.accepted.load(Ordering::SeqCst),
+            rejected: self.rejected.load(Ordering::SeqCst),
+            timed_out: self.timed_out.load(Ordering::SeqCst),
+            active: self.active.load(Ordering::SeqCst),
+            bytes_read: self.bytes_read.load(Ordering::SeqCst),
+            bytes_written: self.bytes_written.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Retrieve the counters of every currently open connection, ordered by connection id
+    pub fn connections(&self) -> Vec<ConnectionStats> {
+        let guard = self.connections.lock().expect("Failed to lock");
+        let mut connections = guard.values().cloned().collect::<Vec<_>>();
+        connections.sort_by_key(|c| c.id);
+        connections
+    }
+
+    fn open(&self, id: u64, peer: SocketAddr) {
+        self.active.fetch_add(1, Ordering::SeqCst);
+        let stats = ConnectionStats {
+            id,
+            peer,
+            bytes_read: 0,
+            bytes_written: 0,
+        };
+        self.connections
+            .lock()
+            .expect("Failed to lock")
+            .insert(id, stats);
+    }
+
+    fn close(&self, id: u64) {
+        let removed = self.connections.lock().expect("Failed to lock").remove(&id);
+        if let Some(c) = removed {
+            println!(
+                "Transferred total {} bytes back to the client {}",
+                c.bytes_written, c.peer
+            );
+        }
+        self.active.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    fn record_read(&self, id: u64, len: usize) {
+        self.bytes_read.fetch_add(len as u64, Ordering::SeqCst);
+        if let Some(c) = self
+            .connections
+            .lock()
+            .expect("Failed to lock")
+            .get_mut(&id)
+        {
+            c.bytes_read += len as u64;
+        }
+    }
+
+    fn record_written(&self, id: u64, len: usize) {
+        self.bytes_written.fetch_add(len as u64, Ordering::SeqCst);
+        if let Some(c) = self
+            .connections
+            .lock()
+            .expect("Failed to lock")
+            .get_mut(&id)
+        {
+            c.bytes_written += len as u64;
+        }
+    }
+}
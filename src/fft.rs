@@ -0,0 +1,374 @@
+//! Fast Fourier transforms over `complex::Complex`. Powers of two use an in-place radix-2
+//! transform, and other lengths are split up by their prime factors.
+//!
+//! The forward transform is `X[k] = Σ x[j]·e^(-2πi·jk/n)` and the inverse divides by `n`, so
+//! `ifft(fft(x))` is `x` again.
+
+use crate::complex::Complex;
+use crate::linalg::Vector;
+use crate::numeric::{Float, Num};
+
+#[cfg(test)]
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+#[cfg(test)]
+fn random_signal(len: usize, seed: u64) -> Vec<Complex<f64>> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..len)
+        .map(|_| Complex::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)))
+        .collect()
+}
+
+#[cfg(test)]
+fn assert_close(a: &[Complex<f64>], b: &[Complex<f64>]) {
+    assert_eq!(a.len(), b.len());
+    let tolerance = 1e-10 * a.len().max(1) as f64;
+    for (k, (x, y)) in a.iter().zip(b).enumerate() {
+        assert!((*x - *y).norm() < tolerance, "{}: {} != {}", k, x, y);
+    }
+}
+
+#[test]
+fn test_fft_matches_dft() {
+    for len in (0..=40).chain([64, 97, 210, 360, 1024]) {
+        let signal = random_signal(len, len as u64);
+        let spectrum = fft(&signal);
+        assert_close(&spectrum, &dft(&signal));
+        assert_close(&ifft(&spectrum), &signal);
+    }
+}
+
+#[test]
+fn test_in_place() {
+    let signal = random_signal(256, 1);
+    let mut data = signal.clone();
+    fft_in_place(&mut data);
+    assert_close(&data, &dft(&signal));
+    ifft_in_place(&mut data);
+    assert_close(&data, &signal);
+
+    // An impulse has a flat spectrum, and a constant has all its energy in the first bin
+    let mut impulse = vec![Complex::<f64>::zero(); 8];
+    impulse[0] = Complex::one();
+    fft_in_place(&mut impulse);
+    assert!(impulse.iter().all(|&x| x == Complex::one()));
+    let spectrum = fft(&[Complex::new(2.0_f32, 0.0); 6]);
+    assert_eq!(spectrum[0], Complex::new(12.0, 0.0));
+    assert!(spectrum[1..].iter().all(|x| x.norm() < 1e-6));
+}
+
+#[test]
+#[should_panic(expected = "power of two")]
+fn test_in_place_needs_power_of_two() {
+    fft_in_place(&mut [Complex::<f64>::zero(); 12]);
+}
+
+#[test]
+fn test_real_fft() {
+    for len in [1, 2, 7, 16, 30, 99] {
+        let signal = random_signal(len, 7)
+            .into_iter()
+            .map(|x| x.re)
+            .collect::<Vec<_>>();
+        let spectrum = rfft(&signal);
+        let complex = signal.iter().map(|&x| Complex::from(x)).collect::<Vec<_>>();
+        assert_close(&spectrum, &dft(&complex)[..len / 2 + 1]);
+
+        let restored = irfft(&spectrum, len);
+        assert!(signal
+            .iter()
+            .zip(&restored)
+            .all(|(a, b)| (a - b).abs() < 1e-10));
+    }
+
+    let empty = rfft::<f64>(&[]);
+    assert_eq!(empty, [Complex::zero()]);
+    assert!(irfft(&empty, 0).is_empty());
+}
+
+#[test]
+fn test_convolve() {
+    let small = convolve(&[1.0, 2.0, 3.0], &[0.0, 1.0, 0.5]);
+    let expected = [0.0_f64, 1.0, 2.5, 4.0, 1.5];
+    assert_eq!(small.len(), expected.len());
+    assert!(small
+        .iter()
+        .zip(expected)
+        .all(|(x, y)| (x - y).abs() < 1e-12));
+    assert!(convolve::<f64>(&[], &[1.0]).is_empty());
+
+    let a = random_signal(50, 3)
+        .iter()
+        .map(|x| x.re)
+        .collect::<Vec<_>>();
+    let b = random_signal(23, 4)
+        .iter()
+        .map(|x| x.im)
+        .collect::<Vec<_>>();
+    let direct = (0..a.len() + b.len() - 1)
+        .map(|n| {
+            (0..a.len())
+                .filter(|&i| n >= i && n - i < b.len())
+                .map(|i| a[i] * b[n - i])
+                .sum::<f64>()
+        })
+        .collect::<Vec<_>>();
+    let fast = convolve(&a, &b);
+    assert!(fast.iter().zip(&direct).all(|(x, y)| (x - y).abs() < 1e-10));
+}
+
+#[test]
+fn test_vectors() {
+    let close = |a: &[f32], b: &[f32]| a.iter().zip(b).all(|(x, y)| (x - y).abs() < 1e-6);
+    let signal = Vector::from(vec![1.0_f32, 0.0, -1.0, 0.0]);
+    let spectrum = signal.rfft();
+    let parts = spectrum
+        .iter()
+        .flat_map(|x| [x.re, x.im])
+        .collect::<Vec<_>>();
+    assert!(close(&parts, &[0.0, 0.0, 2.0, 0.0, 0.0, 0.0]));
+
+    let complex = signal
+        .iter()
+        .map(|&x| Complex::from(x))
+        .collect::<Vector<_>>();
+    let restored = complex.fft().ifft();
+    assert!(close(
+        &restored.iter().map(|x| x.re).collect::<Vec<_>>(),
+        signal.as_slice()
+    ));
+
+    let smoothed = signal.convolve(&Vector::from(vec![0.5, 0.5]));
+    assert_eq!(smoothed.len(), 5);
+    assert!(close(smoothed.as_slice(), &[0.5, 0.5, -0.5, -0.5, 0.0]));
+}
+
+fn real<T: Float>(x: f64) -> T {
+    T::from_f64(x).expect("Floats convert from any f64")
+}
+
+/// `e^(∓2πi·k/n)`, worked out in `f64` so that `f32` transforms stay accurate
+fn twiddle<T: Float>(k: usize, n: usize, inverse: bool) -> Complex<T> {
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let angle = sign * 2.0 * std::f64::consts::PI * (k % n) as f64 / n as f64;
+    Complex::new(real(angle.cos()), real(angle.sin()))
+}
+
+/// The discrete Fourier transform straight from its definition, taking `O(n²)` time. Useful
+/// for checking the fast versions.
+pub fn dft<T: Float>(input: &[Complex<T>]) -> Vec<Complex<T>> {
+    naive(input, false)
+}
+
+fn naive<T: Float>(input: &[Complex<T>], inverse: bool) -> Vec<Complex<T>> {
+    let n = input.len();
+    (0..n)
+        .map(|k| {
+            input
+                .iter()
+                .enumerate()
+                .map(|(j, &x)| x * twiddle(j * k % n, n, inverse))
+                .sum()
+        })
+        .collect()
+}
+
+/// The transform of any length of input
+pub fn fft<T: Float>(input: &[Complex<T>]) -> Vec<Complex<T>> {
+    transform(input, false)
+}
+
+/// The inverse of `fft`, including the division by `n`
+pub fn ifft<T: Float>(input: &[Complex<T>]) -> Vec<Complex<T>> {
+    let scale = real::<T>(input.len().max(1) as f64);
+    transform(input, true)
+        .into_iter()
+        .map(|x| x.unscale(scale))
+        .collect()
+}
+
+/// Transform without allocating. Panics unless the length is a power of two.
+pub fn fft_in_place<T: Float>(data: &mut [Complex<T>]) {
+    radix2(data, false);
+}
+
+/// The inverse of `fft_in_place`. Panics unless the length is a power of two.
+pub fn ifft_in_place<T: Float>(data: &mut [Complex<T>]) {
+    radix2(data, true);
+    let scale = real::<T>(data.len().max(1) as f64);
+    for x in data {
+        *x = x.unscale(scale);
+    }
+}
+
+/// The iterative Cooley-Tukey transform: put the input in bit-reversed order, then combine
+/// pairs of ever larger transforms with butterflies
+fn radix2<T: Float>(data: &mut [Complex<T>], inverse: bool) {
+    let n = data.len();
+    assert!(
+        n.is_power_of_two() || n == 0,
+        "In-place transforms need a power of two length, not {}",
+        n
+    );
+    if n <= 1 {
+        return;
+    }
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let half = len / 2;
+        let twiddles = (0..half)
+            .map(|k| twiddle::<T>(k, len, inverse))
+            .collect::<Vec<_>>();
+        for chunk in data.chunks_exact_mut(len) {
+            let (evens, odds) = chunk.split_at_mut(half);
+            for ((even, odd), &w) in evens.iter_mut().zip(odds).zip(&twiddles) {
+                let (a, b) = (*even, *odd * w);
+                *even = a + b;
+                *odd = a - b;
+            }
+        }
+        len *= 2;
+    }
+}
+
+fn smallest_factor(n: usize) -> usize {
+    (2..)
+        .take_while(|p| p * p <= n)
+        .find(|p| n.is_multiple_of(*p))
+        .unwrap_or(n)
+}
+
+/// Mixed radix: for a smallest prime factor `p`, transform the `p` interleaved subsequences
+/// and combine them. Powers of two go to `radix2`, and primes fall back to the definition.
+fn transform<T: Float>(input: &[Complex<T>], inverse: bool) -> Vec<Complex<T>> {
+    let n = input.len();
+    if n.is_power_of_two() || n == 0 {
+        let mut output = input.to_vec();
+        radix2(&mut output, inverse);
+        return output;
+    }
+    let p = smallest_factor(n);
+    if p == n {
+        return naive(input, inverse);
+    }
+
+    let m = n / p;
+    let parts = (0..p)
+        .map(|r| {
+            let part = input[r..].iter().step_by(p).copied().collect::<Vec<_>>();
+            transform(&part, inverse)
+        })
+        .collect::<Vec<_>>();
+    let twiddles = (0..n)
+        .map(|k| twiddle::<T>(k, n, inverse))
+        .collect::<Vec<_>>();
+    (0..n)
+        .map(|k| {
+            parts
+                .iter()
+                .enumerate()
+                .map(|(r, part)| part[k % m] * twiddles[r * k % n])
+                .sum()
+        })
+        .collect()
+}
+
+/// The transform of a real signal. The second half of its spectrum mirrors the first, so only
+/// the `n / 2 + 1` bins from zero up to the Nyquist frequency are returned. That's a single zero
+/// bin for an empty signal, so `irfft` can still undo it.
+pub fn rfft<T: Float>(input: &[T]) -> Vec<Complex<T>> {
+    let n = input.len();
+    if n < 2 || !n.is_multiple_of(2) {
+        let complex = input.iter().map(|&x| Complex::from(x)).collect::<Vec<_>>();
+        let mut spectrum = fft(&complex);
+        spectrum.resize(n / 2 + 1, Complex::zero());
+        return spectrum;
+    }
+
+    // Pack pairs of samples into one complex number, transform at half the length, then
+    // untangle the transforms of the even and odd samples
+    let half = n / 2;
+    let packed = input
+        .chunks_exact(2)
+        .map(|pair| Complex::new(pair[0], pair[1]))
+        .collect::<Vec<_>>();
+    let z = fft(&packed);
+    let two = T::ONE + T::ONE;
+    (0..=half)
+        .map(|k| {
+            let (a, b) = (z[k % half], z[(half - k) % half].conj());
+            let even = (a + b).unscale(two);
+            let odd = (a - b) * Complex::new(T::ZERO, -T::ONE / two);
+            even + odd * twiddle(k, n, false)
+        })
+        .collect()
+}
+
+/// The inverse of `rfft`, given the length of the original signal
+pub fn irfft<T: Float>(spectrum: &[Complex<T>], len: usize) -> Vec<T> {
+    assert_eq!(
+        spectrum.len(),
+        len / 2 + 1,
+        "A real signal of length {} has {} bins",
+        len,
+        len / 2 + 1
+    );
+    let full = (0..len)
+        .map(|k| match spectrum.get(k) {
+            Some(&x) => x,
+            None => spectrum[len - k].conj(),
+        })
+        .collect::<Vec<_>>();
+    ifft(&full).into_iter().map(|x| x.re).collect()
+}
+
+/// The linear convolution of two real signals, of length `a.len() + b.len() - 1`, by
+/// multiplying their spectra
+pub fn convolve<T: Float>(a: &[T], b: &[T]) -> Vec<T> {
+    if a.is_empty() || b.is_empty() {
+        return vec![];
+    }
+    let len = a.len() + b.len() - 1;
+    let size = len.next_power_of_two();
+    let padded = |signal: &[T]| {
+        let mut padded = signal.to_vec();
+        padded.resize(size, T::ZERO);
+        rfft(&padded)
+    };
+    let product = padded(a)
+        .into_iter()
+        .zip(padded(b))
+        .map(|(x, y)| x * y)
+        .collect::<Vec<_>>();
+    let mut output = irfft(&product, size);
+    output.truncate(len);
+    output
+}
+
+impl<T: Float> Vector<Complex<T>> {
+    pub fn fft(&self) -> Self {
+        fft(self.as_slice()).into()
+    }
+
+    pub fn ifft(&self) -> Self {
+        ifft(self.as_slice()).into()
+    }
+}
+
+impl<T: Float> Vector<T> {
+    pub fn rfft(&self) -> Vector<Complex<T>> {
+        rfft(self.as_slice()).into()
+    }
+
+    pub fn convolve(&self, other: &Vector<T>) -> Vector<T> {
+        convolve(self.as_slice(), other.as_slice()).into()
+    }
+}
@@ -0,0 +1,306 @@
+//! Fibonacci numbers without the exponential recursion of `chap_11::fib` or the silent
+//! overflow of `chap_15::gen_fib`. Everything which works on primitive types is checked, and
+//! `big` has no upper limit at all.
+
+use crate::numeric::Num;
+use num::BigUint;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+#[test]
+fn test_algorithms_agree() {
+    let expected = [0_u64, 1, 1, 2, 3, 5, 8, 13, 21, 34, 55, 89];
+    for (n, &f) in expected.iter().enumerate() {
+        assert_eq!(matrix_power::<u64>(n as u64), Some(f));
+        assert_eq!(fast_doubling::<u64>(n as u64), Some(f));
+        assert_eq!(big(n as u64), BigUint::from(f));
+    }
+    for n in 0..=93 {
+        assert_eq!(matrix_power::<u64>(n), fast_doubling::<u64>(n));
+    }
+    assert_eq!(fast_doubling::<u64>(93), Some(12_200_160_415_121_876_738));
+    assert_eq!(fast_doubling::<f64>(30), Some(832_040.0));
+}
+
+#[test]
+fn test_overflow() {
+    assert_eq!(fast_doubling::<u8>(13), Some(233));
+    assert_eq!(fast_doubling::<u8>(14), None);
+    assert_eq!(matrix_power::<u8>(14), None);
+    assert_eq!(fast_doubling::<u64>(94), None);
+    assert_eq!(matrix_power::<i64>(93), None);
+
+    let terms = checked::<u8>().collect::<Vec<_>>();
+    assert_eq!(terms.len(), 15);
+    assert_eq!(terms[13], Ok(233));
+    assert_eq!(terms[14], Err(Overflow { index: 14 }));
+}
+
+#[test]
+fn test_big() {
+    assert_eq!(
+        big(200).to_string(),
+        "280571172992510140037611932413038677189525"
+    );
+    assert_eq!(big(94), BigUint::from(19_740_274_219_868_223_167_u128));
+}
+
+#[test]
+fn test_lucas() {
+    let expected = [2_u32, 1, 3, 4, 7, 11, 18, 29, 47, 76];
+    for (n, &l) in expected.iter().enumerate() {
+        assert_eq!(lucas::<u32>(n as u64), Some(l));
+        assert_eq!(lucas_big(n as u64), BigUint::from(l));
+    }
+    assert_eq!(lucas::<u8>(11), Some(199));
+    assert_eq!(lucas::<u8>(12), None);
+    assert_eq!(
+        LinearRecurrence::lucas()
+            .iter()
+            .take(10)
+            .collect::<Vec<_>>(),
+        expected.map(Ok)
+    );
+}
+
+#[test]
+fn test_recurrence() {
+    // Pell numbers: P(n) = 2P(n-1) + P(n-2)
+    let pell = LinearRecurrence::new(vec![2_i64, 1], vec![0, 1]);
+    let terms = pell.iter().take(8).map(Result::unwrap).collect::<Vec<_>>();
+    assert_eq!(terms, [0, 1, 2, 5, 12, 29, 70, 169]);
+    assert_eq!(pell.term(7), Some(169));
+
+    // Tribonacci, and a first-order recurrence with a negative coefficient
+    let tribonacci = LinearRecurrence::new(vec![1_i32, 1, 1], vec![0, 0, 1]);
+    for (n, term) in tribonacci.iter().take(30).enumerate() {
+        assert_eq!(tribonacci.term(n as u64), Some(term.unwrap()));
+    }
+    let alternating = LinearRecurrence::new(vec![-1_i32], vec![3]);
+    assert_eq!(alternating.term(5), Some(-3));
+    assert_eq!(alternating.term(6), Some(3));
+
+    assert_eq!(LinearRecurrence::<u8>::fibonacci().term(14), None);
+}
+
+/// A term didn't fit in the type it was asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overflow {
+    pub index: u64,
+}
+
+impl Display for Overflow {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "term {} overflowed", self.index)
+    }
+}
+
+impl Error for Overflow {}
+
+/// F(n) by squaring the matrix [[1, 1], [1, 0]], in O(log n) multiplications
+pub fn matrix_power<T: Num>(n: u64) -> Option<T> {
+    LinearRecurrence::fibonacci().term(n)
+}
+
+/// F(n) using the identities F(2k) = F(k)(2F(k+1) - F(k)) and F(2k+1) = F(k)² + F(k+1)²,
+/// which need fewer multiplications than `matrix_power`
+pub fn fast_doubling<T: Num>(n: u64) -> Option<T> {
+    Some(doubling_pair::<T>(n)?.0)
+}
+
+/// F(n) and, if it fits as well, F(n + 1)
+fn doubling_pair<T: Num>(n: u64) -> Option<(T, Option<T>)> {
+    if n == 0 {
+        return Some((T::ZERO, Some(T::ONE)));
+    }
+    let (a, b) = doubling_pair::<T>(n / 2)?;
+    let b = b?;
+    let c = b
+        .checked_add(b)
+        .and_then(|two_b| two_b.checked_sub(a))
+        .and_then(|diff| a.checked_mul(diff));
+    let d = a
+        .checked_mul(a)
+        .and_then(|a2| a2.checked_add(b.checked_mul(b)?));
+    if n.is_multiple_of(2) {
+        Some((c?, d))
+    } else {
+        let d = d?;
+        Some((d, c.and_then(|c| c.checked_add(d))))
+    }
+}
+
+/// F(n) to arbitrary precision
+pub fn big(n: u64) -> BigUint {
+    big_pair(n).0
+}
+
+fn big_pair(n: u64) -> (BigUint, BigUint) {
+    if n == 0 {
+        return (BigUint::from(0_u8), BigUint::from(1_u8));
+    }
+    let (a, b) = big_pair(n / 2);
+    let c = &a * (&b * 2_u8 - &a);
+    let d = &a * &a + &b * &b;
+    if n.is_multiple_of(2) {
+        (c, d)
+    } else {
+        let next = &c + &d;
+        (d, next)
+    }
+}
+
+/// The Lucas number L(n) = F(n - 1) + F(n + 1), calculated as F(n) + 2F(n - 1) so that nothing
+/// in between is bigger than the answer
+pub fn lucas<T: Num>(n: u64) -> Option<T> {
+    if n == 0 {
+        return T::ONE.checked_add(T::ONE);
+    }
+    let (previous, f) = doubling_pair::<T>(n - 1)?;
+    f?.checked_add(previous.checked_add(previous)?)
+}
+
+pub fn lucas_big(n: u64) -> BigUint {
+    let (f, next) = big_pair(n);
+    next * 2_u8 - f
+}
+
+/// The Fibonacci numbers in order, ending with an `Err` at the first one which doesn't fit
+pub fn checked<T: Num>() -> Terms<T> {
+    LinearRecurrence::fibonacci().iter()
+}
+
+/// a(n) = c₀a(n-1) + c₁a(n-2) + ... + cₖ₋₁a(n-k), starting from k initial terms
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinearRecurrence<T> {
+    coefficients: Vec<T>,
+    initial: Vec<T>,
+}
+
+impl<T: Num> LinearRecurrence<T> {
+    /// Panics unless there are as many initial terms as coefficients, and at least one
+    pub fn new(coefficients: Vec<T>, initial: Vec<T>) -> Self {
+        assert!(!coefficients.is_empty(), "A recurrence needs a coefficient");
+        assert_eq!(
+            coefficients.len(),
+            initial.len(),
+            "A recurrence needs an initial term for every coefficient"
+        );
+        LinearRecurrence {
+            coefficients,
+            initial,
+        }
+    }
+
+    pub fn fibonacci() -> Self {
+        LinearRecurrence::new(vec![T::ONE, T::ONE], vec![T::ZERO, T::ONE])
+    }
+
+    pub fn lucas() -> Self {
+        LinearRecurrence::new(vec![T::ONE, T::ONE], vec![T::ONE + T::ONE, T::ONE])
+    }
+
+    pub fn order(&self) -> usize {
+        self.coefficients.len()
+    }
+
+    pub fn iter(&self) -> Terms<T> {
+        Terms {
+            coefficients: self.coefficients.clone(),
+            window: self.initial.iter().copied().map(Some).collect(),
+            index: 0,
+            done: false,
+        }
+    }
+
+    /// The nth term, by raising the companion matrix to the nth power. That takes O(k³ log n)
+    /// operations for a recurrence of order k.
+    pub fn term(&self, n: u64) -> Option<T> {
+        let k = self.order();
+        if n < k as u64 {
+            return Some(self.initial[n as usize]);
+        }
+        // Row i of the companion matrix shifts a(m-i) down to a(m+1-i), and row 0 applies the
+        // recurrence; with the window written newest first, M^(n-k+1) takes it to a(n).
+        let mut companion = vec![vec![T::ZERO; k]; k];
+        companion[0].copy_from_slice(&self.coefficients);
+        for i in 1..k {
+            companion[i][i - 1] = T::ONE;
+        }
+        let power = matrix_pow(companion, n - k as u64 + 1)?;
+        let newest_first = self.initial.iter().rev();
+        power[0]
+            .iter()
+            .zip(newest_first)
+            .try_fold(T::ZERO, |sum, (&m, &a)| sum.checked_add(m.checked_mul(a)?))
+    }
+}
+
+type SquareMatrix<T> = Vec<Vec<T>>;
+
+fn matrix_mul<T: Num>(a: &SquareMatrix<T>, b: &SquareMatrix<T>) -> Option<SquareMatrix<T>> {
+    let k = a.len();
+    let mut product = vec![vec![T::ZERO; k]; k];
+    for i in 0..k {
+        for j in 0..k {
+            for l in 0..k {
+                let term = a[i][l].checked_mul(b[l][j])?;
+                product[i][j] = product[i][j].checked_add(term)?;
+            }
+        }
+    }
+    Some(product)
+}
+
+fn matrix_pow<T: Num>(mut base: SquareMatrix<T>, mut exponent: u64) -> Option<SquareMatrix<T>> {
+    let k = base.len();
+    let mut result = vec![vec![T::ZERO; k]; k];
+    for (i, row) in result.iter_mut().enumerate() {
+        row[i] = T::ONE;
+    }
+    while exponent > 0 {
+        if exponent % 2 == 1 {
+            result = matrix_mul(&result, &base)?;
+        }
+        exponent /= 2;
+        if exponent > 0 {
+            base = matrix_mul(&base, &base)?;
+        }
+    }
+    Some(result)
+}
+
+/// The terms of a `LinearRecurrence`, which stops after reporting the first overflow
+pub struct Terms<T> {
+    coefficients: Vec<T>,
+    /// The next k terms, oldest first, with `None` for any which overflowed
+    window: Vec<Option<T>>,
+    index: u64,
+    done: bool,
+}
+
+impl<T: Num> Iterator for Terms<T> {
+    type Item = Result<T, Overflow>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let next = self
+            .coefficients
+            .iter()
+            .zip(self.window.iter().rev())
+            .try_fold(T::ZERO, |sum, (&c, &a)| sum.checked_add(c.checked_mul(a?)?));
+        let current = self.window.remove(0);
+        self.window.push(next);
+        let index = self.index;
+        self.index += 1;
+        match current {
+            Some(current) => Some(Ok(current)),
+            None => {
+                self.done = true;
+                Some(Err(Overflow { index }))
+            }
+        }
+    }
+}
@@ -0,0 +1,362 @@
+//! The `Image` type from `chap_12`, grown into something which can load, transform and save
+//! real pictures
+
+use crate::numeric::Num;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::ops::{Index, IndexMut};
+use std::slice::{ChunksExact, ChunksExactMut};
+
+pub use pixel::{Channel, FileChannel, Gray, Pixel, Rgb, Rgba};
+pub use view::{View, ViewMut};
+
+pub mod filter;
+pub mod pixel;
+#[cfg(feature = "png")]
+pub mod png;
+pub mod pnm;
+pub mod view;
+
+#[cfg(test)]
+fn numbered(width: usize, height: usize) -> Image<u32> {
+    Image::from_fn(width, height, |x, y| (y * 10 + x) as u32)
+}
+
+#[test]
+fn test_indexing() {
+    let mut image = numbered(3, 2);
+    assert_eq!(image.dimensions(), (3, 2));
+    assert_eq!(image[(2, 1)], 12);
+    assert_eq!(image[1], [10, 11, 12]);
+    image[(0, 1)] = 99;
+    assert_eq!(image.get(0, 1), Some(&99));
+    assert_eq!(image.get(3, 0), None);
+    assert_eq!(image.get(0, 2), None);
+
+    let pixels = image.enumerate_pixels().collect::<Vec<_>>();
+    assert_eq!(pixels[4], (1, 1, &11));
+    assert_eq!(image.rows().nth(1).unwrap(), [99, 11, 12]);
+    assert_eq!(image.pixels().count(), 6);
+}
+
+#[test]
+fn test_from_vec() {
+    assert!(Image::from_vec(2, 2, vec![0_u8; 4]).is_ok());
+    assert!(matches!(
+        Image::from_vec(2, 2, vec![0_u8; 5]),
+        Err(ImageError::Dimensions { .. })
+    ));
+}
+
+#[test]
+fn test_transforms() {
+    let image = numbered(3, 2);
+    // 00 01 02
+    // 10 11 12
+    assert_eq!(image.flip_horizontal().as_slice(), [2, 1, 0, 12, 11, 10]);
+    assert_eq!(image.flip_vertical().as_slice(), [10, 11, 12, 0, 1, 2]);
+
+    let rotated = image.rotate90();
+    assert_eq!(rotated.dimensions(), (2, 3));
+    assert_eq!(rotated.as_slice(), [10, 0, 11, 1, 12, 2]);
+    assert_eq!(image.rotate270().as_slice(), [2, 12, 1, 11, 0, 10]);
+    assert_eq!(image.rotate180().as_slice(), [12, 11, 10, 2, 1, 0]);
+    assert_eq!(rotated.rotate90().rotate90().rotate90(), image);
+
+    let cropped = image.crop(1, 0, 2, 2);
+    assert_eq!(cropped.as_slice(), [1, 2, 11, 12]);
+}
+
+#[derive(Debug)]
+pub enum ImageError {
+    Io(io::Error),
+    /// The file isn't in the format it claims to be
+    Format(String),
+    /// The file is fine, but holds a different kind of pixel than was asked for
+    Unsupported(String),
+    Dimensions {
+        width: usize,
+        height: usize,
+        pixels: usize,
+    },
+}
+
+impl Display for ImageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageError::Io(e) => write!(f, "{}", e),
+            ImageError::Format(reason) => write!(f, "malformed image: {}", reason),
+            ImageError::Unsupported(reason) => write!(f, "unsupported image: {}", reason),
+            ImageError::Dimensions {
+                width,
+                height,
+                pixels,
+            } => write!(
+                f,
+                "{} pixels can't make a {}x{} image",
+                pixels, width, height
+            ),
+        }
+    }
+}
+
+impl Error for ImageError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ImageError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ImageError {
+    fn from(e: io::Error) -> Self {
+        ImageError::Io(e)
+    }
+}
+
+/// A grid of pixels, stored row by row. Pixels are addressed as `(x, y)` from the top left.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image<P> {
+    width: usize,
+    height: usize,
+    pixels: Vec<P>,
+}
+
+impl<P: Copy + Default> Image<P> {
+    pub fn new(width: usize, height: usize) -> Self {
+        Image::filled(width, height, P::default())
+    }
+}
+
+impl<P: Copy> Image<P> {
+    pub fn filled(width: usize, height: usize, pixel: P) -> Self {
+        Image {
+            width,
+            height,
+            pixels: vec![pixel; width * height],
+        }
+    }
+
+    /// Copy `width` by `height` pixels starting at `(x, y)` into a new image
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Self {
+        self.view(x, y, width, height).to_image()
+    }
+
+    pub fn flip_horizontal(&self) -> Self {
+        Image::from_fn(self.width, self.height, |x, y| {
+            self[(self.width - 1 - x, y)]
+        })
+    }
+
+    pub fn flip_vertical(&self) -> Self {
+        Image::from_fn(self.width, self.height, |x, y| {
+            self[(x, self.height - 1 - y)]
+        })
+    }
+
+    /// Rotate a quarter turn clockwise
+    pub fn rotate90(&self) -> Self {
+        Image::from_fn(self.height, self.width, |x, y| {
+            self[(y, self.height - 1 - x)]
+        })
+    }
+
+    pub fn rotate180(&self) -> Self {
+        Image::from_fn(self.width, self.height, |x, y| {
+            self[(self.width - 1 - x, self.height - 1 - y)]
+        })
+    }
+
+    /// Rotate a quarter turn anticlockwise
+    pub fn rotate270(&self) -> Self {
+        Image::from_fn(self.height, self.width, |x, y| {
+            self[(self.width - 1 - y, x)]
+        })
+    }
+}
+
+impl<P> Image<P> {
+    /// Build an image from its pixels in row order
+    pub fn from_vec(width: usize, height: usize, pixels: Vec<P>) -> Result<Self, ImageError> {
+        if pixels.len() != width * height {
+            return Err(ImageError::Dimensions {
+                width,
+                height,
+                pixels: pixels.len(),
+            });
+        }
+        Ok(Image {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn from_fn<F: FnMut(usize, usize) -> P>(width: usize, height: usize, mut f: F) -> Self {
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.push(f(x, y));
+            }
+        }
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    pub fn as_slice(&self) -> &[P] {
+        &self.pixels
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [P] {
+        &mut self.pixels
+    }
+
+    pub fn into_vec(self) -> Vec<P> {
+        self.pixels
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&P> {
+        (x < self.width && y < self.height).then(|| &self.pixels[y * self.width + x])
+    }
+
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut P> {
+        (x < self.width && y < self.height).then(|| &mut self.pixels[y * self.width + x])
+    }
+
+    /// Every pixel, row by row
+    pub fn pixels(&self) -> std::slice::Iter<'_, P> {
+        self.pixels.iter()
+    }
+
+    pub fn pixels_mut(&mut self) -> std::slice::IterMut<'_, P> {
+        self.pixels.iter_mut()
+    }
+
+    /// Every pixel along with its `(x, y)`
+    pub fn enumerate_pixels(&self) -> impl Iterator<Item = (usize, usize, &P)> {
+        let width = self.width.max(1);
+        self.pixels
+            .iter()
+            .enumerate()
+            .map(move |(i, p)| (i % width, i / width, p))
+    }
+
+    pub fn rows(&self) -> ChunksExact<'_, P> {
+        self.pixels.chunks_exact(self.width.max(1))
+    }
+
+    pub fn rows_mut(&mut self) -> ChunksExactMut<'_, P> {
+        self.pixels.chunks_exact_mut(self.width.max(1))
+    }
+
+    pub fn map<Q, F: FnMut(&P) -> Q>(&self, f: F) -> Image<Q> {
+        Image {
+            width: self.width,
+            height: self.height,
+            pixels: self.pixels.iter().map(f).collect(),
+        }
+    }
+
+    /// Borrow a `width` by `height` rectangle starting at `(x, y)`, without copying. Panics if
+    /// it doesn't fit inside the image.
+    pub fn view(&self, x: usize, y: usize, width: usize, height: usize) -> View<'_, P> {
+        self.check_bounds(x, y, width, height);
+        View::new(&self.pixels, self.width, x, y, width, height)
+    }
+
+    pub fn view_mut(&mut self, x: usize, y: usize, width: usize, height: usize) -> ViewMut<'_, P> {
+        self.check_bounds(x, y, width, height);
+        ViewMut::new(&mut self.pixels, self.width, x, y, width, height)
+    }
+
+    fn check_bounds(&self, x: usize, y: usize, width: usize, height: usize) {
+        assert!(
+            fits(x, width, self.width) && fits(y, height, self.height),
+            "{}x{} at ({}, {}) is outside a {}x{} image",
+            width,
+            height,
+            x,
+            y,
+            self.width,
+            self.height
+        );
+    }
+}
+
+/// Whether `len` items starting at `start` fit within `limit`, without overflowing on the way
+fn fits(start: usize, len: usize, limit: usize) -> bool {
+    start.checked_add(len).is_some_and(|end| end <= limit)
+}
+
+impl<P: Pixel<Channel: FileChannel>> Image<P> {
+    /// Every channel of every pixel as big-endian bytes, the way image files store them
+    pub(crate) fn to_be_bytes(&self) -> Vec<u8> {
+        let bytes = P::Channel::BYTES;
+        let mut raster = Vec::with_capacity(self.pixels.len() * P::CHANNELS * bytes);
+        for pixel in &self.pixels {
+            for i in 0..P::CHANNELS {
+                let sample = pixel.channel(i).to_i128().unwrap_or_default() as u16;
+                raster.extend_from_slice(&sample.to_be_bytes()[2 - bytes..]);
+            }
+        }
+        raster
+    }
+}
+
+/// A row of pixels
+impl<P> Index<usize> for Image<P> {
+    type Output = [P];
+
+    fn index(&self, index: usize) -> &Self::Output {
+        let start_pos = index * self.width;
+        let end_pos = start_pos + self.width;
+        &self.pixels[start_pos..end_pos]
+    }
+}
+
+impl<P> IndexMut<usize> for Image<P> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        let start_pos = index * self.width;
+        let end_pos = start_pos + self.width;
+        &mut self.pixels[start_pos..end_pos]
+    }
+}
+
+/// The pixel at `(x, y)`
+impl<P> Index<(usize, usize)> for Image<P> {
+    type Output = P;
+
+    fn index(&self, (x, y): (usize, usize)) -> &Self::Output {
+        self.get(x, y).unwrap_or_else(|| {
+            panic!(
+                "({}, {}) is outside a {}x{} image",
+                x, y, self.width, self.height
+            )
+        })
+    }
+}
+
+impl<P> IndexMut<(usize, usize)> for Image<P> {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut Self::Output {
+        let (width, height) = self.dimensions();
+        self.get_mut(x, y)
+            .unwrap_or_else(|| panic!("({}, {}) is outside a {}x{} image", x, y, width, height))
+    }
+}
@@ -0,0 +1,425 @@
+//! Convolution and the usual filters built from it. Everything works on any `Pixel`, one channel
+//! at a time, in `f64` so that nothing is lost between the passes of a separable filter.
+
+use super::{Channel, Image, ImageError, Pixel};
+use crate::numeric::Num;
+
+#[cfg(test)]
+use super::Rgb;
+
+#[test]
+fn test_border_modes() {
+    let resolve = |border: Border| (-3..8).map(|i| border.resolve(i, 5)).collect::<Vec<_>>();
+    assert_eq!(resolve(Border::Clamp), [0, 0, 0, 0, 1, 2, 3, 4, 4, 4, 4]);
+    assert_eq!(resolve(Border::Wrap), [2, 3, 4, 0, 1, 2, 3, 4, 0, 1, 2]);
+    assert_eq!(resolve(Border::Mirror), [3, 2, 1, 0, 1, 2, 3, 4, 3, 2, 1]);
+    assert_eq!(Border::Mirror.resolve(-2, 1), 0);
+}
+
+#[test]
+fn test_convolve() {
+    let image = Image::from_fn(4, 3, |x, y| (x * 40 + y * 7) as u8);
+    let filter = Filter::default();
+    assert_eq!(filter.convolve(&image, &Kernel::identity()), image);
+
+    // Shift everything one pixel left, with the right edge wrapping round
+    let shift = Kernel::new(3, 1, vec![0.0, 0.0, 1.0]).unwrap();
+    let wrapped = Filter::new(Border::Wrap).convolve(&image, &shift);
+    assert_eq!(wrapped[0], [40, 80, 120, 0]);
+    assert!(Kernel::new(3, 3, vec![1.0; 8]).is_err());
+}
+
+#[test]
+fn test_blurs_keep_flat_images_flat() {
+    let flat = Image::filled(9, 7, 1234_u16);
+    let filter = Filter::new(Border::Mirror);
+    assert_eq!(filter.box_blur(&flat, 2), flat);
+    assert_eq!(filter.gaussian_blur(&flat, 1.5), flat);
+
+    let spot = Image::from_fn(9, 9, |x, y| if (x, y) == (4, 4) { 1.0_f32 } else { 0.0 });
+    let blurred = filter.gaussian_blur(&spot, 1.0);
+    let total = blurred.pixels().sum::<f32>();
+    assert!((total - 1.0).abs() < 1e-4);
+    assert!(blurred[(4, 4)] > blurred[(3, 4)] && blurred[(3, 4)] > blurred[(2, 4)]);
+    assert_eq!(blurred[(3, 4)], blurred[(5, 4)]);
+    assert_eq!(filter.gaussian_blur(&spot, 0.0), spot);
+    assert_eq!(filter.gaussian_blur(&spot, -1.0), spot);
+}
+
+#[test]
+fn test_sobel_finds_edges() {
+    let image = Image::from_fn(6, 4, |x, _| if x < 3 { 0_u8 } else { 200 });
+    let edges = Filter::default().sobel(&image);
+    assert_eq!(edges[(0, 1)], 0);
+    assert_eq!(edges[(5, 1)], 0);
+    // Full intensity either side of the step
+    assert_eq!(edges[(2, 1)], 255);
+    assert_eq!(edges[(3, 1)], 255);
+}
+
+#[test]
+fn test_median_removes_noise() {
+    let mut image = Image::filled(5, 5, Rgb::new(10_u8, 20, 30));
+    image[(2, 2)] = Rgb::new(255, 0, 255);
+    let cleaned = Filter::default().median(&image, 1);
+    assert_eq!(cleaned, Image::filled(5, 5, Rgb::new(10, 20, 30)));
+}
+
+#[test]
+fn test_equalize() {
+    let image = Image::from_fn(4, 1, |x, _| 100 + x as u8);
+    assert_eq!(equalize(&image).as_slice(), [0, 85, 170, 255]);
+    let flat = Image::filled(3, 3, 0.5_f32);
+    assert_eq!(equalize(&flat), flat);
+}
+
+#[test]
+fn test_threads_match() {
+    let image = Image::from_fn(37, 23, |x, y| ((x * y) % 17) as f32 / 17.0);
+    let serial = Filter::default();
+    let parallel = Filter {
+        threads: 4,
+        ..Filter::default()
+    };
+    assert_eq!(
+        serial.gaussian_blur(&image, 2.0),
+        parallel.gaussian_blur(&image, 2.0)
+    );
+    assert_eq!(serial.median(&image, 2), parallel.median(&image, 2));
+    assert_eq!(serial.sobel(&image), parallel.sobel(&image));
+}
+
+/// What to do when a filter reaches past the edge of the image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Border {
+    /// Repeat the edge pixel
+    #[default]
+    Clamp,
+    /// Carry on from the opposite edge
+    Wrap,
+    /// Reflect about the edge pixel, without repeating it
+    Mirror,
+}
+
+impl Border {
+    /// Map a possibly out-of-range coordinate into `0..len`
+    pub fn resolve(self, i: isize, len: usize) -> usize {
+        let last = len as isize - 1;
+        match self {
+            Border::Clamp => i.clamp(0, last) as usize,
+            Border::Wrap => i.rem_euclid(len as isize) as usize,
+            Border::Mirror if len == 1 => 0,
+            Border::Mirror => {
+                let period = 2 * last;
+                let i = i.rem_euclid(period);
+                (if i > last { period - i } else { i }) as usize
+            }
+        }
+    }
+}
+
+/// Weights to multiply a neighbourhood of pixels by, centred on the middle of the kernel
+#[derive(Debug, Clone, PartialEq)]
+pub struct Kernel {
+    width: usize,
+    height: usize,
+    weights: Vec<f64>,
+}
+
+impl Kernel {
+    /// The weights go row by row. Both sides have to be odd so that there's a middle.
+    pub fn new(width: usize, height: usize, weights: Vec<f64>) -> Result<Self, ImageError> {
+        if weights.len() != width * height || width.is_multiple_of(2) || height.is_multiple_of(2) {
+            return Err(ImageError::Dimensions {
+                width,
+                height,
+                pixels: weights.len(),
+            });
+        }
+        Ok(Kernel {
+            width,
+            height,
+            weights,
+        })
+    }
+
+    pub fn identity() -> Self {
+        Kernel {
+            width: 1,
+            height: 1,
+            weights: vec![1.0],
+        }
+    }
+
+    /// The average of a square `2 * radius + 1` pixels across
+    pub fn box_blur(radius: usize) -> Self {
+        let side = 2 * radius + 1;
+        Kernel {
+            width: side,
+            height: side,
+            weights: vec![1.0 / (side * side) as f64; side * side],
+        }
+    }
+
+    /// One row of a Gaussian, reaching out three standard deviations. Use it along with its
+    /// `transpose`, which is much cheaper than the equivalent square kernel. A `sigma` of zero or
+    /// less blurs nothing, so it gives the identity.
+    pub fn gaussian(sigma: f64) -> Self {
+        if sigma <= 0.0 || sigma.is_nan() {
+            return Kernel::identity();
+        }
+        let radius = (3.0 * sigma).ceil().max(1.0) as isize;
+        let weights = (-radius..=radius)
+            .map(|i| (-(i * i) as f64 / (2.0 * sigma * sigma)).exp())
+            .collect::<Vec<_>>();
+        Kernel {
+            width: weights.len(),
+            height: 1,
+            weights,
+        }
+        .normalized()
+    }
+
+    /// The horizontal gradient
+    pub fn sobel_x() -> Self {
+        Kernel {
+            width: 3,
+            height: 3,
+            weights: vec![-1.0, 0.0, 1.0, -2.0, 0.0, 2.0, -1.0, 0.0, 1.0],
+        }
+    }
+
+    /// The vertical gradient
+    pub fn sobel_y() -> Self {
+        Kernel::sobel_x().transpose()
+    }
+
+    /// Scale the weights to add up to one, unless they add up to zero
+    pub fn normalized(mut self) -> Self {
+        let total = self.weights.iter().sum::<f64>();
+        if total != 0.0 {
+            self.weights.iter_mut().for_each(|w| *w /= total);
+        }
+        self
+    }
+
+    pub fn transpose(&self) -> Self {
+        Kernel {
+            width: self.height,
+            height: self.width,
+            weights: (0..self.width)
+                .flat_map(|x| (0..self.height).map(move |y| self.weights[y * self.width + x]))
+                .collect(),
+        }
+    }
+
+    fn weight(&self, dx: usize, dy: usize) -> f64 {
+        self.weights[dy * self.width + dx]
+    }
+}
+
+/// An image pulled apart into `f64` channels, which is what the filters actually work on
+struct Planes {
+    width: usize,
+    height: usize,
+    channels: usize,
+    /// Interleaved, so each pixel's channels sit together
+    data: Vec<f64>,
+}
+
+impl Planes {
+    fn from_image<P: Pixel>(image: &Image<P>) -> Self {
+        Planes {
+            width: image.width(),
+            height: image.height(),
+            channels: P::CHANNELS,
+            data: image
+                .pixels()
+                .flat_map(|p| (0..P::CHANNELS).map(|i| p.channel(i).to_f64()))
+                .collect(),
+        }
+    }
+
+    fn to_image<P: Pixel>(&self) -> Image<P> {
+        let pixels = self
+            .data
+            .chunks_exact(self.channels)
+            .map(|c| P::from_fn(|i| P::Channel::from_f64_clamped(c[i])))
+            .collect();
+        Image::from_vec(self.width, self.height, pixels).expect("Planes have the image's shape")
+    }
+
+    fn at(&self, x: usize, y: usize, channel: usize) -> f64 {
+        self.data[(y * self.width + x) * self.channels + channel]
+    }
+
+    /// Build new planes of the same shape, with `f` called for every `(x, y, channel)`. Rows
+    /// are shared out between `threads` threads.
+    fn generate<F>(&self, threads: usize, f: F) -> Planes
+    where
+        F: Fn(usize, usize, usize) -> f64 + Sync,
+    {
+        let mut data = vec![0.0; self.data.len()];
+        let row_len = (self.width * self.channels).max(1);
+        let rows_per_band = self.height.div_ceil(threads.max(1)).max(1);
+        let fill = |top: usize, band: &mut [f64]| {
+            for (offset, value) in band.iter_mut().enumerate() {
+                let (row, within) = (top + offset / row_len, offset % row_len);
+                *value = f(within / self.channels, row, within % self.channels);
+            }
+        };
+        if threads <= 1 {
+            fill(0, &mut data);
+        } else {
+            crossbeam_utils::thread::scope(|scope| {
+                for (index, band) in data.chunks_mut(rows_per_band * row_len).enumerate() {
+                    let fill = &fill;
+                    scope.spawn(move |_| fill(index * rows_per_band, band));
+                }
+            })
+            .expect("A filter thread panicked");
+        }
+        Planes {
+            width: self.width,
+            height: self.height,
+            channels: self.channels,
+            data,
+        }
+    }
+
+    fn convolve(&self, kernel: &Kernel, border: Border, threads: usize) -> Planes {
+        let (half_w, half_h) = ((kernel.width / 2) as isize, (kernel.height / 2) as isize);
+        self.generate(threads, |x, y, channel| {
+            let mut total = 0.0;
+            for dy in 0..kernel.height {
+                let sy = border.resolve(y as isize + dy as isize - half_h, self.height);
+                for dx in 0..kernel.width {
+                    let sx = border.resolve(x as isize + dx as isize - half_w, self.width);
+                    total += kernel.weight(dx, dy) * self.at(sx, sy, channel);
+                }
+            }
+            total
+        })
+    }
+}
+
+/// How filters treat the edges of the image, and how many threads they use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Filter {
+    pub border: Border,
+    pub threads: usize,
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter {
+            border: Border::default(),
+            threads: 1,
+        }
+    }
+}
+
+impl Filter {
+    pub fn new(border: Border) -> Self {
+        Filter {
+            border,
+            ..Filter::default()
+        }
+    }
+
+    /// Spread the work over every available core
+    pub fn parallel(border: Border) -> Self {
+        Filter {
+            border,
+            threads: std::thread::available_parallelism().map_or(4, |n| n.get()),
+        }
+    }
+
+    pub fn convolve<P: Pixel>(&self, image: &Image<P>, kernel: &Kernel) -> Image<P> {
+        Planes::from_image(image)
+            .convolve(kernel, self.border, self.threads)
+            .to_image()
+    }
+
+    pub fn box_blur<P: Pixel>(&self, image: &Image<P>, radius: usize) -> Image<P> {
+        self.convolve(image, &Kernel::box_blur(radius))
+    }
+
+    pub fn gaussian_blur<P: Pixel>(&self, image: &Image<P>, sigma: f64) -> Image<P> {
+        let kernel = Kernel::gaussian(sigma);
+        Planes::from_image(image)
+            .convolve(&kernel, self.border, self.threads)
+            .convolve(&kernel.transpose(), self.border, self.threads)
+            .to_image()
+    }
+
+    /// The size of the gradient at each pixel, in each channel
+    pub fn sobel<P: Pixel>(&self, image: &Image<P>) -> Image<P> {
+        let planes = Planes::from_image(image);
+        let gx = planes.convolve(&Kernel::sobel_x(), self.border, self.threads);
+        let gy = planes.convolve(&Kernel::sobel_y(), self.border, self.threads);
+        planes
+            .generate(self.threads, |x, y, c| gx.at(x, y, c).hypot(gy.at(x, y, c)))
+            .to_image()
+    }
+
+    /// Replace each channel with its median over a square `2 * radius + 1` pixels across, which
+    /// gets rid of speckles without blurring edges
+    pub fn median<P: Pixel>(&self, image: &Image<P>, radius: usize) -> Image<P> {
+        let planes = Planes::from_image(image);
+        let radius = radius as isize;
+        planes
+            .generate(self.threads, |x, y, channel| {
+                let mut values = Vec::with_capacity(((2 * radius + 1) * (2 * radius + 1)) as usize);
+                for dy in -radius..=radius {
+                    let sy = self.border.resolve(y as isize + dy, planes.height);
+                    for dx in -radius..=radius {
+                        let sx = self.border.resolve(x as isize + dx, planes.width);
+                        values.push(planes.at(sx, sy, channel));
+                    }
+                }
+                let middle = values.len() / 2;
+                *values.select_nth_unstable_by(middle, f64::total_cmp).1
+            })
+            .to_image()
+    }
+}
+
+/// Stretch each channel so that its values are spread evenly over the whole range. Integer
+/// channels are binned by value, and float channels into 256 levels.
+pub fn equalize<P: Pixel>(image: &Image<P>) -> Image<P> {
+    let full = P::Channel::FULL.to_f64();
+    let levels = if P::Channel::FULL == P::Channel::ONE {
+        256
+    } else {
+        (full as usize + 1).min(1 << 16)
+    };
+    let bin = |value: f64| ((value / full).clamp(0.0, 1.0) * (levels - 1) as f64).round() as usize;
+
+    let mut planes = Planes::from_image(image);
+    for channel in 0..planes.channels {
+        let mut cumulative = vec![0_usize; levels];
+        for value in planes.data.iter().skip(channel).step_by(planes.channels) {
+            cumulative[bin(*value)] += 1;
+        }
+        for i in 1..levels {
+            cumulative[i] += cumulative[i - 1];
+        }
+        let total = planes.width * planes.height;
+        let lowest = cumulative.iter().copied().find(|&n| n > 0).unwrap_or(0);
+        if total == lowest {
+            // Only one value, so there's nothing to spread out
+            continue;
+        }
+        for value in planes
+            .data
+            .iter_mut()
+            .skip(channel)
+            .step_by(planes.channels)
+        {
+            let rank = (cumulative[bin(*value)] - lowest) as f64 / (total - lowest) as f64;
+            *value = rank * full;
+        }
+    }
+    planes.to_image()
+}
@@ -0,0 +1,221 @@
+use crate::numeric::Num;
+
+#[test]
+fn test_channels() {
+    let rgb = Rgb::new(1_u8, 2, 3);
+    assert_eq!(rgb.channels(), [1, 2, 3]);
+    assert_eq!(Rgb::from_channels(&[1, 2, 3]), rgb);
+    assert_eq!(rgb.map_channels(|c| c * 2), Rgb::new(2, 4, 6));
+    assert_eq!(Rgba::from(rgb), Rgba::new(1, 2, 3, 255));
+    assert_eq!(Rgba::from(Rgb::new(0.5_f32, 0.5, 0.5)).a, 1.0);
+    assert_eq!(Rgb::from(Gray(7_u16)), Rgb::new(7, 7, 7));
+    assert_eq!(Gray::from(Rgb::new(255_u8, 255, 255)), Gray(255));
+    assert_eq!(Gray::from(Rgb::new(0_u8, 255, 0)), Gray(150));
+    assert_eq!(9_u8.channels(), [9]);
+    assert_eq!(u8::from_f64_clamped(-3.0), 0);
+    assert_eq!(u16::from_f64_clamped(1e9), u16::MAX);
+    assert_eq!(f32::from_f64_clamped(1.5), 1.0);
+}
+
+/// The numbers which pixels are made of, and the value which stands for full intensity
+pub trait Channel: Num {
+    const FULL: Self;
+
+    /// Clamp into `0..=FULL`, rounding to the nearest whole number for integer channels
+    fn from_f64_clamped(value: f64) -> Self;
+}
+
+macro_rules! impl_integer_channel {
+    ( $( $t:ident )* ) => {
+        $(
+            impl Channel for $t {
+                const FULL: Self = $t::MAX;
+
+                fn from_f64_clamped(value: f64) -> Self {
+                    // `as` saturates, and maps NaN to zero
+                    value.round() as $t
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_float_channel {
+    ( $( $t:ident )* ) => {
+        $(
+            impl Channel for $t {
+                const FULL: Self = 1.0;
+
+                fn from_f64_clamped(value: f64) -> Self {
+                    value.clamp(0.0, 1.0) as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_integer_channel!(u8 u16 u32);
+
+/// Channels which image files can hold, stored as this many big-endian bytes
+pub trait FileChannel: Channel {
+    const BYTES: usize;
+}
+
+impl FileChannel for u8 {
+    const BYTES: usize = 1;
+}
+
+impl FileChannel for u16 {
+    const BYTES: usize = 2;
+}
+impl_float_channel!(f32 f64);
+
+/// A pixel made of a fixed number of channels of the same type
+pub trait Pixel: Copy + Default + PartialEq + std::fmt::Debug {
+    type Channel: Channel;
+    const CHANNELS: usize;
+
+    /// Panics if `index` isn't less than `CHANNELS`
+    fn channel(&self, index: usize) -> Self::Channel;
+
+    /// Build a pixel by asking for each channel in turn
+    fn from_fn<F: FnMut(usize) -> Self::Channel>(f: F) -> Self;
+
+    fn channels(&self) -> Vec<Self::Channel> {
+        (0..Self::CHANNELS).map(|i| self.channel(i)).collect()
+    }
+
+    /// Panics if there are fewer than `CHANNELS` values
+    fn from_channels(channels: &[Self::Channel]) -> Self {
+        Self::from_fn(|i| channels[i])
+    }
+
+    fn map_channels<F: FnMut(Self::Channel) -> Self::Channel>(&self, mut f: F) -> Self {
+        Self::from_fn(|i| f(self.channel(i)))
+    }
+}
+
+/// A bare number is a single-channel pixel, as in `Image<u8>`
+macro_rules! impl_bare_pixel {
+    ( $( $t:ident )* ) => {
+        $(
+            impl Pixel for $t {
+                type Channel = $t;
+                const CHANNELS: usize = 1;
+
+                fn channel(&self, index: usize) -> $t {
+                    assert_eq!(index, 0, "Channel out of range");
+                    *self
+                }
+
+                fn from_fn<F: FnMut(usize) -> $t>(mut f: F) -> Self {
+                    f(0)
+                }
+            }
+        )*
+    };
+}
+
+impl_bare_pixel!(u8 u16 u32 f32 f64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+pub struct Gray<T = u8>(pub T);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+pub struct Rgb<T = u8> {
+    pub r: T,
+    pub g: T,
+    pub b: T,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+pub struct Rgba<T = u8> {
+    pub r: T,
+    pub g: T,
+    pub b: T,
+    pub a: T,
+}
+
+impl<T> Rgb<T> {
+    pub const fn new(r: T, g: T, b: T) -> Self {
+        Rgb { r, g, b }
+    }
+}
+
+impl<T> Rgba<T> {
+    pub const fn new(r: T, g: T, b: T, a: T) -> Self {
+        Rgba { r, g, b, a }
+    }
+}
+
+impl<T: Channel> Pixel for Gray<T> {
+    type Channel = T;
+    const CHANNELS: usize = 1;
+
+    fn channel(&self, index: usize) -> T {
+        assert_eq!(index, 0, "Channel out of range");
+        self.0
+    }
+
+    fn from_fn<F: FnMut(usize) -> T>(mut f: F) -> Self {
+        Gray(f(0))
+    }
+}
+
+impl<T: Channel> Pixel for Rgb<T> {
+    type Channel = T;
+    const CHANNELS: usize = 3;
+
+    fn channel(&self, index: usize) -> T {
+        match index {
+            0 => self.r,
+            1 => self.g,
+            2 => self.b,
+            _ => panic!("Channel out of range"),
+        }
+    }
+
+    fn from_fn<F: FnMut(usize) -> T>(mut f: F) -> Self {
+        Rgb::new(f(0), f(1), f(2))
+    }
+}
+
+impl<T: Channel> Pixel for Rgba<T> {
+    type Channel = T;
+    const CHANNELS: usize = 4;
+
+    fn channel(&self, index: usize) -> T {
+        match index {
+            0 => self.r,
+            1 => self.g,
+            2 => self.b,
+            3 => self.a,
+            _ => panic!("Channel out of range"),
+        }
+    }
+
+    fn from_fn<F: FnMut(usize) -> T>(mut f: F) -> Self {
+        Rgba::new(f(0), f(1), f(2), f(3))
+    }
+}
+
+impl<T: Channel> From<Gray<T>> for Rgb<T> {
+    fn from(gray: Gray<T>) -> Self {
+        Rgb::new(gray.0, gray.0, gray.0)
+    }
+}
+
+/// Fully opaque
+impl<T: Channel> From<Rgb<T>> for Rgba<T> {
+    fn from(rgb: Rgb<T>) -> Self {
+        Rgba::new(rgb.r, rgb.g, rgb.b, T::FULL)
+    }
+}
+
+/// The perceived brightness, using the Rec. 601 weights
+impl<T: Channel> From<Rgb<T>> for Gray<T> {
+    fn from(rgb: Rgb<T>) -> Self {
+        let luma = 0.299 * rgb.r.to_f64() + 0.587 * rgb.g.to_f64() + 0.114 * rgb.b.to_f64();
+        Gray(T::from_f64_clamped(luma))
+    }
+}
@@ -0,0 +1,125 @@
+//! PNG support, which needs the `png` feature
+
+use super::{Channel, FileChannel, Gray, Image, ImageError, Pixel, Rgb, Rgba};
+use crate::numeric::Num;
+use png::{BitDepth, ColorType, Decoder, Encoder, Transformations};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+#[test]
+fn test_round_trip() {
+    let image = Image::from_fn(5, 3, |x, y| Rgba::new(x as u8, y as u8, 9, 200));
+    let mut bytes = vec![];
+    image.write_png(&mut bytes).unwrap();
+    assert!(bytes.starts_with(b"\x89PNG"));
+    assert!(bytes.ends_with(b"IEND\xaeB`\x82"));
+    assert_eq!(Image::<Rgba>::read_png(&bytes[..]).unwrap(), image);
+
+    let deep = Image::from_fn(2, 2, |x, y| Gray((x * 30_000 + y) as u16));
+    let mut bytes = vec![];
+    deep.write_png(&mut bytes).unwrap();
+    assert_eq!(Image::<Gray<u16>>::read_png(&bytes[..]).unwrap(), deep);
+
+    let error = Image::<Rgb>::read_png(&bytes[..]).unwrap_err();
+    assert!(matches!(error, ImageError::Unsupported(_)));
+}
+
+/// Pixels which can be stored in a PNG file
+pub trait PngPixel: Pixel<Channel: FileChannel> {
+    const COLOR: ColorType;
+}
+
+macro_rules! impl_png_pixel {
+    ( $color:ident: $( $t:ty ),* ) => {
+        $(
+            impl PngPixel for $t {
+                const COLOR: ColorType = ColorType::$color;
+            }
+        )*
+    };
+}
+
+impl_png_pixel!(Grayscale: u8, u16, Gray<u8>, Gray<u16>);
+impl_png_pixel!(Rgb: Rgb<u8>, Rgb<u16>);
+impl_png_pixel!(Rgba: Rgba<u8>, Rgba<u16>);
+
+fn depth<P: PngPixel>() -> BitDepth {
+    if P::Channel::BYTES == 1 {
+        BitDepth::Eight
+    } else {
+        BitDepth::Sixteen
+    }
+}
+
+impl<P: PngPixel> Image<P> {
+    /// Palettes and bit depths under 8 are expanded, but the colour type and the remaining
+    /// depth have to match `P`
+    pub fn read_png<R: Read>(reader: R) -> Result<Self, ImageError> {
+        let mut decoder = Decoder::new(reader);
+        decoder.set_transformations(Transformations::EXPAND);
+        let mut reader = decoder
+            .read_info()
+            .map_err(|e| ImageError::Format(e.to_string()))?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader
+            .next_frame(&mut buffer)
+            .map_err(|e| ImageError::Format(e.to_string()))?;
+        if (info.color_type, info.bit_depth) != (P::COLOR, depth::<P>()) {
+            return Err(ImageError::Unsupported(format!(
+                "expected {:?} at {:?}, found {:?} at {:?}",
+                P::COLOR,
+                depth::<P>(),
+                info.color_type,
+                info.bit_depth
+            )));
+        }
+
+        let bytes = P::Channel::BYTES;
+        let (width, height) = (info.width as usize, info.height as usize);
+        let pixels = buffer
+            .chunks(info.line_size)
+            .take(height)
+            .flat_map(|line| line[..width * P::CHANNELS * bytes].chunks_exact(P::CHANNELS * bytes))
+            .map(|pixel| {
+                P::from_fn(|i| {
+                    let sample = pixel[i * bytes..(i + 1) * bytes]
+                        .iter()
+                        .fold(0, |n, &b| n << 8 | b as i128);
+                    P::Channel::from_i128(sample).unwrap_or(P::Channel::FULL)
+                })
+            })
+            .collect();
+        Image::from_vec(width, height, pixels)
+    }
+
+    pub fn write_png<W: Write>(&self, mut writer: W) -> Result<(), ImageError> {
+        let dimension = |n: usize| {
+            u32::try_from(n).map_err(|_| ImageError::Unsupported(format!("{} is too big", n)))
+        };
+        let mut encoder = Encoder::new(
+            &mut writer,
+            dimension(self.width())?,
+            dimension(self.height())?,
+        );
+        encoder.set_color(P::COLOR);
+        encoder.set_depth(depth::<P>());
+        // Finishing writes the end chunk and reports any error, which dropping would swallow
+        encoder
+            .write_header()
+            .and_then(|mut png| {
+                png.write_image_data(&self.to_be_bytes())?;
+                png.finish()
+            })
+            .map_err(|e| ImageError::Format(e.to_string()))?;
+        Ok(writer.flush()?)
+    }
+
+    pub fn open_png<Q: AsRef<Path>>(path: Q) -> Result<Self, ImageError> {
+        Image::read_png(BufReader::new(File::open(path)?))
+    }
+
+    pub fn save_png<Q: AsRef<Path>>(&self, path: Q) -> Result<(), ImageError> {
+        self.write_png(BufWriter::new(File::create(path)?))
+    }
+}
@@ -0,0 +1,228 @@
+//! The Netpbm formats: PGM for gray images and PPM for colour ones. Both the binary and the plain
+//! text variants can be read, and the binary one is written.
+
+use super::{Channel, FileChannel, Gray, Image, ImageError, Pixel, Rgb};
+use crate::numeric::Num;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+#[test]
+fn test_round_trip() {
+    let image = Image::from_fn(3, 2, |x, y| Rgb::new(x as u8 * 100, y as u8 * 200, 7));
+    let mut bytes = vec![];
+    image.write_pnm(&mut bytes).unwrap();
+    assert!(bytes.starts_with(b"P6\n3 2\n255\n"));
+    assert_eq!(bytes.len(), 11 + 3 * 2 * 3);
+    assert_eq!(Image::<Rgb>::read_pnm(&bytes[..]).unwrap(), image);
+
+    let deep = Image::from_fn(2, 2, |x, y| Gray((x * 1000 + y) as u16));
+    let mut bytes = vec![];
+    deep.write_pnm(&mut bytes).unwrap();
+    assert!(bytes.starts_with(b"P5\n2 2\n65535\n"));
+    assert_eq!(Image::<Gray<u16>>::read_pnm(&bytes[..]).unwrap(), deep);
+}
+
+#[test]
+fn test_read_plain_with_comments() {
+    let text = b"P2\n# A comment\n3 1 # and another\n15\n0 5\n15\n";
+    let image = Image::<u8>::read_pnm(&text[..]).unwrap();
+    // Samples are scaled from a maximum of 15 up to 255
+    assert_eq!(image.as_slice(), [0, 85, 255]);
+
+    let text = b"P3 1 1 255 1 2 3";
+    let image = Image::<Rgb>::read_pnm(&text[..]).unwrap();
+    assert_eq!(image[(0, 0)], Rgb::new(1, 2, 3));
+}
+
+#[test]
+fn test_read_errors() {
+    let read = |bytes: &[u8]| Image::<Gray>::read_pnm(bytes).unwrap_err().to_string();
+    assert_eq!(
+        read(b"P6 1 1 255 abc"),
+        "unsupported image: expected P5 or P2, found P6"
+    );
+    assert_eq!(
+        read(b"P5 1 1 65535 ab"),
+        "unsupported image: 16 bit samples need a 16 bit channel"
+    );
+    assert_eq!(
+        read(b"P5 2 2 255 abc"),
+        "malformed image: expected 4 bytes of pixels, found 3"
+    );
+    assert_eq!(
+        read(b"P2 1 1 255 x"),
+        "malformed image: expected a number, found \"x\""
+    );
+    assert_eq!(read(b"P5 1"), "malformed image: the header ends early");
+    assert_eq!(
+        read(b"P5 4294967296 4294967296 255 "),
+        "malformed image: 4294967296x4294967296 is too large"
+    );
+    let wide = format!("P5 {} 1 65535 ", usize::MAX / 2 + 1);
+    assert_eq!(
+        Image::<u16>::read_pnm(wide.as_bytes())
+            .unwrap_err()
+            .to_string(),
+        format!("malformed image: {}x1 is too large", usize::MAX / 2 + 1)
+    );
+}
+
+/// Pixels which can be stored in a PGM or PPM file
+pub trait PnmPixel: Pixel<Channel: FileChannel> {
+    const BINARY_MAGIC: &'static str;
+    const PLAIN_MAGIC: &'static str;
+}
+
+macro_rules! impl_pnm_pixel {
+    ( $binary:literal $plain:literal: $( $t:ty ),* ) => {
+        $(
+            impl PnmPixel for $t {
+                const BINARY_MAGIC: &'static str = $binary;
+                const PLAIN_MAGIC: &'static str = $plain;
+            }
+        )*
+    };
+}
+
+impl_pnm_pixel!("P5" "P2": u8, u16, Gray<u8>, Gray<u16>);
+impl_pnm_pixel!("P6" "P3": Rgb<u8>, Rgb<u16>);
+
+/// Walks through the whitespace-separated header, skipping comments
+struct Header<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Header<'a> {
+    fn token(&mut self) -> Result<&'a str, ImageError> {
+        loop {
+            match self.bytes.get(self.position) {
+                Some(b'#') => {
+                    while self.bytes.get(self.position).is_some_and(|&b| b != b'\n') {
+                        self.position += 1;
+                    }
+                }
+                Some(b) if b.is_ascii_whitespace() => self.position += 1,
+                Some(_) => break,
+                None => return Err(ImageError::Format("the header ends early".to_string())),
+            }
+        }
+        let start = self.position;
+        while self
+            .bytes
+            .get(self.position)
+            .is_some_and(|b| !b.is_ascii_whitespace() && *b != b'#')
+        {
+            self.position += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.position])
+            .map_err(|_| ImageError::Format("the header isn't text".to_string()))
+    }
+
+    fn number(&mut self) -> Result<usize, ImageError> {
+        let token = self.token()?;
+        token
+            .parse()
+            .map_err(|_| ImageError::Format(format!("expected a number, found {:?}", token)))
+    }
+}
+
+impl<P: PnmPixel> Image<P> {
+    pub fn read_pnm<R: Read>(mut reader: R) -> Result<Self, ImageError> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+        let mut header = Header {
+            bytes: &bytes,
+            position: 0,
+        };
+        let magic = header.token()?;
+        let plain = match magic {
+            _ if magic == P::BINARY_MAGIC => false,
+            _ if magic == P::PLAIN_MAGIC => true,
+            _ => {
+                return Err(ImageError::Unsupported(format!(
+                    "expected {} or {}, found {}",
+                    P::BINARY_MAGIC,
+                    P::PLAIN_MAGIC,
+                    magic
+                )))
+            }
+        };
+        let width = header.number()?;
+        let height = header.number()?;
+        let max = header.number()?;
+        if max == 0 || max > u16::MAX as usize {
+            return Err(ImageError::Format(format!("bad maximum value {}", max)));
+        }
+        if max > 255 && P::Channel::BYTES == 1 {
+            return Err(ImageError::Unsupported(
+                "16 bit samples need a 16 bit channel".to_string(),
+            ));
+        }
+        let too_large = || ImageError::Format(format!("{}x{} is too large", width, height));
+        let count = width
+            .checked_mul(height)
+            .and_then(|pixels| pixels.checked_mul(P::CHANNELS))
+            .ok_or_else(too_large)?;
+
+        let samples = if plain {
+            (0..count)
+                .map(|_| header.number())
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            // Exactly one whitespace character separates the header from the pixels
+            let raster = bytes.get(header.position + 1..).unwrap_or_default();
+            let bytes_per_sample = if max > 255 { 2 } else { 1 };
+            let expected = count.checked_mul(bytes_per_sample).ok_or_else(too_large)?;
+            if raster.len() < expected {
+                return Err(ImageError::Format(format!(
+                    "expected {} bytes of pixels, found {}",
+                    expected,
+                    raster.len()
+                )));
+            }
+            raster
+                .chunks_exact(bytes_per_sample)
+                .take(count)
+                .map(|sample| sample.iter().fold(0, |n, &b| n << 8 | b as usize))
+                .collect()
+        };
+
+        let full = P::Channel::FULL.to_i128().unwrap_or_default() as usize;
+        let scale = |sample: usize| {
+            let sample = sample.min(max);
+            // Round to the nearest value on our scale
+            let scaled = (sample * full + max / 2) / max;
+            P::Channel::from_i128(scaled as i128).unwrap_or(P::Channel::FULL)
+        };
+        let pixels = samples
+            .chunks_exact(P::CHANNELS)
+            .map(|channels| P::from_fn(|i| scale(channels[i])))
+            .collect();
+        Image::from_vec(width, height, pixels)
+    }
+
+    /// Write the binary form, with samples running up to the channel's `FULL` value
+    pub fn write_pnm<W: Write>(&self, mut writer: W) -> Result<(), ImageError> {
+        let full = P::Channel::FULL.to_i128().unwrap_or_default();
+        write!(
+            writer,
+            "{}\n{} {}\n{}\n",
+            P::BINARY_MAGIC,
+            self.width(),
+            self.height(),
+            full
+        )?;
+        writer.write_all(&self.to_be_bytes())?;
+        Ok(writer.flush()?)
+    }
+
+    pub fn open_pnm<Q: AsRef<Path>>(path: Q) -> Result<Self, ImageError> {
+        Image::read_pnm(BufReader::new(File::open(path)?))
+    }
+
+    pub fn save_pnm<Q: AsRef<Path>>(&self, path: Q) -> Result<(), ImageError> {
+        self.write_pnm(BufWriter::new(File::create(path)?))
+    }
+}
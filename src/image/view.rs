@@ -0,0 +1,244 @@
+use super::{fits, Image};
+use std::ops::{Index, IndexMut};
+
+#[test]
+fn test_views_borrow() {
+    let mut image = Image::from_fn(4, 4, |x, y| (y * 10 + x) as u8);
+    {
+        let view = image.view(1, 1, 2, 3);
+        assert_eq!(view.dimensions(), (2, 3));
+        assert_eq!(view[(0, 0)], 11);
+        assert_eq!(
+            view.rows().collect::<Vec<_>>(),
+            [[11, 12], [21, 22], [31, 32]]
+        );
+        let inner = view.view(1, 1, 1, 2);
+        assert_eq!(inner.to_image().as_slice(), [22, 32]);
+        assert_eq!(view.get(2, 0), None);
+    }
+
+    let mut view = image.view_mut(2, 0, 2, 2);
+    view.fill(0);
+    view[(1, 1)] = 7;
+    for row in view.rows_mut() {
+        row[0] += 1;
+    }
+    assert_eq!(image[0], [0, 1, 1, 0]);
+    assert_eq!(image[1], [10, 11, 1, 7]);
+}
+
+#[test]
+#[should_panic]
+fn test_view_out_of_bounds() {
+    let image = Image::<u8>::new(4, 4);
+    image.view(3, 0, 2, 1);
+}
+
+#[test]
+#[should_panic(expected = "is outside a 4x4 image")]
+fn test_view_offset_overflows() {
+    let image = Image::<u8>::new(4, 4);
+    image.view(usize::MAX, 0, 2, 1);
+}
+
+#[test]
+#[should_panic(expected = "is outside a 2x2 view")]
+fn test_subview_offset_overflows() {
+    let image = Image::<u8>::new(4, 4);
+    image.view(0, 0, 2, 2).view(0, usize::MAX, 1, 2);
+}
+
+#[test]
+#[should_panic(expected = "Pasted view doesn't fit")]
+fn test_paste_offset_overflows() {
+    let source = Image::<u8>::new(2, 2);
+    let mut image = Image::<u8>::new(4, 4);
+    image
+        .view_mut(0, 0, 4, 4)
+        .paste(usize::MAX, 0, &source.view(0, 0, 2, 2));
+}
+
+/// A rectangle of an `Image`, borrowed rather than copied
+#[derive(Debug, Clone, Copy)]
+pub struct View<'a, P> {
+    /// The underlying pixels, starting at the view's top left
+    pixels: &'a [P],
+    /// How far apart rows are in `pixels`
+    stride: usize,
+    width: usize,
+    height: usize,
+}
+
+impl<'a, P> View<'a, P> {
+    pub(crate) fn new(
+        pixels: &'a [P],
+        stride: usize,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    ) -> Self {
+        View {
+            pixels: &pixels[(y * stride + x).min(pixels.len())..],
+            stride,
+            width,
+            height,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&'a P> {
+        (x < self.width && y < self.height).then(|| &self.pixels[y * self.stride + x])
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &'a [P]> {
+        let (pixels, stride, width) = (self.pixels, self.stride, self.width);
+        (0..self.height).map(move |y| &pixels[y * stride..y * stride + width])
+    }
+
+    pub fn pixels(&self) -> impl Iterator<Item = &'a P> {
+        self.rows().flatten()
+    }
+
+    /// A smaller view inside this one, with `(x, y)` relative to this view
+    pub fn view(&self, x: usize, y: usize, width: usize, height: usize) -> View<'a, P> {
+        assert!(
+            fits(x, width, self.width) && fits(y, height, self.height),
+            "{}x{} at ({}, {}) is outside a {}x{} view",
+            width,
+            height,
+            x,
+            y,
+            self.width,
+            self.height
+        );
+        View::new(self.pixels, self.stride, x, y, width, height)
+    }
+}
+
+impl<P: Copy> View<'_, P> {
+    pub fn to_image(&self) -> Image<P> {
+        Image::from_fn(self.width, self.height, |x, y| self[(x, y)])
+    }
+}
+
+impl<P> Index<(usize, usize)> for View<'_, P> {
+    type Output = P;
+
+    fn index(&self, (x, y): (usize, usize)) -> &Self::Output {
+        self.get(x, y)
+            .unwrap_or_else(|| panic!("({}, {}) is outside the view", x, y))
+    }
+}
+
+/// A rectangle of an `Image` which can be written to in place
+#[derive(Debug)]
+pub struct ViewMut<'a, P> {
+    pixels: &'a mut [P],
+    stride: usize,
+    width: usize,
+    height: usize,
+}
+
+impl<'a, P> ViewMut<'a, P> {
+    pub(crate) fn new(
+        pixels: &'a mut [P],
+        stride: usize,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    ) -> Self {
+        let start = (y * stride + x).min(pixels.len());
+        ViewMut {
+            pixels: &mut pixels[start..],
+            stride,
+            width,
+            height,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    pub fn as_view(&self) -> View<'_, P> {
+        View::new(self.pixels, self.stride, 0, 0, self.width, self.height)
+    }
+
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut P> {
+        (x < self.width && y < self.height).then(|| &mut self.pixels[y * self.stride + x])
+    }
+
+    pub fn rows_mut(&mut self) -> impl Iterator<Item = &mut [P]> {
+        let width = self.width;
+        self.pixels
+            .chunks_mut(self.stride.max(1))
+            .take(self.height)
+            .map(move |row| &mut row[..width])
+    }
+
+    pub fn fill(&mut self, pixel: P)
+    where
+        P: Copy,
+    {
+        for row in self.rows_mut() {
+            row.fill(pixel);
+        }
+    }
+
+    /// Copy `source` into this view with its top left at `(x, y)`. Panics if it doesn't fit.
+    pub fn paste(&mut self, x: usize, y: usize, source: &View<'_, P>)
+    where
+        P: Copy,
+    {
+        assert!(
+            fits(x, source.width(), self.width) && fits(y, source.height(), self.height),
+            "Pasted view doesn't fit"
+        );
+        for (dy, row) in source.rows().enumerate() {
+            let start = (y + dy) * self.stride + x;
+            self.pixels[start..start + row.len()].copy_from_slice(row);
+        }
+    }
+}
+
+impl<P> Index<(usize, usize)> for ViewMut<'_, P> {
+    type Output = P;
+
+    fn index(&self, (x, y): (usize, usize)) -> &Self::Output {
+        assert!(
+            x < self.width && y < self.height,
+            "({}, {}) is outside the view",
+            x,
+            y
+        );
+        &self.pixels[y * self.stride + x]
+    }
+}
+
+impl<P> IndexMut<(usize, usize)> for ViewMut<'_, P> {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut Self::Output {
+        self.get_mut(x, y)
+            .unwrap_or_else(|| panic!("({}, {}) is outside the view", x, y))
+    }
+}
@@ -0,0 +1,366 @@
+//! The `Interval` type from `chap_12`, with set operations, `IntervalSet` and interval
+//! arithmetic. Intervals are closed, so `[1, 3]` and `[3, 5]` overlap at 3.
+
+use std::cmp::Ordering;
+use std::ops::{Add, Mul, Sub};
+
+pub use tree::IntervalTree;
+
+pub mod tree;
+
+#[test]
+fn test_interval_ops() {
+    let a = Interval::new(1, 5);
+    let b = Interval::new(3, 8);
+    let c = Interval::new(6, 9);
+    assert!(a.overlaps(&b) && !a.overlaps(&c));
+    assert!(a.overlaps(&Interval::new(5, 6)));
+    assert_eq!(a.intersection(&b), Some(Interval::new(3, 5)));
+    assert_eq!(a.intersection(&c), None);
+    assert_eq!(a.union(&b), Some(Interval::new(1, 8)));
+    assert_eq!(a.union(&c), None);
+    assert_eq!(a.hull(&c), Interval::new(1, 9));
+    assert!(a.contains(&1) && a.contains(&5) && !a.contains(&6));
+    assert_eq!(b.length(), 5);
+    assert_eq!(Interval::point(2.5).length(), 0.0);
+    assert_eq!(Interval::try_new(3, 1), None);
+}
+
+#[test]
+#[should_panic]
+fn test_backwards_interval() {
+    Interval::new(2, 1);
+}
+
+#[test]
+fn test_arithmetic() {
+    let x = Interval::new(1.0, 2.0);
+    let y = Interval::new(-3.0, 0.5);
+    assert_eq!(x + y, Interval::new(-2.0, 2.5));
+    assert_eq!(x - y, Interval::new(0.5, 5.0));
+    assert_eq!(x * y, Interval::new(-6.0, 1.0));
+    assert_eq!(y * y, Interval::new(-1.5, 9.0));
+
+    // A measurement of 9.8 ± 0.1 over a time of 2 ± 0.05
+    let g = Interval::new(9.7, 9.9);
+    let t = Interval::new(1.95, 2.05);
+    let distance = g * t * t * Interval::point(0.5);
+    assert!(distance.contains(&19.6));
+    assert!(distance.lower > 18.4 && distance.upper < 20.9);
+}
+
+#[test]
+fn test_interval_set() {
+    let set = [(10, 12), (1, 3), (2, 5), (7, 7), (5, 6)]
+        .into_iter()
+        .map(|(lower, upper)| Interval::new(lower, upper))
+        .collect::<IntervalSet<_>>();
+    assert_eq!(
+        set.as_slice(),
+        [
+            Interval::new(1, 6),
+            Interval::new(7, 7),
+            Interval::new(10, 12)
+        ]
+    );
+    assert!(set.contains(&7) && set.contains(&11) && !set.contains(&8));
+
+    let other = IntervalSet::from(vec![Interval::new(0, 2), Interval::new(4, 11)]);
+    assert_eq!(set.union(&other).as_slice(), [Interval::new(0, 12)]);
+    assert_eq!(
+        set.intersection(&other).as_slice(),
+        [
+            Interval::new(1, 2),
+            Interval::new(4, 6),
+            Interval::new(7, 7),
+            Interval::new(10, 11)
+        ]
+    );
+    assert_eq!(
+        set.difference(&other).as_slice(),
+        [Interval::new(2, 4), Interval::new(11, 12)]
+    );
+    assert_eq!(
+        other.difference(&set).as_slice(),
+        [Interval::new(0, 1), Interval::new(6, 10)]
+    );
+    assert!(set.difference(&set).is_empty());
+}
+
+/// The closed interval `[lower, upper]`. The fields are private so that `lower <= upper` always
+/// holds, which `IntervalSet` and `IntervalTree` rely on.
+///
+/// ```compile_fail
+/// use programming_rust::interval::Interval;
+/// let backwards = Interval { lower: 5, upper: 1 };
+/// ```
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Interval<T> {
+    lower: T,
+    upper: T,
+}
+
+impl<T> Interval<T> {
+    pub fn lower(&self) -> &T {
+        &self.lower
+    }
+
+    pub fn upper(&self) -> &T {
+        &self.upper
+    }
+}
+
+impl<T: PartialOrd> Interval<T> {
+    /// Panics if `lower` is above `upper`
+    pub fn new(lower: T, upper: T) -> Interval<T> {
+        assert!(lower <= upper, "Interval's lower bound is above its upper");
+        Interval { lower, upper }
+    }
+
+    /// `None` if `lower` is above `upper`
+    pub fn try_new(lower: T, upper: T) -> Option<Interval<T>> {
+        (lower <= upper).then_some(Interval { lower, upper })
+    }
+
+    pub fn contains(&self, x: &T) -> bool {
+        self.lower <= *x && *x <= self.upper
+    }
+
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.lower <= other.upper && other.lower <= self.upper
+    }
+}
+
+impl<T: PartialOrd + Copy> Interval<T> {
+    pub fn point(x: T) -> Interval<T> {
+        Interval { lower: x, upper: x }
+    }
+
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        Interval::try_new(max(self.lower, other.lower), min(self.upper, other.upper))
+    }
+
+    /// The two together, if they overlap so that the result is still an interval
+    pub fn union(&self, other: &Self) -> Option<Self> {
+        self.overlaps(other).then(|| self.hull(other))
+    }
+
+    /// The smallest interval covering both
+    pub fn hull(&self, other: &Self) -> Self {
+        Interval {
+            lower: min(self.lower, other.lower),
+            upper: max(self.upper, other.upper),
+        }
+    }
+}
+
+impl<T: Sub<Output = T> + Copy> Interval<T> {
+    pub fn length(&self) -> T {
+        self.upper - self.lower
+    }
+}
+
+/// Less or greater when the intervals don't overlap, and incomparable when they do
+impl<T: PartialOrd> PartialOrd for Interval<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self == other {
+            Some(Ordering::Equal)
+        } else if self.lower > other.upper {
+            Some(Ordering::Greater)
+        } else if self.upper < other.lower {
+            Some(Ordering::Less)
+        } else {
+            None
+        }
+    }
+}
+
+fn min<T: PartialOrd>(a: T, b: T) -> T {
+    if b < a {
+        b
+    } else {
+        a
+    }
+}
+
+fn max<T: PartialOrd>(a: T, b: T) -> T {
+    if b > a {
+        b
+    } else {
+        a
+    }
+}
+
+/// Every value `x + y` can take, for `x` in one interval and `y` in the other
+impl<T: Add<Output = T>> Add for Interval<T> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Interval {
+            lower: self.lower + rhs.lower,
+            upper: self.upper + rhs.upper,
+        }
+    }
+}
+
+impl<T: Sub<Output = T>> Sub for Interval<T> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Interval {
+            lower: self.lower - rhs.upper,
+            upper: self.upper - rhs.lower,
+        }
+    }
+}
+
+/// Signs can flip the order, so the bounds come from whichever corner products are extreme
+impl<T: Mul<Output = T> + PartialOrd + Copy> Mul for Interval<T> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let products = [
+            self.lower * rhs.lower,
+            self.lower * rhs.upper,
+            self.upper * rhs.lower,
+            self.upper * rhs.upper,
+        ];
+        Interval {
+            lower: products.into_iter().reduce(min).expect("Four products"),
+            upper: products.into_iter().reduce(max).expect("Four products"),
+        }
+    }
+}
+
+/// A union of intervals, kept sorted and merged so that no two of them overlap
+#[derive(PartialEq, Debug, Clone)]
+pub struct IntervalSet<T> {
+    intervals: Vec<Interval<T>>,
+}
+
+impl<T> Default for IntervalSet<T> {
+    fn default() -> Self {
+        IntervalSet { intervals: vec![] }
+    }
+}
+
+impl<T: PartialOrd + Copy> IntervalSet<T> {
+    pub fn new() -> Self {
+        IntervalSet::default()
+    }
+
+    pub fn as_slice(&self) -> &[Interval<T>] {
+        &self.intervals
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Interval<T>> {
+        self.intervals.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.intervals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    pub fn contains(&self, x: &T) -> bool {
+        let after = self.intervals.partition_point(|it| it.upper < *x);
+        self.intervals.get(after).is_some_and(|it| it.contains(x))
+    }
+
+    pub fn insert(&mut self, interval: Interval<T>) {
+        let start = self
+            .intervals
+            .partition_point(|it| it.upper < interval.lower);
+        let end = self
+            .intervals
+            .partition_point(|it| it.lower <= interval.upper);
+        let merged = self.intervals[start..end]
+            .iter()
+            .fold(interval, |merged, it| merged.hull(it));
+        self.intervals.splice(start..end, [merged]);
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut union = self.clone();
+        for interval in other {
+            union.insert(*interval);
+        }
+        union
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut intervals = vec![];
+        let (mut i, mut j) = (0, 0);
+        while let (Some(a), Some(b)) = (self.intervals.get(i), other.intervals.get(j)) {
+            intervals.extend(a.intersection(b));
+            // Whichever ends first can't overlap anything further on
+            if a.upper < b.upper {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        IntervalSet::from(intervals)
+    }
+
+    /// Everything in `self` which isn't in `other`. The intervals stay closed, so the endpoints
+    /// shared with `other` are kept: `[0, 10]` minus `[3, 5]` is `[0, 3]` and `[5, 10]`, and taking
+    /// away a single point does nothing.
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut intervals = vec![];
+        for a in self {
+            let mut rest = a.lower;
+            let mut cut = false;
+            let first = other.intervals.partition_point(|b| b.upper < a.lower);
+            for b in other.intervals[first..]
+                .iter()
+                .take_while(|b| b.lower <= a.upper)
+            {
+                if b.lower > rest {
+                    intervals.push(Interval::new(rest, b.lower));
+                }
+                rest = max(rest, b.upper);
+                cut = true;
+            }
+            if rest < a.upper {
+                intervals.push(Interval::new(rest, a.upper));
+            } else if !cut {
+                intervals.push(*a);
+            }
+        }
+        // Removing a single point leaves two touching pieces, which merge back together
+        IntervalSet::from(intervals)
+    }
+}
+
+impl<T: PartialOrd + Copy> From<Vec<Interval<T>>> for IntervalSet<T> {
+    fn from(mut intervals: Vec<Interval<T>>) -> Self {
+        intervals.sort_by(|a, b| a.lower.partial_cmp(&b.lower).unwrap_or(Ordering::Equal));
+        let mut merged: Vec<Interval<T>> = Vec::with_capacity(intervals.len());
+        for interval in intervals {
+            match merged.last_mut() {
+                Some(last) if interval.lower <= last.upper => *last = last.hull(&interval),
+                _ => merged.push(interval),
+            }
+        }
+        IntervalSet { intervals: merged }
+    }
+}
+
+impl<T: PartialOrd + Copy> FromIterator<Interval<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = Interval<T>>>(iter: I) -> Self {
+        IntervalSet::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl<'a, T> IntoIterator for &'a IntervalSet<T> {
+    type Item = &'a Interval<T>;
+    type IntoIter = std::slice::Iter<'a, Interval<T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.intervals.iter()
+    }
+}
@@ -0,0 +1,370 @@
+//! An AVL tree of intervals ordered by lower bound, with each node also remembering the highest
+//! upper bound beneath it. That lets overlap queries skip any subtree which ends too early.
+
+use super::Interval;
+use std::cmp::Ordering;
+
+#[cfg(test)]
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+#[test]
+fn test_queries() {
+    let tree = [(15, 20), (10, 30), (17, 19), (5, 20), (12, 15), (30, 40)]
+        .into_iter()
+        .map(|(lower, upper)| Interval::new(lower, upper))
+        .collect::<IntervalTree<_>>();
+    assert_eq!(tree.len(), 6);
+    let lowers = tree.iter().map(|it| it.lower).collect::<Vec<_>>();
+    assert_eq!(lowers, [5, 10, 12, 15, 17, 30]);
+
+    let stabbed = tree.stab(&18);
+    assert_eq!(
+        stabbed,
+        [
+            &Interval::new(5, 20),
+            &Interval::new(10, 30),
+            &Interval::new(15, 20),
+            &Interval::new(17, 19)
+        ]
+    );
+    assert_eq!(tree.stab(&30).len(), 2);
+    assert!(tree.stab(&41).is_empty());
+    assert_eq!(
+        tree.overlapping(&Interval::new(0, 6)),
+        [&Interval::new(5, 20)]
+    );
+    assert_eq!(tree.overlapping(&Interval::new(21, 35)).len(), 2);
+}
+
+#[test]
+fn test_remove() {
+    let mut tree = IntervalTree::new();
+    tree.insert(Interval::new(1, 4));
+    tree.insert(Interval::new(1, 4));
+    tree.insert(Interval::new(2, 9));
+    assert!(tree.remove(&Interval::new(1, 4)));
+    assert_eq!(tree.len(), 2);
+    assert_eq!(tree.stab(&3).len(), 2);
+    assert!(!tree.remove(&Interval::new(1, 5)));
+    assert!(tree.remove(&Interval::new(2, 9)));
+    assert!(tree.stab(&5).is_empty());
+    assert!(tree.remove(&Interval::new(1, 4)));
+    assert!(tree.is_empty());
+}
+
+#[test]
+fn test_matches_linear_scan() {
+    let mut rng = StdRng::seed_from_u64(7);
+    let mut random = || {
+        let lower = rng.gen_range(0..1000);
+        Interval::new(lower, lower + rng.gen_range(0..50))
+    };
+    let mut intervals = (0..500).map(|_| random()).collect::<Vec<_>>();
+    let mut tree = intervals.iter().copied().collect::<IntervalTree<_>>();
+    for removed in intervals.drain(..200) {
+        assert!(tree.remove(&removed));
+    }
+    tree.check();
+    assert_eq!(tree.len(), intervals.len());
+
+    intervals.sort_by_key(|it| (it.lower, it.upper));
+    assert!(tree.iter().eq(&intervals));
+    for _ in 0..200 {
+        let query = random();
+        let scanned = intervals
+            .iter()
+            .filter(|it| it.overlaps(&query))
+            .collect::<Vec<_>>();
+        assert_eq!(tree.overlapping(&query), scanned);
+    }
+}
+
+type Link<T> = Option<Box<Node<T>>>;
+
+#[derive(Debug, Clone)]
+struct Node<T> {
+    interval: Interval<T>,
+    /// The highest upper bound in this subtree
+    max: T,
+    height: u32,
+    left: Link<T>,
+    right: Link<T>,
+}
+
+/// A multiset of intervals which can quickly find the ones overlapping a point or a range
+#[derive(Debug, Clone)]
+pub struct IntervalTree<T> {
+    root: Link<T>,
+    len: usize,
+}
+
+impl<T> Default for IntervalTree<T> {
+    fn default() -> Self {
+        IntervalTree { root: None, len: 0 }
+    }
+}
+
+impl<T: Ord + Clone> IntervalTree<T> {
+    pub fn new() -> Self {
+        IntervalTree::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Add an interval, even if an equal one is already there
+    pub fn insert(&mut self, interval: Interval<T>) {
+        self.root = Some(insert(self.root.take(), interval));
+        self.len += 1;
+    }
+
+    /// Take out one interval equal to `interval`, returning whether there was one
+    pub fn remove(&mut self, interval: &Interval<T>) -> bool {
+        let mut removed = false;
+        self.root = remove(self.root.take(), interval, &mut removed);
+        if removed {
+            self.len -= 1;
+        }
+        removed
+    }
+
+    /// Every interval containing `point`, in lower bound order
+    pub fn stab(&self, point: &T) -> Vec<&Interval<T>> {
+        let mut found = vec![];
+        search(&self.root, point, point, &mut found);
+        found
+    }
+
+    /// Every interval overlapping `range`, in lower bound order
+    pub fn overlapping(&self, range: &Interval<T>) -> Vec<&Interval<T>> {
+        let mut found = vec![];
+        search(&self.root, &range.lower, &range.upper, &mut found);
+        found
+    }
+
+    /// All the intervals, in lower bound order
+    pub fn iter(&self) -> Iter<'_, T> {
+        let mut iter = Iter { stack: vec![] };
+        iter.push_left(&self.root);
+        iter
+    }
+
+    /// Assert that the tree is balanced and every `max` is right
+    #[cfg(test)]
+    fn check(&self) {
+        fn walk<T: Ord + Clone>(link: &Link<T>) -> (u32, Option<T>) {
+            let Some(node) = link else {
+                return (0, None);
+            };
+            let (left_height, left_max) = walk(&node.left);
+            let (right_height, right_max) = walk(&node.right);
+            assert!(left_height.abs_diff(right_height) <= 1);
+            assert_eq!(node.height, 1 + left_height.max(right_height));
+            let max = [left_max, right_max, Some(node.interval.upper.clone())]
+                .into_iter()
+                .flatten()
+                .max();
+            assert!(Some(&node.max) == max.as_ref());
+            (node.height, max)
+        }
+        walk(&self.root);
+    }
+}
+
+fn height<T>(link: &Link<T>) -> u32 {
+    link.as_ref().map_or(0, |node| node.height)
+}
+
+/// Intervals go in lower bound order, then upper bound order
+fn compare<T: Ord>(a: &Interval<T>, b: &Interval<T>) -> Ordering {
+    (&a.lower, &a.upper).cmp(&(&b.lower, &b.upper))
+}
+
+impl<T: Ord + Clone> Node<T> {
+    fn new(interval: Interval<T>) -> Box<Self> {
+        Box::new(Node {
+            max: interval.upper.clone(),
+            interval,
+            height: 1,
+            left: None,
+            right: None,
+        })
+    }
+
+    /// Recompute `height` and `max` from the children
+    fn update(&mut self) {
+        self.height = 1 + height(&self.left).max(height(&self.right));
+        self.max = [&self.left, &self.right]
+            .into_iter()
+            .flatten()
+            .map(|child| &child.max)
+            .fold(&self.interval.upper, |max, child| max.max(child))
+            .clone();
+    }
+
+    fn balance(&self) -> i64 {
+        height(&self.left) as i64 - height(&self.right) as i64
+    }
+
+    fn rotate_right(mut self: Box<Self>) -> Box<Self> {
+        let mut left = self.left.take().expect("Rotating right needs a left child");
+        self.left = left.right.take();
+        self.update();
+        left.right = Some(self);
+        left.update();
+        left
+    }
+
+    fn rotate_left(mut self: Box<Self>) -> Box<Self> {
+        let mut right = self
+            .right
+            .take()
+            .expect("Rotating left needs a right child");
+        self.right = right.left.take();
+        self.update();
+        right.left = Some(self);
+        right.update();
+        right
+    }
+
+    /// Restore the AVL property after one side has changed height by one
+    fn rebalance(mut self: Box<Self>) -> Box<Self> {
+        self.update();
+        match self.balance() {
+            2.. => {
+                if self.left.as_ref().is_some_and(|left| left.balance() < 0) {
+                    self.left = self.left.take().map(Node::rotate_left);
+                }
+                self.rotate_right()
+            }
+            ..=-2 => {
+                if self.right.as_ref().is_some_and(|right| right.balance() > 0) {
+                    self.right = self.right.take().map(Node::rotate_right);
+                }
+                self.rotate_left()
+            }
+            _ => self,
+        }
+    }
+}
+
+fn insert<T: Ord + Clone>(link: Link<T>, interval: Interval<T>) -> Box<Node<T>> {
+    let Some(mut node) = link else {
+        return Node::new(interval);
+    };
+    if compare(&interval, &node.interval) == Ordering::Less {
+        node.left = Some(insert(node.left.take(), interval));
+    } else {
+        node.right = Some(insert(node.right.take(), interval));
+    }
+    node.rebalance()
+}
+
+fn remove<T: Ord + Clone>(link: Link<T>, interval: &Interval<T>, removed: &mut bool) -> Link<T> {
+    let mut node = link?;
+    match compare(interval, &node.interval) {
+        Ordering::Less => node.left = remove(node.left.take(), interval, removed),
+        Ordering::Greater => node.right = remove(node.right.take(), interval, removed),
+        Ordering::Equal => {
+            *removed = true;
+            match (node.left.take(), node.right.take()) {
+                (None, child) | (child, None) => return child,
+                (left, Some(right)) => {
+                    let (right, successor) = remove_min(right);
+                    node.interval = successor;
+                    node.left = left;
+                    node.right = right;
+                }
+            }
+        }
+    }
+    Some(node.rebalance())
+}
+
+/// Take out the leftmost interval, returning what's left of the subtree along with it
+fn remove_min<T: Ord + Clone>(mut node: Box<Node<T>>) -> (Link<T>, Interval<T>) {
+    match node.left.take() {
+        None => (node.right.take(), node.interval),
+        Some(left) => {
+            let (left, min) = remove_min(left);
+            node.left = left;
+            (Some(node.rebalance()), min)
+        }
+    }
+}
+
+/// Collect the intervals in `link` which overlap `[lower, upper]`
+fn search<'a, T: Ord>(link: &'a Link<T>, lower: &T, upper: &T, found: &mut Vec<&'a Interval<T>>) {
+    let Some(node) = link else {
+        return;
+    };
+    // Nothing down here reaches as far as the query
+    if node.max < *lower {
+        return;
+    }
+    search(&node.left, lower, upper, found);
+    if node.interval.lower <= *upper {
+        if *lower <= node.interval.upper {
+            found.push(&node.interval);
+        }
+        // Everything to the right starts at or after this node, so only look if this one does
+        // before the query ends
+        search(&node.right, lower, upper, found);
+    }
+}
+
+/// Iterates over an `IntervalTree` in lower bound order
+pub struct Iter<'a, T> {
+    stack: Vec<&'a Node<T>>,
+}
+
+impl<'a, T> Iter<'a, T> {
+    fn push_left(&mut self, mut link: &'a Link<T>) {
+        while let Some(node) = link {
+            self.stack.push(node);
+            link = &node.left;
+        }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a Interval<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.push_left(&node.right);
+        Some(&node.interval)
+    }
+}
+
+impl<'a, T: Ord + Clone> IntoIterator for &'a IntervalTree<T> {
+    type Item = &'a Interval<T>;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Ord + Clone> FromIterator<Interval<T>> for IntervalTree<T> {
+    fn from_iter<I: IntoIterator<Item = Interval<T>>>(iter: I) -> Self {
+        let mut tree = IntervalTree::new();
+        for interval in iter {
+            tree.insert(interval);
+        }
+        tree
+    }
+}
+
+impl<T: Ord + Clone> Extend<Interval<T>> for IntervalTree<T> {
+    fn extend<I: IntoIterator<Item = Interval<T>>>(&mut self, iter: I) {
+        for interval in iter {
+            self.insert(interval);
+        }
+    }
+}
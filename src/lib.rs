@@ -14,4 +14,21 @@ pub mod chap_18;
 pub mod chap_19;
 pub mod chap_22;
 pub mod chap_23;
+pub mod chat_server;
+pub mod cli_error;
+pub mod complex;
+pub mod echo_server;
+pub mod fft;
+pub mod fibonacci;
+pub mod image;
+pub mod interval;
 pub mod json_lib;
+pub mod linalg;
+pub mod mandelbrot;
+pub mod numeric;
+pub mod prim_future;
+pub mod sink;
+pub mod spellcheck;
+pub mod string_set;
+#[cfg(feature = "tls")]
+pub mod tls;
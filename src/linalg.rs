@@ -0,0 +1,510 @@
+use crate::chap_11::checked_dot;
+use crate::numeric::Num;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::ops::{Add, Index, IndexMut, Mul, Neg, Sub};
+
+#[test]
+fn test_vector_dot() {
+    let v1 = Vector::from(vec![1, 2, 3]);
+    let v2 = Vector::from(vec![4, 5, 6]);
+    assert_eq!(v1.dot(&v2), Ok(32));
+
+    let short = Vector::from(vec![1, 2]);
+    assert_eq!(
+        v1.dot(&short),
+        Err(ShapeError {
+            left: (3, 1),
+            right: (2, 1)
+        })
+    );
+}
+
+#[test]
+fn test_vector_ops() {
+    let v1 = Vector::from(vec![1.0, 2.0]);
+    let v2 = Vector::from(vec![0.5, 0.5]);
+    assert_eq!(&v1 + &v2, Vector::from(vec![1.5, 2.5]));
+    assert_eq!(&v1 - &v2, Vector::from(vec![0.5, 1.5]));
+    assert_eq!(v1.clone() * 2.0, Vector::from(vec![2.0, 4.0]));
+    assert_eq!(2.0 * v1.clone(), Vector::from(vec![2.0, 4.0]));
+    assert_eq!(-v1, Vector::from(vec![-1.0, -2.0]));
+    assert_eq!(Vector::<u8>::zeros(3), Vector::from(vec![0, 0, 0]));
+}
+
+#[test]
+#[should_panic(expected = "not compatible")]
+fn test_mismatched_add_panics() {
+    let _ = Vector::from(vec![1, 2]) + Vector::from(vec![1]);
+}
+
+#[test]
+fn test_matrix_multiply() {
+    let a = Matrix::from_rows(vec![vec![1, 2, 3], vec![4, 5, 6]]).unwrap();
+    let b = Matrix::from_rows(vec![vec![7, 8], vec![9, 10], vec![11, 12]]).unwrap();
+    let product = a.matmul(&b).unwrap();
+    assert_eq!(
+        product,
+        Matrix::from_rows(vec![vec![58, 64], vec![139, 154]]).unwrap()
+    );
+    assert_eq!(&a * &b, product);
+    assert!(a.matmul(&a).is_err());
+
+    let v = Vector::from(vec![1, 0, -1]);
+    assert_eq!(a.mul_vector(&v), Ok(Vector::from(vec![-2, -2])));
+}
+
+#[test]
+fn test_identity_and_transpose() {
+    let a = Matrix::from_rows(vec![vec![1.5, 2.0], vec![3.0, 4.0], vec![5.0, 6.0]]).unwrap();
+    assert_eq!(a.transpose().shape(), (2, 3));
+    assert_eq!(a.transpose()[(1, 2)], 6.0);
+    assert_eq!(a.transpose().transpose(), a);
+    assert_eq!(a.matmul(&Matrix::identity(2)).unwrap(), a);
+    assert_eq!(Matrix::identity(3).matmul(&a).unwrap(), a);
+    assert_eq!(&a - &a, Matrix::zeros(3, 2));
+    assert_eq!(0.5 * (&a + &a), a);
+}
+
+#[test]
+fn test_ragged_rows() {
+    assert_eq!(
+        Matrix::from_rows(vec![vec![1, 2], vec![3]]),
+        Err(ShapeError {
+            left: (1, 2),
+            right: (1, 1)
+        })
+    );
+}
+
+/// The operands of a vector or matrix operation have shapes which don't fit together. Shapes
+/// are `(rows, columns)`, with vectors counting as a single column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShapeError {
+    pub left: (usize, usize),
+    pub right: (usize, usize),
+}
+
+impl Display for ShapeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "shapes {:?} and {:?} are not compatible",
+            self.left, self.right
+        )
+    }
+}
+
+impl Error for ShapeError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vector<T> {
+    elements: Vec<T>,
+}
+
+impl<T> Vector<T> {
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        &self.elements
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.elements
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.elements.iter()
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        self.elements
+    }
+}
+
+impl<T: Num> Vector<T> {
+    pub fn zeros(len: usize) -> Self {
+        Vector {
+            elements: vec![T::ZERO; len],
+        }
+    }
+
+    pub fn dot(&self, other: &Vector<T>) -> Result<T, ShapeError> {
+        checked_dot(&self.elements, &other.elements).ok_or_else(|| self.mismatch(other))
+    }
+
+    pub fn checked_add(&self, other: &Vector<T>) -> Result<Vector<T>, ShapeError> {
+        self.zip_with(other, |a, b| a + b)
+    }
+
+    pub fn checked_sub(&self, other: &Vector<T>) -> Result<Vector<T>, ShapeError> {
+        self.zip_with(other, |a, b| a - b)
+    }
+
+    /// Multiply element by element
+    pub fn hadamard(&self, other: &Vector<T>) -> Result<Vector<T>, ShapeError> {
+        self.zip_with(other, |a, b| a * b)
+    }
+
+    pub fn scale(&self, factor: T) -> Vector<T> {
+        self.elements.iter().map(|&e| e * factor).collect()
+    }
+
+    fn zip_with(&self, other: &Vector<T>, op: impl Fn(T, T) -> T) -> Result<Vector<T>, ShapeError> {
+        if self.len() != other.len() {
+            return Err(self.mismatch(other));
+        }
+        Ok(self
+            .elements
+            .iter()
+            .zip(&other.elements)
+            .map(|(&a, &b)| op(a, b))
+            .collect())
+    }
+
+    fn mismatch(&self, other: &Vector<T>) -> ShapeError {
+        ShapeError {
+            left: (self.len(), 1),
+            right: (other.len(), 1),
+        }
+    }
+}
+
+impl<T> From<Vec<T>> for Vector<T> {
+    fn from(elements: Vec<T>) -> Self {
+        Vector { elements }
+    }
+}
+
+impl<T> FromIterator<T> for Vector<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Vector {
+            elements: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<T> Index<usize> for Vector<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        &self.elements[index]
+    }
+}
+
+impl<T> IndexMut<usize> for Vector<T> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        &mut self.elements[index]
+    }
+}
+
+/// The element-wise operators panic on mismatched shapes, the same way slice indexing panics
+/// when out of bounds; use the `checked_` methods to get a `ShapeError` instead.
+impl<T: Num> Add for &Vector<T> {
+    type Output = Vector<T>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.checked_add(rhs).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+impl<T: Num> Add for Vector<T> {
+    type Output = Vector<T>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        &self + &rhs
+    }
+}
+
+impl<T: Num> Sub for &Vector<T> {
+    type Output = Vector<T>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.checked_sub(rhs).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+impl<T: Num> Sub for Vector<T> {
+    type Output = Vector<T>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        &self - &rhs
+    }
+}
+
+impl<T: Num + Neg<Output = T>> Neg for Vector<T> {
+    type Output = Vector<T>;
+
+    fn neg(self) -> Self::Output {
+        self.elements.into_iter().map(|e| -e).collect()
+    }
+}
+
+impl<T: Num> Mul<T> for Vector<T> {
+    type Output = Vector<T>;
+
+    fn mul(self, rhs: T) -> Self::Output {
+        self.scale(rhs)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matrix<T> {
+    rows: usize,
+    cols: usize,
+    /// Row-major storage
+    elements: Vec<T>,
+}
+
+impl<T: Num> Matrix<T> {
+    /// Build a matrix from its elements in row-major order
+    pub fn new(rows: usize, cols: usize, elements: Vec<T>) -> Result<Self, ShapeError> {
+        if elements.len() != rows * cols {
+            return Err(ShapeError {
+                left: (rows, cols),
+                right: (elements.len(), 1),
+            });
+        }
+        Ok(Matrix {
+            rows,
+            cols,
+            elements,
+        })
+    }
+
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Result<Self, ShapeError> {
+        let cols = rows.first().map_or(0, Vec::len);
+        if let Some(ragged) = rows.iter().find(|row| row.len() != cols) {
+            return Err(ShapeError {
+                left: (1, cols),
+                right: (1, ragged.len()),
+            });
+        }
+        let count = rows.len();
+        Matrix::new(count, cols, rows.into_iter().flatten().collect())
+    }
+
+    pub fn zeros(rows: usize, cols: usize) -> Self {
+        Matrix {
+            rows,
+            cols,
+            elements: vec![T::ZERO; rows * cols],
+        }
+    }
+
+    pub fn identity(size: usize) -> Self {
+        let mut identity = Matrix::zeros(size, size);
+        for i in 0..size {
+            identity[(i, i)] = T::ONE;
+        }
+        identity
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn shape(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    pub fn row(&self, row: usize) -> &[T] {
+        &self.elements[row * self.cols..(row + 1) * self.cols]
+    }
+
+    pub fn column(&self, col: usize) -> Vector<T> {
+        (0..self.rows).map(|row| self[(row, col)]).collect()
+    }
+
+    pub fn transpose(&self) -> Matrix<T> {
+        let elements = (0..self.cols)
+            .flat_map(|col| (0..self.rows).map(move |row| (row, col)))
+            .map(|index| self[index])
+            .collect();
+        Matrix {
+            rows: self.cols,
+            cols: self.rows,
+            elements,
+        }
+    }
+
+    pub fn matmul(&self, other: &Matrix<T>) -> Result<Matrix<T>, ShapeError> {
+        if self.cols != other.rows {
+            return Err(ShapeError {
+                left: self.shape(),
+                right: other.shape(),
+            });
+        }
+        // Transposing first lets every element be the dot product of two contiguous rows
+        let other = other.transpose();
+        let elements = (0..self.rows)
+            .flat_map(|row| (0..other.rows).map(move |col| (row, col)))
+            .map(|(row, col)| crate::chap_11::dot(self.row(row), other.row(col)))
+            .collect();
+        Ok(Matrix {
+            rows: self.rows,
+            cols: other.rows,
+            elements,
+        })
+    }
+
+    pub fn mul_vector(&self, vector: &Vector<T>) -> Result<Vector<T>, ShapeError> {
+        if self.cols != vector.len() {
+            return Err(ShapeError {
+                left: self.shape(),
+                right: (vector.len(), 1),
+            });
+        }
+        Ok((0..self.rows)
+            .map(|row| crate::chap_11::dot(self.row(row), vector.as_slice()))
+            .collect())
+    }
+
+    pub fn checked_add(&self, other: &Matrix<T>) -> Result<Matrix<T>, ShapeError> {
+        self.zip_with(other, |a, b| a + b)
+    }
+
+    pub fn checked_sub(&self, other: &Matrix<T>) -> Result<Matrix<T>, ShapeError> {
+        self.zip_with(other, |a, b| a - b)
+    }
+
+    pub fn hadamard(&self, other: &Matrix<T>) -> Result<Matrix<T>, ShapeError> {
+        self.zip_with(other, |a, b| a * b)
+    }
+
+    pub fn scale(&self, factor: T) -> Matrix<T> {
+        Matrix {
+            rows: self.rows,
+            cols: self.cols,
+            elements: self.elements.iter().map(|&e| e * factor).collect(),
+        }
+    }
+
+    fn zip_with(&self, other: &Matrix<T>, op: impl Fn(T, T) -> T) -> Result<Matrix<T>, ShapeError> {
+        if self.shape() != other.shape() {
+            return Err(ShapeError {
+                left: self.shape(),
+                right: other.shape(),
+            });
+        }
+        Ok(Matrix {
+            rows: self.rows,
+            cols: self.cols,
+            elements: self
+                .elements
+                .iter()
+                .zip(&other.elements)
+                .map(|(&a, &b)| op(a, b))
+                .collect(),
+        })
+    }
+}
+
+impl<T> Index<(usize, usize)> for Matrix<T> {
+    type Output = T;
+
+    fn index(&self, (row, col): (usize, usize)) -> &T {
+        assert!(col < self.cols, "column {} out of bounds", col);
+        &self.elements[row * self.cols + col]
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Matrix<T> {
+    fn index_mut(&mut self, (row, col): (usize, usize)) -> &mut T {
+        assert!(col < self.cols, "column {} out of bounds", col);
+        &mut self.elements[row * self.cols + col]
+    }
+}
+
+impl<T: Num> Add for &Matrix<T> {
+    type Output = Matrix<T>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.checked_add(rhs).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+impl<T: Num> Add for Matrix<T> {
+    type Output = Matrix<T>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        &self + &rhs
+    }
+}
+
+impl<T: Num> Sub for &Matrix<T> {
+    type Output = Matrix<T>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.checked_sub(rhs).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+impl<T: Num> Sub for Matrix<T> {
+    type Output = Matrix<T>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        &self - &rhs
+    }
+}
+
+/// Matrix product, panicking if the inner dimensions differ
+impl<T: Num> Mul for &Matrix<T> {
+    type Output = Matrix<T>;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.matmul(rhs).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+impl<T: Num> Mul for Matrix<T> {
+    type Output = Matrix<T>;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        &self * &rhs
+    }
+}
+
+impl<T: Num> Mul<T> for Matrix<T> {
+    type Output = Matrix<T>;
+
+    fn mul(self, rhs: T) -> Self::Output {
+        self.scale(rhs)
+    }
+}
+
+/// Scalars on the left, as in `Mul<AppleBasket> for usize`. Coherence rules mean this can't be
+/// a blanket impl, so it's spelled out per type.
+macro_rules! impl_scalar_mul {
+    ( $( $t:ident )* ) => {
+        $(
+            impl Mul<Vector<$t>> for $t {
+                type Output = Vector<$t>;
+
+                fn mul(self, rhs: Vector<$t>) -> Self::Output {
+                    rhs.scale(self)
+                }
+            }
+
+            impl Mul<Matrix<$t>> for $t {
+                type Output = Matrix<$t>;
+
+                fn mul(self, rhs: Matrix<$t>) -> Self::Output {
+                    rhs.scale(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_scalar_mul!(u8 i8 u16 i16 u32 i32 u64 i64 f32 f64 usize isize i128 u128);
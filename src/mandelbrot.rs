@@ -0,0 +1,193 @@
+//! Draws the Mandelbrot set, using `chap_15::complex_successors` for the escape times
+
+use crate::chap_15::complex_successors;
+use crate::image::{Image, Rgb};
+use num::Complex;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+#[test]
+fn test_parse() {
+    assert_eq!(parse_pair::<usize>("400x600", 'x'), Some((400, 600)));
+    assert_eq!(parse_pair::<f64>("-1.5,0.25", ','), Some((-1.5, 0.25)));
+    assert_eq!(parse_pair::<usize>("400x", 'x'), None);
+    assert_eq!(
+        "-1.2,0.35:-1,0.2".parse::<Region>(),
+        Ok(Region {
+            upper_left: Complex::new(-1.2, 0.35),
+            lower_right: Complex::new(-1.0, 0.2),
+        })
+    );
+    assert!("-1,0.35:-1.2,0.2".parse::<Region>().is_err());
+    assert!("nonsense".parse::<Region>().is_err());
+}
+
+#[test]
+fn test_pixel_to_point() {
+    let region = Region {
+        upper_left: Complex::new(-1.0, 1.0),
+        lower_right: Complex::new(1.0, -1.0),
+    };
+    assert_eq!(
+        region.pixel_to_point((100, 200), (25, 175)),
+        Complex::new(-0.5, -0.75)
+    );
+}
+
+#[test]
+fn test_parallel_matches_serial() {
+    let region = Region::default();
+    let serial = render(&region, 61, 37, 64, 1);
+    assert_eq!(render(&region, 61, 37, 64, 4), serial);
+    // More threads than rows still covers every row
+    assert_eq!(render(&region, 61, 37, 64, 100), serial);
+
+    // The middle pixel, at -0.4+0.4i, is in the set and the corners aren't
+    let centre = Region {
+        upper_left: Complex::new(-2.0, 2.0),
+        lower_right: Complex::new(2.0, -2.0),
+    };
+    let escapes = render(&centre, 5, 5, 64, 2);
+    assert_eq!(escapes[(2, 2)], None);
+    assert!(escapes[(0, 0)].is_some());
+
+    let colours = colorize(&escapes, 64);
+    assert_eq!(colours[(2, 2)], Rgb::new(0, 0, 0));
+    assert_ne!(colours[(0, 0)], Rgb::new(0, 0, 0));
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseRegionError(String);
+
+impl Display for ParseRegionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for ParseRegionError {}
+
+/// The rectangle of the complex plane being drawn
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    pub upper_left: Complex<f64>,
+    pub lower_right: Complex<f64>,
+}
+
+impl Default for Region {
+    /// The whole set
+    fn default() -> Self {
+        Region {
+            upper_left: Complex::new(-2.2, 1.2),
+            lower_right: Complex::new(1.0, -1.2),
+        }
+    }
+}
+
+impl Region {
+    /// The point in the plane for the pixel at `(x, y)` of an image `bounds` pixels big
+    pub fn pixel_to_point(&self, bounds: (usize, usize), (x, y): (usize, usize)) -> Complex<f64> {
+        let (width, height) = (
+            self.lower_right.re - self.upper_left.re,
+            self.upper_left.im - self.lower_right.im,
+        );
+        Complex {
+            re: self.upper_left.re + x as f64 * width / bounds.0 as f64,
+            // The imaginary axis points up, but y goes down the image
+            im: self.upper_left.im - y as f64 * height / bounds.1 as f64,
+        }
+    }
+}
+
+/// Parses `"upper_left:lower_right"`, where each corner is `"re,im"`
+impl FromStr for Region {
+    type Err = ParseRegionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let corner = |s: &str| {
+            parse_pair::<f64>(s, ',')
+                .map(|(re, im)| Complex::new(re, im))
+                .ok_or_else(|| ParseRegionError(format!("bad corner {:?}", s)))
+        };
+        let (upper_left, lower_right) = s
+            .split_once(':')
+            .ok_or_else(|| ParseRegionError(format!("expected two corners, found {:?}", s)))?;
+        let region = Region {
+            upper_left: corner(upper_left)?,
+            lower_right: corner(lower_right)?,
+        };
+        if region.upper_left.re >= region.lower_right.re
+            || region.upper_left.im <= region.lower_right.im
+        {
+            return Err(ParseRegionError(format!(
+                "{} isn't above and to the left of {}",
+                region.upper_left, region.lower_right
+            )));
+        }
+        Ok(region)
+    }
+}
+
+/// Parse `"<left><separator><right>"`, as in `"400x600"` or `"1.0,0.5"`
+pub fn parse_pair<T: FromStr>(s: &str, separator: char) -> Option<(T, T)> {
+    let (left, right) = s.split_once(separator)?;
+    Some((left.parse().ok()?, right.parse().ok()?))
+}
+
+/// How many iterations each pixel took to escape, or `None` for points which stayed within
+/// `limit` iterations and so are taken to be in the set. The rows are split into one band per
+/// thread.
+pub fn render(
+    region: &Region,
+    width: usize,
+    height: usize,
+    limit: usize,
+    threads: usize,
+) -> Image<Option<usize>> {
+    let mut escapes = Image::new(width, height);
+    let rows_per_band = height.div_ceil(threads.max(1)).max(1);
+    let band_len = (rows_per_band * width).max(1);
+    crossbeam_utils::thread::scope(|scope| {
+        for (index, band) in escapes.as_mut_slice().chunks_mut(band_len).enumerate() {
+            let top = index * rows_per_band;
+            scope.spawn(move |_| {
+                for (offset, escape) in band.iter_mut().enumerate() {
+                    let pixel = (offset % width, top + offset / width);
+                    let point = region.pixel_to_point((width, height), pixel);
+                    *escape = complex_successors(point, limit);
+                }
+            });
+        }
+    })
+    .expect("A rendering thread panicked");
+    escapes
+}
+
+/// Colour the escape counts with a gradient through blue, white and orange. Points in the set
+/// are black.
+pub fn colorize(escapes: &Image<Option<usize>>, limit: usize) -> Image<Rgb> {
+    const STOPS: [(f64, Rgb); 5] = [
+        (0.0, Rgb::new(0, 7, 100)),
+        (0.16, Rgb::new(32, 107, 203)),
+        (0.42, Rgb::new(237, 255, 255)),
+        (0.64, Rgb::new(255, 170, 0)),
+        (0.86, Rgb::new(0, 2, 0)),
+    ];
+    escapes.map(|escape| {
+        let Some(count) = *escape else {
+            return Rgb::new(0, 0, 0);
+        };
+        // A square root spreads out the low counts, which is where most pixels are
+        let t = (count as f64 / limit.max(1) as f64).sqrt().min(1.0);
+        let upper = STOPS
+            .iter()
+            .position(|&(at, _)| at > t)
+            .unwrap_or(STOPS.len());
+        let (from_at, from) = STOPS[upper.saturating_sub(1)];
+        let (to_at, to) = STOPS.get(upper).copied().unwrap_or((1.0, STOPS[0].1));
+        let mix = (t - from_at) / (to_at - from_at);
+        let lerp = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * mix).round() as u8;
+        Rgb::new(lerp(from.r, to.r), lerp(from.g, to.g), lerp(from.b, to.b))
+    })
+}
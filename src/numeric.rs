@@ -0,0 +1,368 @@
+//! A small numeric trait tower, so that generic code can ask for "a number" instead of listing
+//! every operator it uses. Each layer is implemented for the primitive types by a macro, in the
+//! same way as `impl_for_number!` in `chap_22`.
+
+use std::fmt::Debug;
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+
+#[cfg(test)]
+fn sum_of_squares<T: Num>(values: &[T]) -> Option<T> {
+    values
+        .iter()
+        .try_fold(T::ZERO, |acc, &v| acc.checked_add(v.checked_mul(v)?))
+}
+
+#[test]
+fn test_identities() {
+    assert_eq!(u8::ZERO, 0);
+    assert_eq!(i128::ONE, 1);
+    assert_eq!(f32::ONE + f32::ONE, 2.0);
+    assert_eq!(sum_of_squares(&[1, 2, 3]), Some(14));
+    assert_eq!(sum_of_squares(&[1.5, 0.5]), Some(2.5));
+}
+
+#[test]
+fn test_checked_and_saturating() {
+    assert_eq!(sum_of_squares(&[10_u8, 10, 10]), None);
+    assert_eq!(200_u8.saturating_add(100), 255);
+    assert_eq!(0_u32.checked_sub(1), None);
+    assert_eq!(i8::MIN.checked_div(-1), None);
+    assert_eq!(5_i16.checked_div(0), None);
+
+    assert_eq!(f64::MAX.checked_mul(2.0), None);
+    assert_eq!(1.0_f64.checked_div(0.0), None);
+    assert_eq!(f32::MAX.saturating_add(f32::MAX), f32::MAX);
+    assert_eq!(f64::MIN.saturating_sub(f64::MAX), f64::MIN);
+}
+
+#[test]
+fn test_conversions() {
+    assert_eq!(<u8 as Num>::from_f64(255.0), Some(255));
+    assert_eq!(<u8 as Num>::from_f64(256.0), None);
+    assert_eq!(<i32 as Num>::from_f64(-2.5), None);
+    assert_eq!(<i64 as Num>::from_i128(-3), Some(-3));
+    assert_eq!(<u16 as Num>::from_i128(-3), None);
+    let two_to_the = |n| 2_f64.powi(n);
+    assert_eq!(<i64 as Num>::from_f64(two_to_the(63)), None);
+    assert_eq!(<i64 as Num>::from_f64(-two_to_the(63)), Some(i64::MIN));
+    assert_eq!(<u64 as Num>::from_f64(two_to_the(64)), None);
+    assert_eq!(<u64 as Num>::from_f64(two_to_the(63)), Some(1 << 63));
+    assert_eq!(<u128 as Num>::from_f64(two_to_the(128)), None);
+    assert_eq!(<i128 as Num>::from_f64(two_to_the(127)), None);
+    assert_eq!(<i8 as Num>::from_f64(127.0), Some(127));
+    assert_eq!(<i8 as Num>::from_f64(128.0), None);
+    assert_eq!(<i8 as Num>::from_f64(-128.0), Some(-128));
+    assert_eq!(<f32 as Num>::from_i128(3), Some(3.0));
+    assert_eq!(
+        <f64 as Num>::from_f64(f64::NAN).map(f64::is_nan),
+        Some(true)
+    );
+    assert_eq!(7_u64.to_f64(), 7.0);
+    assert_eq!((-7_i8).to_i128(), Some(-7));
+    assert_eq!(2.9_f32.to_i128(), Some(2));
+    assert_eq!(f64::INFINITY.to_i128(), None);
+}
+
+#[test]
+fn test_signed_and_roots() {
+    assert_eq!(Signed::abs(-4_i32), 4);
+    assert_eq!(Signed::abs(-4.5_f64), 4.5);
+    assert_eq!((-3_i64).signum(), -1);
+    assert_eq!(Float::sqrt(16.0_f32), 4.0);
+    assert_eq!(Integer::isqrt(17_u32), Some(4));
+    assert_eq!(Integer::isqrt(-1_i32), None);
+    assert!(Integer::is_even(10_usize));
+}
+
+/// Anything with a zero, a one and the four basic operations
+pub trait Num:
+    Copy
+    + Default
+    + Debug
+    + PartialEq
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+    const MIN: Self;
+    const MAX: Self;
+
+    /// `None` on overflow or division by zero; for floats, whenever the result isn't finite
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+    fn checked_div(self, rhs: Self) -> Option<Self>;
+
+    /// Clamp to `MIN` or `MAX` instead of overflowing
+    fn saturating_add(self, rhs: Self) -> Self;
+    fn saturating_sub(self, rhs: Self) -> Self;
+    fn saturating_mul(self, rhs: Self) -> Self;
+
+    /// Exact conversions; `None` when the value doesn't fit. Floats convert to the nearest
+    /// representable value, and to integers only when they have no fractional part.
+    fn from_i128(value: i128) -> Option<Self>;
+    fn from_f64(value: f64) -> Option<Self>;
+    /// Floats are truncated towards zero
+    fn to_i128(self) -> Option<i128>;
+    fn to_f64(self) -> f64;
+}
+
+/// Numbers which can be negative
+pub trait Signed: Num + Neg<Output = Self> {
+    fn abs(self) -> Self;
+    fn signum(self) -> Self;
+}
+
+pub trait Integer: Num + Ord + Eq + Rem<Output = Self> {
+    /// The integer square root, or `None` for negative numbers
+    fn isqrt(self) -> Option<Self>;
+
+    fn is_even(self) -> bool {
+        self % (Self::ONE + Self::ONE) == Self::ZERO
+    }
+}
+
+pub trait Float: Signed {
+    const EPSILON: Self;
+    const NAN: Self;
+    const PI: Self;
+
+    fn sqrt(self) -> Self;
+    fn powi(self, exponent: i32) -> Self;
+    fn powf(self, exponent: Self) -> Self;
+    fn exp(self) -> Self;
+    fn ln(self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn atan2(self, other: Self) -> Self;
+    fn hypot(self, other: Self) -> Self;
+    fn is_finite(self) -> bool;
+    fn is_nan(self) -> bool;
+}
+
+macro_rules! impl_integer {
+    ( $( $t:ident )* ) => {
+        $(
+            impl Num for $t {
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+                const MIN: Self = $t::MIN;
+                const MAX: Self = $t::MAX;
+
+                fn checked_add(self, rhs: Self) -> Option<Self> {
+                    $t::checked_add(self, rhs)
+                }
+
+                fn checked_sub(self, rhs: Self) -> Option<Self> {
+                    $t::checked_sub(self, rhs)
+                }
+
+                fn checked_mul(self, rhs: Self) -> Option<Self> {
+                    $t::checked_mul(self, rhs)
+                }
+
+                fn checked_div(self, rhs: Self) -> Option<Self> {
+                    $t::checked_div(self, rhs)
+                }
+
+                fn saturating_add(self, rhs: Self) -> Self {
+                    $t::saturating_add(self, rhs)
+                }
+
+                fn saturating_sub(self, rhs: Self) -> Self {
+                    $t::saturating_sub(self, rhs)
+                }
+
+                fn saturating_mul(self, rhs: Self) -> Self {
+                    $t::saturating_mul(self, rhs)
+                }
+
+                fn from_i128(value: i128) -> Option<Self> {
+                    $t::try_from(value).ok()
+                }
+
+                fn from_f64(value: f64) -> Option<Self> {
+                    // `MAX` rounds up to 2^BITS for the widest types, so compare against that
+                    // power of two, less 2^(BITS-1) for signed types, which is exact instead
+                    let end = 2_f64.powi($t::BITS as i32) + $t::MIN as f64;
+                    if value.fract() != 0.0 || value < $t::MIN as f64 || value >= end {
+                        return None;
+                    }
+                    Some(value as $t)
+                }
+
+                fn to_i128(self) -> Option<i128> {
+                    i128::try_from(self).ok()
+                }
+
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_unsigned_integer {
+    ( $( $t:ident )* ) => {
+        $(
+            impl Integer for $t {
+                fn isqrt(self) -> Option<Self> {
+                    Some($t::isqrt(self))
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_signed_integer {
+    ( $( $t:ident )* ) => {
+        $(
+            impl Integer for $t {
+                fn isqrt(self) -> Option<Self> {
+                    $t::checked_isqrt(self)
+                }
+            }
+
+            impl Signed for $t {
+                fn abs(self) -> Self {
+                    $t::abs(self)
+                }
+
+                fn signum(self) -> Self {
+                    $t::signum(self)
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_float {
+    ( $( $t:ident )* ) => {
+        $(
+            impl Num for $t {
+                const ZERO: Self = 0.0;
+                const ONE: Self = 1.0;
+                const MIN: Self = $t::MIN;
+                const MAX: Self = $t::MAX;
+
+                fn checked_add(self, rhs: Self) -> Option<Self> {
+                    Some(self + rhs).filter(|r| r.is_finite())
+                }
+
+                fn checked_sub(self, rhs: Self) -> Option<Self> {
+                    Some(self - rhs).filter(|r| r.is_finite())
+                }
+
+                fn checked_mul(self, rhs: Self) -> Option<Self> {
+                    Some(self * rhs).filter(|r| r.is_finite())
+                }
+
+                fn checked_div(self, rhs: Self) -> Option<Self> {
+                    Some(self / rhs).filter(|r| r.is_finite())
+                }
+
+                fn saturating_add(self, rhs: Self) -> Self {
+                    (self + rhs).clamp($t::MIN, $t::MAX)
+                }
+
+                fn saturating_sub(self, rhs: Self) -> Self {
+                    (self - rhs).clamp($t::MIN, $t::MAX)
+                }
+
+                fn saturating_mul(self, rhs: Self) -> Self {
+                    (self * rhs).clamp($t::MIN, $t::MAX)
+                }
+
+                fn from_i128(value: i128) -> Option<Self> {
+                    Some(value as $t)
+                }
+
+                fn from_f64(value: f64) -> Option<Self> {
+                    let converted = value as $t;
+                    (converted.is_finite() || !value.is_finite()).then_some(converted)
+                }
+
+                fn to_i128(self) -> Option<i128> {
+                    if !self.is_finite() || self.abs() >= i128::MAX as $t {
+                        return None;
+                    }
+                    Some(self as i128)
+                }
+
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+            }
+
+            impl Signed for $t {
+                fn abs(self) -> Self {
+                    $t::abs(self)
+                }
+
+                fn signum(self) -> Self {
+                    $t::signum(self)
+                }
+            }
+
+            impl Float for $t {
+                const EPSILON: Self = $t::EPSILON;
+                const NAN: Self = $t::NAN;
+                const PI: Self = std::$t::consts::PI;
+
+                fn sqrt(self) -> Self {
+                    $t::sqrt(self)
+                }
+
+                fn powi(self, exponent: i32) -> Self {
+                    $t::powi(self, exponent)
+                }
+
+                fn powf(self, exponent: Self) -> Self {
+                    $t::powf(self, exponent)
+                }
+
+                fn exp(self) -> Self {
+                    $t::exp(self)
+                }
+
+                fn ln(self) -> Self {
+                    $t::ln(self)
+                }
+
+                fn sin(self) -> Self {
+                    $t::sin(self)
+                }
+
+                fn cos(self) -> Self {
+                    $t::cos(self)
+                }
+
+                fn atan2(self, other: Self) -> Self {
+                    $t::atan2(self, other)
+                }
+
+                fn hypot(self, other: Self) -> Self {
+                    $t::hypot(self, other)
+                }
+
+                fn is_finite(self) -> bool {
+                    $t::is_finite(self)
+                }
+
+                fn is_nan(self) -> bool {
+                    $t::is_nan(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_integer!(u8 i8 u16 i16 u32 i32 u64 i64 usize isize i128 u128);
+impl_unsigned_integer!(u8 u16 u32 u64 usize u128);
+impl_signed_integer!(i8 i16 i32 i64 isize i128);
+impl_float!(f32 f64);
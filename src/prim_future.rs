@@ -0,0 +1,157 @@
+use crossbeam_utils::sync::Parker;
+use futures_lite::pin;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::thread;
+
+pub use blocking::{BlockingConfig, BlockingError, BlockingPool};
+use channel::oneshot;
+use instrument::EventKind;
+
+pub mod blocking;
+pub mod channel;
+pub mod executor;
+pub mod instrument;
+#[cfg(target_os = "linux")]
+pub mod reactor;
+pub mod sync;
+pub mod timer;
+pub mod work_stealing;
+
+#[test]
+fn test_spawn_blocking() {
+    let fut = spawn_blocking(|| {
+        thread::sleep(std::time::Duration::from_millis(50));
+        1200
+    });
+    assert_eq!(block_on(fut), 1200);
+}
+
+#[test]
+fn test_spawn_blocking_panic() {
+    let result = block_on(spawn_blocking(|| -> u8 { panic!("oops") }).try_join());
+    assert_eq!(result, Err(BlockingError::Panicked("oops".to_string())));
+}
+
+/// Run `closure` on the global `BlockingPool`. If the pool is saturated the returned future waits
+/// for room before submitting the closure.
+pub fn spawn_blocking<R, F>(closure: F) -> SpawnBlocking<R>
+where
+    F: FnOnce() -> R,
+    F: Send + 'static,
+    R: Send + 'static,
+{
+    BlockingPool::global().spawn(closure)
+}
+
+/// Like `spawn_blocking`, but fails immediately if the global pool is saturated
+pub fn try_spawn_blocking<R, F>(closure: F) -> Result<SpawnBlocking<R>, BlockingError>
+where
+    F: FnOnce() -> R,
+    F: Send + 'static,
+    R: Send + 'static,
+{
+    BlockingPool::global().try_spawn(closure)
+}
+
+/// Resolves to the closure's return value. If the closure panicked, awaiting this re-raises the
+/// panic in the awaiting task; use `try_join` to get it back as an error instead.
+pub struct SpawnBlocking<T> {
+    result: oneshot::Receiver<thread::Result<T>>,
+    /// The closure, while it waits for room in a saturated pool
+    pending: Option<blocking::Pending>,
+}
+
+impl<T> SpawnBlocking<T> {
+    fn new(
+        result: oneshot::Receiver<thread::Result<T>>,
+        pending: Option<blocking::Pending>,
+    ) -> Self {
+        SpawnBlocking { result, pending }
+    }
+
+    pub fn try_join(self) -> TryJoin<T> {
+        TryJoin(self)
+    }
+
+    fn poll_result(&mut self, cx: &mut Context<'_>) -> Poll<thread::Result<T>> {
+        if let Some(pending) = self.pending.take() {
+            if let Err(pending) = pending.submit(Some(cx.waker())) {
+                self.pending = Some(pending);
+                return Poll::Pending;
+            }
+        }
+        // Workers finish off the queue even when the pool is dropped, so the job always runs
+        Pin::new(&mut self.result)
+            .poll(cx)
+            .map(|result| result.expect("Blocking job was dropped without running"))
+    }
+}
+
+impl<R> Future for SpawnBlocking<R> {
+    type Output = R;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.poll_result(cx) {
+            Poll::Ready(Ok(value)) => Poll::Ready(value),
+            Poll::Ready(Err(payload)) => std::panic::resume_unwind(payload),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Resolves to the closure's return value, or to the panic it raised
+pub struct TryJoin<T>(SpawnBlocking<T>);
+
+impl<R> Future for TryJoin<R> {
+    type Output = Result<R, BlockingError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.0
+            .poll_result(cx)
+            .map(|result| result.map_err(BlockingError::from_panic))
+    }
+}
+
+/// Run `future` to completion on the current thread, parking it whenever the future is pending
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    block_on_named("block_on", future)
+}
+
+/// Like `block_on`, with `name` identifying the future to the instrumentation hook
+pub fn block_on_named<F: Future>(name: &str, future: F) -> F::Output {
+    let probe = Arc::new(instrument::Probe::new(name));
+    let parker = Parker::new();
+    let unparker = parker.unparker().clone();
+    let waker = waker_fn::waker_fn({
+        let probe = probe.clone();
+        move || {
+            if probe.is_enabled() {
+                let source = thread::current().name().unwrap_or("unnamed").to_string();
+                probe.emit(EventKind::Wake { source });
+            }
+            unparker.unpark()
+        }
+    });
+    let mut context = Context::from_waker(&waker);
+
+    pin!(future);
+
+    loop {
+        probe.emit(EventKind::PollStart);
+        let poll = future.as_mut().poll(&mut context);
+        probe.emit(EventKind::PollEnd {
+            ready: poll.is_ready(),
+        });
+        match poll {
+            Poll::Ready(value) => return value,
+            Poll::Pending => {
+                probe.emit(EventKind::ParkStart);
+                parker.park();
+                probe.emit(EventKind::ParkEnd);
+            }
+        }
+    }
+}
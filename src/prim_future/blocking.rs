@@ -0,0 +1,323 @@
+use super::channel::oneshot;
+use super::SpawnBlocking;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::task::Waker;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(test)]
+use super::block_on;
+#[cfg(test)]
+use std::sync::mpsc;
+
+#[test]
+fn test_burst_stays_bounded() {
+    let pool = BlockingPool::new(BlockingConfig {
+        max_threads: 8,
+        ..Default::default()
+    });
+    let handles = (0..10_000_u64)
+        .map(|n| pool.spawn(move || n))
+        .collect::<Vec<_>>();
+    assert!(pool.threads() <= 8);
+    let total = handles.into_iter().map(block_on).sum::<u64>();
+    assert_eq!(total, 49_995_000);
+}
+
+#[test]
+fn test_saturated_pool() {
+    let pool = Arc::new(BlockingPool::new(BlockingConfig {
+        max_threads: 1,
+        max_queue: 1,
+        ..Default::default()
+    }));
+    let (release, gate) = mpsc::channel::<()>();
+    let busy = pool.spawn(move || gate.recv().unwrap());
+    let queued = pool.spawn(|| "queued");
+
+    assert_eq!(
+        pool.try_spawn(|| "rejected").err(),
+        Some(BlockingError::Saturated)
+    );
+
+    // Spawning doesn't block the caller; the closure gets in once the queue drains
+    let waiting = pool.spawn(|| "waited");
+    release.send(()).unwrap();
+
+    block_on(busy);
+    assert_eq!(block_on(queued), "queued");
+    assert_eq!(block_on(waiting), "waited");
+}
+
+#[test]
+fn test_idle_threads_exit() {
+    let pool = BlockingPool::new(BlockingConfig {
+        idle_timeout: Duration::from_millis(20),
+        ..Default::default()
+    });
+    let handles = (0..4)
+        .map(|_| pool.spawn(|| thread::sleep(Duration::from_millis(20))))
+        .collect::<Vec<_>>();
+    assert_eq!(pool.threads(), 4);
+    handles.into_iter().for_each(block_on);
+    thread::sleep(Duration::from_millis(200));
+    assert_eq!(pool.threads(), 0);
+}
+
+#[test]
+fn test_panic_becomes_error() {
+    let pool = BlockingPool::new(BlockingConfig::default());
+    let result = block_on(
+        pool.spawn(|| -> u32 { panic!("ran out of stormlight") })
+            .try_join(),
+    );
+    assert_eq!(
+        result,
+        Err(BlockingError::Panicked("ran out of stormlight".to_string()))
+    );
+    // The worker survives the panic
+    assert_eq!(block_on(pool.spawn(|| 7)), 7);
+}
+
+/// Limits for a `BlockingPool`
+#[derive(Debug, Clone)]
+pub struct BlockingConfig {
+    /// The most threads which will ever run closures at once
+    pub max_threads: usize,
+    /// How many closures may wait for a thread once they're all busy
+    pub max_queue: usize,
+    /// How long a thread with nothing to do sticks around before exiting
+    pub idle_timeout: Duration,
+}
+
+impl Default for BlockingConfig {
+    fn default() -> Self {
+        BlockingConfig {
+            max_threads: 64,
+            max_queue: 4096,
+            idle_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockingError {
+    /// Every thread was busy and the queue was full
+    Saturated,
+    /// The closure panicked with this message
+    Panicked(String),
+}
+
+impl Display for BlockingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlockingError::Saturated => write!(f, "blocking pool is saturated"),
+            BlockingError::Panicked(message) => write!(f, "blocking task panicked: {}", message),
+        }
+    }
+}
+
+impl Error for BlockingError {}
+
+impl BlockingError {
+    pub(crate) fn from_panic(payload: Box<dyn std::any::Any + Send>) -> Self {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "non-string panic payload".to_string());
+        BlockingError::Panicked(message)
+    }
+}
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+struct PoolState {
+    queue: VecDeque<Job>,
+    /// Futures holding jobs which didn't fit, to be woken when a job leaves the queue
+    waiting: Vec<Waker>,
+    threads: usize,
+    idle: usize,
+    shutting_down: bool,
+}
+
+struct PoolInner {
+    config: BlockingConfig,
+    state: Mutex<PoolState>,
+    /// Signalled when a job is queued, or when the pool shuts down
+    work: Condvar,
+}
+
+/// A pool of threads for running blocking closures. Threads are started on demand up to
+/// `max_threads` and exit again after sitting idle for `idle_timeout`.
+pub struct BlockingPool {
+    inner: Arc<PoolInner>,
+}
+
+impl BlockingPool {
+    pub fn new(config: BlockingConfig) -> Self {
+        let config = BlockingConfig {
+            max_threads: config.max_threads.max(1),
+            ..config
+        };
+        BlockingPool {
+            inner: Arc::new(PoolInner {
+                config,
+                state: Mutex::new(PoolState {
+                    queue: VecDeque::new(),
+                    waiting: vec![],
+                    threads: 0,
+                    idle: 0,
+                    shutting_down: false,
+                }),
+                work: Condvar::new(),
+            }),
+        }
+    }
+
+    /// The pool behind `spawn_blocking`, created with the default limits on first use
+    pub fn global() -> &'static BlockingPool {
+        static GLOBAL: OnceLock<BlockingPool> = OnceLock::new();
+        GLOBAL.get_or_init(|| BlockingPool::new(BlockingConfig::default()))
+    }
+
+    /// Run `closure` on the pool. When the pool is saturated the closure is held by the returned
+    /// future, which submits it once there's room, so the calling thread is never blocked.
+    pub fn spawn<R, F>(&self, closure: F) -> SpawnBlocking<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        self.submit(closure, true)
+            .expect("Waiting submissions are never rejected")
+    }
+
+    /// Run `closure` on the pool, failing straight away if the pool is saturated
+    pub fn try_spawn<R, F>(&self, closure: F) -> Result<SpawnBlocking<R>, BlockingError>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        self.submit(closure, false)
+    }
+
+    /// The number of threads currently alive, busy or not
+    pub fn threads(&self) -> usize {
+        self.inner.state.lock().expect("Failed to lock").threads
+    }
+
+    fn submit<R, F>(&self, closure: F, wait: bool) -> Result<SpawnBlocking<R>, BlockingError>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        let job: Job = Box::new(move || {
+            let _ = sender.send(panic::catch_unwind(AssertUnwindSafe(closure)));
+        });
+
+        let pending = Pending {
+            pool: self.inner.clone(),
+            job,
+        };
+        match pending.submit(None) {
+            Ok(()) => Ok(SpawnBlocking::new(receiver, None)),
+            Err(pending) if wait => Ok(SpawnBlocking::new(receiver, Some(pending))),
+            Err(_) => Err(BlockingError::Saturated),
+        }
+    }
+}
+
+/// A job which didn't fit into a saturated pool, kept by its `SpawnBlocking` until there's room
+pub(crate) struct Pending {
+    pool: Arc<PoolInner>,
+    job: Job,
+}
+
+impl Pending {
+    /// Queue the job, or hand it back if the pool is still saturated, in which case `waker` is
+    /// woken once a job leaves the queue
+    pub(crate) fn submit(self, waker: Option<&Waker>) -> Result<(), Pending> {
+        let inner = &self.pool;
+        let config = &inner.config;
+        let mut state = inner.state.lock().expect("Failed to lock");
+        // Room for a job is an idle thread, a thread we could still start, or a queue slot
+        let capacity = state.idle + (config.max_threads - state.threads) + config.max_queue;
+        if state.queue.len() >= capacity {
+            if let Some(waker) = waker {
+                state.waiting.push(waker.clone());
+            }
+            drop(state);
+            return Err(self);
+        }
+
+        state.queue.push_back(self.job);
+        if state.queue.len() > state.idle && state.threads < config.max_threads {
+            state.threads += 1;
+            let spawned = thread::Builder::new()
+                .name("prim-blocking".to_string())
+                .spawn({
+                    let inner = inner.clone();
+                    move || run_worker(inner)
+                });
+            if let Err(e) = spawned {
+                state.threads -= 1;
+                // Another thread will get round to the job, but with none at all it'd never run
+                if state.threads == 0 {
+                    state.queue.pop_back();
+                    drop(state);
+                    panic!("Failed to spawn blocking thread: {}", e);
+                }
+            }
+        } else {
+            inner.work.notify_one();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for BlockingPool {
+    /// Idle threads exit right away; busy ones finish off the queue first
+    fn drop(&mut self) {
+        self.inner
+            .state
+            .lock()
+            .expect("Failed to lock")
+            .shutting_down = true;
+        self.inner.work.notify_all();
+    }
+}
+
+fn run_worker(inner: Arc<PoolInner>) {
+    let mut state = inner.state.lock().expect("Failed to lock");
+    loop {
+        if let Some(job) = state.queue.pop_front() {
+            // Everyone waiting gets to try again, since any of them may have gone away meanwhile
+            let waiting = std::mem::take(&mut state.waiting);
+            drop(state);
+            waiting.into_iter().for_each(Waker::wake);
+            // Panics are caught inside the job, so the thread outlives them
+            job();
+            state = inner.state.lock().expect("Failed to lock");
+            continue;
+        }
+        if state.shutting_down {
+            break;
+        }
+        state.idle += 1;
+        let (guard, timeout) = inner
+            .work
+            .wait_timeout(state, inner.config.idle_timeout)
+            .expect("Failed to lock");
+        state = guard;
+        state.idle -= 1;
+        if timeout.timed_out() && state.queue.is_empty() {
+            break;
+        }
+    }
+    state.threads -= 1;
+}
@@ -0,0 +1,90 @@
+//! Channels for passing values between tasks. They only rely on `Waker`s for notification, so
+//! they work with any executor: ours, async-std's, or a plain `block_on`.
+
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+
+pub mod broadcast;
+pub mod mpsc;
+pub mod oneshot;
+
+/// Every sender went away, and there's nothing left to receive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecvError;
+
+impl Display for RecvError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "receiving on a closed channel")
+    }
+}
+
+impl Error for RecvError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    Empty,
+    Closed,
+}
+
+impl Display for TryRecvError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TryRecvError::Empty => write!(f, "receiving on an empty channel"),
+            TryRecvError::Closed => write!(f, "receiving on a closed channel"),
+        }
+    }
+}
+
+impl Error for TryRecvError {}
+
+/// The receiver went away; the value which couldn't be delivered is handed back
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+impl<T> Debug for SendError<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SendError(..)")
+    }
+}
+
+impl<T> Display for SendError<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "sending on a closed channel")
+    }
+}
+
+impl<T> Error for SendError<T> {}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TrySendError<T> {
+    Full(T),
+    Closed(T),
+}
+
+impl<T> TrySendError<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            TrySendError::Full(value) | TrySendError::Closed(value) => value,
+        }
+    }
+}
+
+impl<T> Debug for TrySendError<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrySendError::Full(_) => write!(f, "Full(..)"),
+            TrySendError::Closed(_) => write!(f, "Closed(..)"),
+        }
+    }
+}
+
+impl<T> Display for TrySendError<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrySendError::Full(_) => write!(f, "sending on a full channel"),
+            TrySendError::Closed(_) => write!(f, "sending on a closed channel"),
+        }
+    }
+}
+
+impl<T> Error for TrySendError<T> {}
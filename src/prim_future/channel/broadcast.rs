@@ -0,0 +1,275 @@
+use super::{SendError, TryRecvError};
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+#[cfg(test)]
+use crate::prim_future::block_on;
+
+#[test]
+fn test_every_receiver_sees_every_value() {
+    let (tx, mut first) = channel(16);
+    let mut second = tx.subscribe();
+
+    assert_eq!(tx.send("Windrunner"), Ok(2));
+    assert_eq!(tx.send("Skybreaker"), Ok(2));
+    drop(tx);
+    for rx in [&mut first, &mut second] {
+        assert_eq!(block_on(rx.recv()), Ok("Windrunner"));
+        assert_eq!(block_on(rx.recv()), Ok("Skybreaker"));
+        assert_eq!(block_on(rx.recv()), Err(RecvError::Closed));
+    }
+}
+
+#[test]
+fn test_slow_receiver_lags() {
+    let (tx, mut rx) = channel(2);
+    for n in 0..5 {
+        tx.send(n).unwrap();
+    }
+    assert_eq!(block_on(rx.recv()), Err(RecvError::Lagged(3)));
+    assert_eq!(block_on(rx.recv()), Ok(3));
+    assert_eq!(block_on(rx.recv()), Ok(4));
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+}
+
+#[test]
+fn test_send_without_receivers() {
+    let (tx, rx) = channel(2);
+    drop(rx);
+    assert_eq!(tx.send(1).map_err(|e| e.0), Err(1));
+    // A late subscriber only sees what is sent after it joined
+    let mut rx = tx.subscribe();
+    tx.send(2).unwrap();
+    assert_eq!(rx.try_recv(), Ok(2));
+}
+
+#[test]
+fn test_broadcast_across_threads() {
+    let (tx, rx) = channel(64);
+    let readers = (0..4)
+        .map(|_| {
+            let mut rx = rx.clone();
+            std::thread::spawn(move || {
+                block_on(async {
+                    let mut total = 0;
+                    while let Ok(n) = rx.recv().await {
+                        total += n;
+                    }
+                    total
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+    drop(rx);
+    async_std::task::block_on(async {
+        for n in 1..=10 {
+            tx.send(n).unwrap();
+        }
+    });
+    drop(tx);
+    for reader in readers {
+        assert_eq!(reader.join().unwrap(), 55);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvError {
+    /// Every sender went away, and there's nothing left to receive
+    Closed,
+    /// The receiver fell behind and this many values were dropped before it could see them
+    Lagged(u64),
+}
+
+impl Display for RecvError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecvError::Closed => write!(f, "receiving on a closed channel"),
+            RecvError::Lagged(missed) => write!(f, "receiver lagged by {} values", missed),
+        }
+    }
+}
+
+impl Error for RecvError {}
+
+struct Inner<T> {
+    /// The most recent values, each tagged with its position in the stream
+    buffer: VecDeque<(u64, T)>,
+    capacity: usize,
+    next_position: u64,
+    senders: usize,
+    receivers: usize,
+    wakers: HashMap<u64, Waker>,
+    next_receiver: u64,
+}
+
+impl<T> Inner<T> {
+    /// Count a new receiver and hand out its id
+    fn add_receiver(&mut self) -> u64 {
+        let id = self.next_receiver;
+        self.next_receiver += 1;
+        self.receivers += 1;
+        id
+    }
+
+    fn take_wakers(&mut self) -> Vec<Waker> {
+        self.wakers.drain().map(|(_, waker)| waker).collect()
+    }
+}
+
+/// A channel where every receiver sees every value. At most `capacity` values are kept around,
+/// so a receiver which falls further behind than that misses the oldest ones.
+pub fn channel<T: Clone>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    let mut inner = Inner {
+        buffer: VecDeque::new(),
+        capacity: capacity.max(1),
+        next_position: 0,
+        senders: 1,
+        receivers: 0,
+        wakers: HashMap::new(),
+        next_receiver: 0,
+    };
+    let id = inner.add_receiver();
+    let inner = Arc::new(Mutex::new(inner));
+    (Sender(inner.clone()), Receiver { inner, id, next: 0 })
+}
+
+pub struct Sender<T>(Arc<Mutex<Inner<T>>>);
+
+impl<T: Clone> Sender<T> {
+    /// Publish `value` to every current receiver, returning how many there are. This never
+    /// waits: when the buffer is full the oldest value is dropped.
+    pub fn send(&self, value: T) -> Result<usize, SendError<T>> {
+        let (receivers, wakers) = {
+            let mut inner = self.0.lock().expect("Failed to lock");
+            if inner.receivers == 0 {
+                return Err(SendError(value));
+            }
+            let position = inner.next_position;
+            inner.next_position += 1;
+            inner.buffer.push_back((position, value));
+            if inner.buffer.len() > inner.capacity {
+                inner.buffer.pop_front();
+            }
+            (inner.receivers, inner.take_wakers())
+        };
+        wakers.into_iter().for_each(Waker::wake);
+        Ok(receivers)
+    }
+
+    /// A new receiver which sees the values sent from now on
+    pub fn subscribe(&self) -> Receiver<T> {
+        let mut inner = self.0.lock().expect("Failed to lock");
+        Receiver {
+            inner: self.0.clone(),
+            id: inner.add_receiver(),
+            next: inner.next_position,
+        }
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.0.lock().expect("Failed to lock").senders += 1;
+        Sender(self.0.clone())
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let wakers = {
+            let mut inner = self.0.lock().expect("Failed to lock");
+            inner.senders -= 1;
+            if inner.senders == 0 {
+                inner.take_wakers()
+            } else {
+                vec![]
+            }
+        };
+        wakers.into_iter().for_each(Waker::wake);
+    }
+}
+
+pub struct Receiver<T> {
+    inner: Arc<Mutex<Inner<T>>>,
+    id: u64,
+    /// The position of the next value this receiver wants
+    next: u64,
+}
+
+impl<T: Clone> Receiver<T> {
+    pub fn recv(&mut self) -> RecvFuture<'_, T> {
+        RecvFuture { receiver: self }
+    }
+
+    /// Like `recv`, except that lagging is silently skipped over
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        loop {
+            match self.poll_next(None) {
+                Poll::Ready(Ok(value)) => return Ok(value),
+                Poll::Ready(Err(RecvError::Lagged(_))) => continue,
+                Poll::Ready(Err(RecvError::Closed)) => return Err(TryRecvError::Closed),
+                Poll::Pending => return Err(TryRecvError::Empty),
+            }
+        }
+    }
+
+    fn poll_next(&mut self, waker: Option<&Waker>) -> Poll<Result<T, RecvError>> {
+        let mut inner = self.inner.lock().expect("Failed to lock");
+        if let Some((oldest, _)) = inner.buffer.front() {
+            if self.next < *oldest {
+                let missed = oldest - self.next;
+                self.next = *oldest;
+                return Poll::Ready(Err(RecvError::Lagged(missed)));
+            }
+        }
+        let oldest = inner.next_position - inner.buffer.len() as u64;
+        if let Some((_, value)) = inner.buffer.get((self.next - oldest) as usize) {
+            self.next += 1;
+            return Poll::Ready(Ok(value.clone()));
+        }
+        if inner.senders == 0 {
+            return Poll::Ready(Err(RecvError::Closed));
+        }
+        if let Some(waker) = waker {
+            inner.wakers.insert(self.id, waker.clone());
+        }
+        Poll::Pending
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    /// The clone picks up from the same position as the original
+    fn clone(&self) -> Self {
+        let mut inner = self.inner.lock().expect("Failed to lock");
+        Receiver {
+            inner: self.inner.clone(),
+            id: inner.add_receiver(),
+            next: self.next,
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut inner = self.inner.lock().expect("Failed to lock");
+        inner.receivers -= 1;
+        inner.wakers.remove(&self.id);
+    }
+}
+
+pub struct RecvFuture<'a, T> {
+    receiver: &'a mut Receiver<T>,
+}
+
+impl<T: Clone> Future for RecvFuture<'_, T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.receiver.poll_next(Some(cx.waker()))
+    }
+}
@@ -0,0 +1,387 @@
+use super::{RecvError, SendError, TryRecvError, TrySendError};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+#[cfg(test)]
+use crate::prim_future::block_on;
+#[cfg(test)]
+use std::time::Duration;
+
+#[test]
+fn test_mpsc_in_order() {
+    let (tx, mut rx) = channel(4);
+    let producer = std::thread::spawn(move || {
+        block_on(async {
+            for n in 0..100 {
+                tx.send(n).await.unwrap();
+            }
+        })
+    });
+    let received = block_on(async {
+        let mut received = vec![];
+        while let Ok(n) = rx.recv().await {
+            received.push(n);
+        }
+        received
+    });
+    producer.join().unwrap();
+    assert_eq!(received, (0..100).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_backpressure() {
+    let (tx, mut rx) = channel(2);
+    tx.try_send(1).unwrap();
+    tx.try_send(2).unwrap();
+    assert_eq!(tx.try_send(3), Err(TrySendError::Full(3)));
+
+    let blocked = std::thread::spawn({
+        let tx = tx.clone();
+        move || block_on(tx.send(3))
+    });
+    std::thread::sleep(Duration::from_millis(50));
+    assert!(!blocked.is_finished());
+    assert_eq!(block_on(rx.recv()), Ok(1));
+    blocked.join().unwrap().unwrap();
+    assert_eq!(rx.try_recv(), Ok(2));
+    assert_eq!(rx.try_recv(), Ok(3));
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+}
+
+#[test]
+fn test_try_send_waits_its_turn() {
+    let waker = waker_fn::waker_fn(|| {});
+    let mut cx = Context::from_waker(&waker);
+    let (tx, mut rx) = channel(1);
+    tx.try_send(1).unwrap();
+    let mut blocked = tx.send(2);
+    assert!(Pin::new(&mut blocked).poll(&mut cx).is_pending());
+    assert_eq!(tx.try_send(3), Err(TrySendError::Full(3)));
+
+    // The room which opens up is held for the blocked sender until it gets polled again
+    assert_eq!(rx.try_recv(), Ok(1));
+    assert_eq!(tx.try_send(3), Err(TrySendError::Full(3)));
+    assert_eq!(Pin::new(&mut blocked).poll(&mut cx), Poll::Ready(Ok(())));
+    assert_eq!(rx.try_recv(), Ok(2));
+    assert_eq!(tx.try_send(3), Ok(()));
+}
+
+#[test]
+fn test_close_detection() {
+    let (tx, mut rx) = channel(8);
+    let other = tx.clone();
+    block_on(tx.send("Kaladin")).unwrap();
+    drop(tx);
+    drop(other);
+    // Whatever was queued is still delivered before the channel reports being closed
+    assert_eq!(block_on(rx.recv()), Ok("Kaladin"));
+    assert_eq!(block_on(rx.recv()), Err(RecvError));
+
+    let (tx, rx) = channel(1);
+    drop(rx);
+    assert_eq!(block_on(tx.send(1)).map_err(|e| e.0), Err(1));
+}
+
+#[test]
+fn test_drop_blocked_send_after_receiver() {
+    let waker = waker_fn::waker_fn(|| {});
+    let mut cx = Context::from_waker(&waker);
+    for poll_again in [false, true] {
+        let (tx, rx) = channel(1);
+        tx.try_send(1).unwrap();
+        let mut blocked = tx.send(2);
+        assert!(Pin::new(&mut blocked).poll(&mut cx).is_pending());
+        drop(rx);
+        if poll_again {
+            let polled = Pin::new(&mut blocked).poll(&mut cx);
+            assert!(matches!(polled, Poll::Ready(Err(SendError(2)))));
+        }
+        drop(blocked);
+    }
+}
+
+#[test]
+fn test_mpsc_in_async_std() {
+    async_std::task::block_on(async {
+        let (tx, mut rx) = channel(1);
+        for n in 0..4 {
+            let tx = tx.clone();
+            async_std::task::spawn(async move { tx.send(n).await.unwrap() });
+        }
+        drop(tx);
+        let mut received = vec![];
+        while let Ok(n) = rx.recv().await {
+            received.push(n);
+        }
+        received.sort();
+        assert_eq!(received, vec![0, 1, 2, 3]);
+    });
+}
+
+struct Inner<T> {
+    queue: VecDeque<T>,
+    capacity: usize,
+    senders: usize,
+    receiver_alive: bool,
+    receiver: Option<Waker>,
+    /// Senders waiting for room, in the order they started waiting
+    blocked: VecDeque<(u64, Waker)>,
+    /// Senders taken off `blocked` and woken, which haven't had their turn yet
+    woken: usize,
+    next_ticket: u64,
+}
+
+impl<T> Inner<T> {
+    fn wake_receiver(&mut self) -> Option<Waker> {
+        self.receiver.take()
+    }
+
+    fn wake_blocked_sender(&mut self) -> Option<Waker> {
+        let (_, waker) = self.blocked.pop_front()?;
+        self.woken += 1;
+        Some(waker)
+    }
+
+    /// Whether anyone is already waiting for room, and so gets it before a new sender does
+    fn has_waiting_senders(&self) -> bool {
+        !self.blocked.is_empty() || self.woken > 0
+    }
+}
+
+/// A channel holding at most `capacity` values; senders wait for room once it's full
+pub fn channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    let inner = Arc::new(Mutex::new(Inner {
+        queue: VecDeque::with_capacity(capacity),
+        capacity: capacity.max(1),
+        senders: 1,
+        receiver_alive: true,
+        receiver: None,
+        blocked: VecDeque::new(),
+        woken: 0,
+        next_ticket: 0,
+    }));
+    (Sender(inner.clone()), Receiver(inner))
+}
+
+pub struct Sender<T>(Arc<Mutex<Inner<T>>>);
+
+impl<T> Sender<T> {
+    /// Send `value`, waiting for room if the channel is full
+    pub fn send(&self, value: T) -> SendFuture<'_, T> {
+        SendFuture {
+            sender: self,
+            value: Some(value),
+            ticket: None,
+        }
+    }
+
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        let waker = {
+            let mut inner = self.0.lock().expect("Failed to lock");
+            if !inner.receiver_alive {
+                return Err(TrySendError::Closed(value));
+            }
+            // Room that a blocked sender is waiting for isn't ours to take
+            if inner.queue.len() >= inner.capacity || inner.has_waiting_senders() {
+                return Err(TrySendError::Full(value));
+            }
+            inner.queue.push_back(value);
+            inner.wake_receiver()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    pub fn is_closed(&self) -> bool {
+        !self.0.lock().expect("Failed to lock").receiver_alive
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.0.lock().expect("Failed to lock").senders += 1;
+        Sender(self.0.clone())
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let waker = {
+            let mut inner = self.0.lock().expect("Failed to lock");
+            inner.senders -= 1;
+            if inner.senders == 0 {
+                inner.wake_receiver()
+            } else {
+                None
+            }
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+pub struct SendFuture<'a, T> {
+    sender: &'a Sender<T>,
+    value: Option<T>,
+    /// Our place in the queue of blocked senders, once we've had to wait
+    ticket: Option<u64>,
+}
+
+// The value is never pinned, so moving the future around is harmless
+impl<T> Unpin for SendFuture<'_, T> {}
+
+impl<T> Future for SendFuture<'_, T> {
+    type Output = Result<(), SendError<T>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let mut inner = this.sender.0.lock().expect("Failed to lock");
+        let value = this.value.take().expect("Send polled after completion");
+        if !inner.receiver_alive {
+            this.ticket = None;
+            return Poll::Ready(Err(SendError(value)));
+        }
+
+        // Only jump the queue of blocked senders if we were the one woken up
+        let our_turn = match this.ticket {
+            Some(ticket) => !inner.blocked.iter().any(|(t, _)| *t == ticket),
+            None => !inner.has_waiting_senders(),
+        };
+        if our_turn && this.ticket.is_some() {
+            // We were woken, and either send now or go back to the front of the line
+            inner.woken -= 1;
+        }
+        if our_turn && inner.queue.len() < inner.capacity {
+            this.ticket = None;
+            inner.queue.push_back(value);
+            let receiver = inner.wake_receiver();
+            // Room may be left for whoever is next in line
+            let next = if inner.queue.len() < inner.capacity {
+                inner.wake_blocked_sender()
+            } else {
+                None
+            };
+            drop(inner);
+            receiver.into_iter().chain(next).for_each(Waker::wake);
+            return Poll::Ready(Ok(()));
+        }
+
+        this.value = Some(value);
+        match this.ticket {
+            Some(ticket) if our_turn => {
+                // Woken, but someone else filled the slot first, so go back to the front
+                inner.blocked.push_front((ticket, cx.waker().clone()));
+            }
+            Some(ticket) => {
+                if let Some(entry) = inner.blocked.iter_mut().find(|(t, _)| *t == ticket) {
+                    entry.1 = cx.waker().clone();
+                }
+            }
+            None => {
+                let ticket = inner.next_ticket;
+                inner.next_ticket += 1;
+                inner.blocked.push_back((ticket, cx.waker().clone()));
+                this.ticket = Some(ticket);
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for SendFuture<'_, T> {
+    /// A cancelled send gives up its place, passing on a wake-up it may already have taken
+    fn drop(&mut self) {
+        let Some(ticket) = self.ticket else { return };
+        let waker = {
+            let mut inner = self.sender.0.lock().expect("Failed to lock");
+            // The receiver's drop already let go of every blocked sender
+            if !inner.receiver_alive {
+                return;
+            }
+            let before = inner.blocked.len();
+            inner.blocked.retain(|(t, _)| *t != ticket);
+            let was_woken = inner.blocked.len() == before;
+            if was_woken {
+                inner.woken -= 1;
+            }
+            if was_woken && inner.queue.len() < inner.capacity {
+                inner.wake_blocked_sender()
+            } else {
+                None
+            }
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+pub struct Receiver<T>(Arc<Mutex<Inner<T>>>);
+
+impl<T> Receiver<T> {
+    /// Wait for the next value. Once every sender has been dropped and the queue is empty, this
+    /// returns an error.
+    pub fn recv(&mut self) -> RecvFuture<'_, T> {
+        RecvFuture { receiver: self }
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let (value, waker) = {
+            let mut inner = self.0.lock().expect("Failed to lock");
+            match inner.queue.pop_front() {
+                Some(value) => (value, inner.wake_blocked_sender()),
+                None if inner.senders == 0 => return Err(TryRecvError::Closed),
+                None => return Err(TryRecvError::Empty),
+            }
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        Ok(value)
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let blocked = {
+            let mut inner = self.0.lock().expect("Failed to lock");
+            inner.receiver_alive = false;
+            std::mem::take(&mut inner.blocked)
+        };
+        for (_, waker) in blocked {
+            waker.wake();
+        }
+    }
+}
+
+pub struct RecvFuture<'a, T> {
+    receiver: &'a mut Receiver<T>,
+}
+
+impl<T> Future for RecvFuture<'_, T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.receiver.try_recv() {
+            Ok(value) => Poll::Ready(Ok(value)),
+            Err(TryRecvError::Closed) => Poll::Ready(Err(RecvError)),
+            Err(TryRecvError::Empty) => {
+                let mut inner = self.receiver.0.lock().expect("Failed to lock");
+                // A value or the last sender's drop may have slipped in since `try_recv`
+                if !inner.queue.is_empty() || inner.senders == 0 {
+                    drop(inner);
+                    cx.waker().wake_by_ref();
+                } else {
+                    inner.receiver = Some(cx.waker().clone());
+                }
+                Poll::Pending
+            }
+        }
+    }
+}
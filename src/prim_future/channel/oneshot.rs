@@ -0,0 +1,131 @@
+use super::{RecvError, TryRecvError};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+#[cfg(test)]
+use crate::prim_future::block_on;
+
+#[test]
+fn test_oneshot() {
+    let (tx, rx) = channel();
+    std::thread::spawn(move || tx.send("Honor is dead").unwrap());
+    assert_eq!(block_on(rx), Ok("Honor is dead"));
+}
+
+#[test]
+fn test_sender_dropped() {
+    let (tx, rx) = channel::<u8>();
+    drop(tx);
+    assert_eq!(block_on(rx), Err(RecvError));
+}
+
+#[test]
+fn test_receiver_dropped() {
+    let (tx, mut rx) = channel();
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    drop(rx);
+    assert!(tx.is_closed());
+    assert_eq!(tx.send(3), Err(3));
+}
+
+#[test]
+fn test_oneshot_in_async_std() {
+    let (tx, rx) = channel();
+    async_std::task::block_on(async {
+        async_std::task::spawn(async move { tx.send(5).unwrap() });
+        assert_eq!(rx.await, Ok(5));
+    });
+}
+
+struct Inner<T> {
+    value: Option<T>,
+    waker: Option<Waker>,
+    sender_alive: bool,
+    receiver_alive: bool,
+}
+
+/// A channel for handing over exactly one value
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let inner = Arc::new(Mutex::new(Inner {
+        value: None,
+        waker: None,
+        sender_alive: true,
+        receiver_alive: true,
+    }));
+    (Sender(inner.clone()), Receiver(inner))
+}
+
+pub struct Sender<T>(Arc<Mutex<Inner<T>>>);
+
+impl<T> Sender<T> {
+    /// Hand `value` over to the receiver, or give it back if the receiver has been dropped
+    pub fn send(self, value: T) -> Result<(), T> {
+        let waker = {
+            let mut inner = self.0.lock().expect("Failed to lock");
+            if !inner.receiver_alive {
+                return Err(value);
+            }
+            inner.value = Some(value);
+            inner.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    pub fn is_closed(&self) -> bool {
+        !self.0.lock().expect("Failed to lock").receiver_alive
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let waker = {
+            let mut inner = self.0.lock().expect("Failed to lock");
+            inner.sender_alive = false;
+            inner.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// Resolves to the value, or to an error if the sender was dropped without sending one
+pub struct Receiver<T>(Arc<Mutex<Inner<T>>>);
+
+impl<T> Receiver<T> {
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let mut inner = self.0.lock().expect("Failed to lock");
+        match inner.value.take() {
+            Some(value) => Ok(value),
+            None if inner.sender_alive => Err(TryRecvError::Empty),
+            None => Err(TryRecvError::Closed),
+        }
+    }
+}
+
+impl<T> Future for Receiver<T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut inner = self.0.lock().expect("Failed to lock");
+        if let Some(value) = inner.value.take() {
+            return Poll::Ready(Ok(value));
+        }
+        if !inner.sender_alive {
+            return Poll::Ready(Err(RecvError));
+        }
+        inner.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.0.lock().expect("Failed to lock").receiver_alive = false;
+    }
+}
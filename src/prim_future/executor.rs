@@ -0,0 +1,384 @@
+use super::channel::oneshot;
+use crossbeam_utils::sync::{Parker, Unparker};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+
+#[cfg(test)]
+use super::spawn_blocking;
+#[cfg(test)]
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_spawn_and_join() {
+    let total = Executor::new().block_on(async {
+        let handles = (1..=10)
+            .map(|n| spawn(async move { n * n }))
+            .collect::<Vec<_>>();
+        let mut total = 0;
+        for handle in handles {
+            total += handle.await;
+        }
+        total
+    });
+    assert_eq!(total, 385);
+}
+
+#[test]
+fn test_blocking_tasks_run_concurrently() {
+    let started = Instant::now();
+    let results = Executor::new().block_on(async {
+        let handles = (0..5)
+            .map(|n| {
+                spawn(async move {
+                    spawn_blocking(move || {
+                        std::thread::sleep(Duration::from_millis(200));
+                        n
+                    })
+                    .await
+                })
+            })
+            .collect::<Vec<_>>();
+        join_all(handles).await
+    });
+    assert_eq!(results, vec![0, 1, 2, 3, 4]);
+    assert!(started.elapsed() < Duration::from_millis(800));
+}
+
+#[test]
+fn test_nested_spawn() {
+    let value = Executor::new()
+        .block_on(async { spawn(async { spawn(async { "inner" }).await.len() }).await });
+    assert_eq!(value, 5);
+}
+
+#[test]
+fn test_detached_tasks_still_run() {
+    let flag = Arc::new(AtomicBool::new(false));
+    Executor::new().block_on({
+        let flag = flag.clone();
+        async move {
+            drop(spawn(async move { flag.store(true, Ordering::SeqCst) }));
+            spawn(async {}).await;
+        }
+    });
+    assert!(flag.load(Ordering::SeqCst));
+}
+
+#[test]
+fn test_select() {
+    let winner = Executor::new().block_on(async {
+        let slow = spawn_blocking(|| {
+            std::thread::sleep(Duration::from_millis(500));
+            "slow"
+        });
+        let fast = spawn_blocking(|| "fast");
+        select(slow, fast).await
+    });
+    assert_eq!(winner, Either::Right("fast"));
+}
+
+#[test]
+fn test_wakes_queue_a_task_once() {
+    let executor = Executor::new();
+    let _guard = EnterGuard::enter(executor.queue.clone());
+    drop(spawn(std::future::pending::<()>()));
+    let task = executor.queue.pop().expect("Spawned task isn't queued");
+    task.clone().run();
+
+    let waker = Waker::from(task.clone());
+    for _ in 0..10 {
+        waker.wake_by_ref();
+    }
+    let queued = |executor: &Executor| {
+        let tasks = executor.queue.tasks.lock().unwrap();
+        tasks.as_ref().map_or(0, VecDeque::len)
+    };
+    assert_eq!(queued(&executor), 1);
+
+    // Waking the task once the executor is gone mustn't leave it stuck in the queue for good
+    let weak = Arc::downgrade(&task);
+    drop(task);
+    drop(executor);
+    waker.wake_by_ref();
+    drop(waker);
+    assert!(weak.upgrade().is_none());
+}
+
+#[test]
+#[should_panic(expected = "outside of an executor")]
+fn test_spawn_outside_executor() {
+    spawn(async {});
+}
+
+type BoxFuture = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+/// The tasks which have been woken up and are waiting to be polled. This is `None` once the
+/// executor is gone, and tasks woken after that are dropped instead of queued.
+struct RunQueue {
+    tasks: Mutex<Option<VecDeque<Arc<Task>>>>,
+    unparker: Unparker,
+}
+
+impl RunQueue {
+    fn push(&self, task: Arc<Task>) {
+        if let Some(tasks) = self.tasks.lock().expect("Failed to lock").as_mut() {
+            tasks.push_back(task);
+            self.unparker.unpark();
+        }
+    }
+
+    fn pop(&self) -> Option<Arc<Task>> {
+        self.tasks
+            .lock()
+            .expect("Failed to lock")
+            .as_mut()?
+            .pop_front()
+    }
+}
+
+struct Task {
+    future: Mutex<Option<BoxFuture>>,
+    queue: Arc<RunQueue>,
+    /// Set while the task sits in the queue, so that repeated wake-ups queue it only once
+    scheduled: AtomicBool,
+}
+
+impl Wake for Task {
+    fn wake(self: Arc<Self>) {
+        if !self.scheduled.swap(true, Ordering::SeqCst) {
+            self.queue.clone().push(self);
+        }
+    }
+}
+
+impl Task {
+    fn run(self: Arc<Self>) {
+        let waker = Waker::from(self.clone());
+        let mut context = Context::from_waker(&waker);
+        let mut slot = self.future.lock().expect("Failed to lock");
+        self.scheduled.store(false, Ordering::SeqCst);
+        if let Some(future) = slot.as_mut() {
+            if future.as_mut().poll(&mut context).is_ready() {
+                // Drop the finished future right away, later wake-ups then become no-ops
+                *slot = None;
+            }
+        }
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<RunQueue>>> = const { RefCell::new(None) };
+}
+
+/// A single-threaded executor. Spawned tasks are polled on the thread which calls `block_on`,
+/// interleaved with the main future, and the thread parks whenever there's nothing to do.
+pub struct Executor {
+    parker: Parker,
+    queue: Arc<RunQueue>,
+}
+
+impl Executor {
+    pub fn new() -> Self {
+        let parker = Parker::new();
+        let queue = Arc::new(RunQueue {
+            tasks: Mutex::new(Some(VecDeque::new())),
+            unparker: parker.unparker().clone(),
+        });
+        Executor { parker, queue }
+    }
+
+    /// Run `future` to completion, along with any tasks spawned while it runs. Tasks which are
+    /// still pending when `future` completes are dropped along with the executor.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        let _guard = EnterGuard::enter(self.queue.clone());
+
+        let woken = Arc::new(AtomicBool::new(true));
+        let waker = waker_fn::waker_fn({
+            let woken = woken.clone();
+            let unparker = self.parker.unparker().clone();
+            move || {
+                woken.store(true, Ordering::SeqCst);
+                unparker.unpark();
+            }
+        });
+        let mut context = Context::from_waker(&waker);
+        let mut future = std::pin::pin!(future);
+
+        loop {
+            if woken.swap(false, Ordering::SeqCst) {
+                if let Poll::Ready(value) = future.as_mut().poll(&mut context) {
+                    return value;
+                }
+            }
+            while let Some(task) = self.queue.pop() {
+                task.run();
+            }
+            if !woken.load(Ordering::SeqCst) {
+                self.parker.park();
+            }
+        }
+    }
+}
+
+impl Drop for Executor {
+    fn drop(&mut self) {
+        // Queued tasks point back at the queue, so break the cycle by dropping them here. Closing
+        // the queue also stops tasks woken later on, say by a blocking thread, from forming a new
+        // cycle. Dropping a task can wake others, so that happens after the lock is released.
+        let tasks = self.queue.tasks.lock().expect("Failed to lock").take();
+        drop(tasks);
+    }
+}
+
+impl Default for Executor {
+    fn default() -> Self {
+        Executor::new()
+    }
+}
+
+/// Makes `spawn` find the executor which is running on this thread
+struct EnterGuard(Option<Arc<RunQueue>>);
+
+impl EnterGuard {
+    fn enter(queue: Arc<RunQueue>) -> Self {
+        EnterGuard(CURRENT.with(|current| current.borrow_mut().replace(queue)))
+    }
+}
+
+impl Drop for EnterGuard {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// Spawn a task on the executor running on this thread. The task runs even if the returned
+/// handle is dropped.
+///
+/// # Panics
+/// When called outside of `Executor::block_on`.
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let queue = CURRENT
+        .with(|current| current.borrow().clone())
+        .expect("spawn called outside of an executor");
+
+    let (sender, receiver) = oneshot::channel();
+    let task = Arc::new(Task {
+        future: Mutex::new(Some(Box::pin(async move {
+            // Nobody minds if the handle has been dropped
+            let _ = sender.send(future.await);
+        }))),
+        queue: queue.clone(),
+        scheduled: AtomicBool::new(true),
+    });
+    queue.push(task);
+    JoinHandle(receiver)
+}
+
+/// Resolves to the output of a spawned task. Awaiting it panics if the task was dropped before it
+/// completed, which happens when it panics or its executor goes away.
+pub struct JoinHandle<T>(pub(super) oneshot::Receiver<T>);
+
+impl<T> Future for JoinHandle<T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0)
+            .poll(cx)
+            .map(|result| result.expect("Task was dropped before it completed"))
+    }
+}
+
+/// Wait for all of `futures` to complete, collecting their outputs in order
+pub fn join_all<I>(futures: I) -> JoinAll<I::Item>
+where
+    I: IntoIterator,
+    I::Item: Future,
+{
+    let pending = futures
+        .into_iter()
+        .map(|f| Some(Box::pin(f)))
+        .collect::<Vec<_>>();
+    let outputs = pending.iter().map(|_| None).collect();
+    JoinAll { pending, outputs }
+}
+
+pub struct JoinAll<F: Future> {
+    pending: Vec<Option<Pin<Box<F>>>>,
+    outputs: Vec<Option<F::Output>>,
+}
+
+// Every future is boxed, so moving the `JoinAll` itself around is harmless
+impl<F: Future> Unpin for JoinAll<F> {}
+
+impl<F: Future> Future for JoinAll<F> {
+    type Output = Vec<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let mut done = true;
+        for (slot, output) in this.pending.iter_mut().zip(this.outputs.iter_mut()) {
+            if let Some(future) = slot {
+                match future.as_mut().poll(cx) {
+                    Poll::Ready(value) => {
+                        *output = Some(value);
+                        *slot = None;
+                    }
+                    Poll::Pending => done = false,
+                }
+            }
+        }
+        if !done {
+            return Poll::Pending;
+        }
+        let outputs = this
+            .outputs
+            .iter_mut()
+            .map(|o| o.take().expect("JoinAll polled after completion"))
+            .collect();
+        Poll::Ready(outputs)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Either<L, R> {
+    Left(L),
+    Right(R),
+}
+
+/// Wait for whichever of the two futures completes first, dropping the other one. When both
+/// are ready at the same time the left one wins.
+pub fn select<A: Future, B: Future>(left: A, right: B) -> Select<A, B> {
+    Select {
+        left: Box::pin(left),
+        right: Box::pin(right),
+    }
+}
+
+pub struct Select<A, B> {
+    left: Pin<Box<A>>,
+    right: Pin<Box<B>>,
+}
+
+impl<A: Future, B: Future> Future for Select<A, B> {
+    type Output = Either<A::Output, B::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Poll::Ready(value) = self.left.as_mut().poll(cx) {
+            return Poll::Ready(Either::Left(value));
+        }
+        if let Poll::Ready(value) = self.right.as_mut().poll(cx) {
+            return Poll::Ready(Either::Right(value));
+        }
+        Poll::Pending
+    }
+}
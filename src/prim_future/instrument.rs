@@ -0,0 +1,322 @@
+//! Hooks into `block_on`, so that what a future spends its time on can be looked at afterwards.
+//! Nothing is recorded unless a hook has been installed with `set_hook`.
+
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+#[cfg(test)]
+use super::{block_on_named, spawn_blocking};
+
+#[cfg(test)]
+fn record(name: &'static str) -> Vec<Record> {
+    // The hook is global, so tests installing one mustn't overlap
+    static INSTALLING: Mutex<()> = Mutex::new(());
+    let _serial = INSTALLING.lock().expect("Failed to lock");
+    let recorder = Arc::new(Recorder::new());
+    let previous = set_hook(Some(recorder.clone()));
+    block_on_named(name, async {
+        spawn_blocking(|| std::thread::sleep(Duration::from_millis(30))).await;
+        spawn_blocking(|| 2).await
+    });
+    set_hook(previous);
+    // Other tests may run `block_on` while the hook is installed, so only keep our own
+    recorder
+        .records()
+        .into_iter()
+        .filter(|r| r.name == name)
+        .collect()
+}
+
+#[test]
+fn test_summary() {
+    let records = record("test_summary");
+    let summary = Summary::from_records(&records);
+    let stats = &summary.futures[0];
+    assert_eq!(summary.futures.len(), 1);
+    assert_eq!(stats.name, "test_summary");
+    assert!(stats.polls >= 2);
+    assert!(stats.parked >= Duration::from_millis(30));
+    assert!(stats
+        .wakes
+        .keys()
+        .all(|source| source.starts_with("prim-blocking")));
+    assert!(summary.to_string().contains("test_summary"));
+}
+
+#[test]
+fn test_chrome_trace() {
+    let records = record("test_chrome_trace");
+    let trace = chrome_trace(&records);
+    let events = trace["traceEvents"].as_array().unwrap();
+    let polls = events.iter().filter(|e| e["name"] == "poll").count();
+    let wakes = events.iter().filter(|e| e["ph"] == "i").count();
+    assert!(polls >= 2);
+    assert_eq!(wakes, polls - 1);
+    assert!(events
+        .iter()
+        .all(|e| e["args"]["future"] == "test_chrome_trace"));
+}
+
+/// Identifies one call to `block_on`
+pub type FutureId = u64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventKind {
+    PollStart,
+    PollEnd {
+        ready: bool,
+    },
+    ParkStart,
+    ParkEnd,
+    /// The future's waker was called, from the thread named here
+    Wake {
+        source: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Event<'a> {
+    pub future: FutureId,
+    pub name: &'a str,
+    pub at: Instant,
+    pub kind: EventKind,
+}
+
+/// Receives every event from every `block_on` while it's installed. Wake events arrive on
+/// whichever thread did the waking, so implementations need to be thread safe.
+pub trait Hook: Send + Sync {
+    fn on_event(&self, event: &Event<'_>);
+}
+
+static HOOK: RwLock<Option<Arc<dyn Hook>>> = RwLock::new(None);
+static NEXT_FUTURE: AtomicU64 = AtomicU64::new(0);
+
+/// Install `hook` for the `block_on` calls which start from now on, returning the previous one
+pub fn set_hook(hook: Option<Arc<dyn Hook>>) -> Option<Arc<dyn Hook>> {
+    std::mem::replace(&mut *HOOK.write().expect("Failed to lock"), hook)
+}
+
+pub(crate) fn current_hook() -> Option<Arc<dyn Hook>> {
+    HOOK.read().expect("Failed to lock").clone()
+}
+
+pub(crate) fn next_future_id() -> FutureId {
+    NEXT_FUTURE.fetch_add(1, Ordering::Relaxed)
+}
+
+/// What `block_on` hands to the hook, if there is one
+pub(crate) struct Probe {
+    hook: Option<Arc<dyn Hook>>,
+    future: FutureId,
+    name: String,
+}
+
+impl Probe {
+    pub(crate) fn new(name: &str) -> Self {
+        Probe {
+            hook: current_hook(),
+            future: next_future_id(),
+            name: name.to_string(),
+        }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.hook.is_some()
+    }
+
+    pub(crate) fn emit(&self, kind: EventKind) {
+        if let Some(hook) = &self.hook {
+            hook.on_event(&Event {
+                future: self.future,
+                name: &self.name,
+                at: Instant::now(),
+                kind,
+            });
+        }
+    }
+}
+
+/// An owned copy of an `Event`
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub future: FutureId,
+    pub name: String,
+    pub at: Instant,
+    pub kind: EventKind,
+}
+
+/// A hook which keeps every event in memory
+pub struct Recorder {
+    records: Mutex<Vec<Record>>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Recorder {
+            records: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn records(&self) -> Vec<Record> {
+        self.records.lock().expect("Failed to lock").clone()
+    }
+
+    pub fn summary(&self) -> Summary {
+        Summary::from_records(&self.records.lock().expect("Failed to lock"))
+    }
+
+    /// Write the events out in Chrome's trace-event format, for `chrome://tracing` or Perfetto
+    pub fn write_chrome_trace<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let trace = chrome_trace(&self.records.lock().expect("Failed to lock"));
+        std::fs::write(path, serde_json::to_string_pretty(&trace)?)
+    }
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Recorder::new()
+    }
+}
+
+impl Hook for Recorder {
+    fn on_event(&self, event: &Event<'_>) {
+        self.records.lock().expect("Failed to lock").push(Record {
+            future: event.future,
+            name: event.name.to_string(),
+            at: event.at,
+            kind: event.kind.clone(),
+        });
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FutureStats {
+    pub future: FutureId,
+    pub name: String,
+    pub polls: usize,
+    pub polling: Duration,
+    pub parked: Duration,
+    /// How many wake-ups came from each thread
+    pub wakes: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Summary {
+    pub futures: Vec<FutureStats>,
+}
+
+impl Summary {
+    pub fn from_records(records: &[Record]) -> Self {
+        let mut futures = BTreeMap::<FutureId, FutureStats>::new();
+        let mut started = BTreeMap::<FutureId, Instant>::new();
+        for record in records {
+            let stats = futures.entry(record.future).or_insert_with(|| FutureStats {
+                future: record.future,
+                name: record.name.clone(),
+                ..Default::default()
+            });
+            match &record.kind {
+                EventKind::PollStart | EventKind::ParkStart => {
+                    started.insert(record.future, record.at);
+                }
+                EventKind::PollEnd { .. } => {
+                    stats.polls += 1;
+                    if let Some(start) = started.remove(&record.future) {
+                        stats.polling += record.at - start;
+                    }
+                }
+                EventKind::ParkEnd => {
+                    if let Some(start) = started.remove(&record.future) {
+                        stats.parked += record.at - start;
+                    }
+                }
+                EventKind::Wake { source } => {
+                    *stats.wakes.entry(source.clone()).or_default() += 1;
+                }
+            }
+        }
+        Summary {
+            futures: futures.into_values().collect(),
+        }
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:<24} {:>6} {:>12} {:>12}  wakes",
+            "future", "polls", "polling", "parked"
+        )?;
+        for stats in &self.futures {
+            let wakes = stats
+                .wakes
+                .iter()
+                .map(|(source, count)| format!("{}={}", source, count))
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(
+                f,
+                "{:<24} {:>6} {:>12?} {:>12?}  {}",
+                format!("{}#{}", stats.name, stats.future),
+                stats.polls,
+                stats.polling,
+                stats.parked,
+                wakes
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Convert records into Chrome's trace-event JSON. Polls and parks become complete ("X")
+/// events on a track per future, and wake-ups become instant ("i") events.
+pub fn chrome_trace(records: &[Record]) -> Value {
+    let Some(origin) = records.iter().map(|r| r.at).min() else {
+        return json!({ "traceEvents": [] });
+    };
+    let micros = |at: Instant| (at - origin).as_secs_f64() * 1e6;
+
+    let mut events = vec![];
+    let mut started = BTreeMap::<FutureId, Instant>::new();
+    for record in records {
+        let args = json!({ "future": record.name });
+        match &record.kind {
+            EventKind::PollStart | EventKind::ParkStart => {
+                started.insert(record.future, record.at);
+            }
+            EventKind::PollEnd { ready } => {
+                if let Some(start) = started.remove(&record.future) {
+                    events.push(json!({
+                        "name": "poll", "cat": if *ready { "ready" } else { "pending" },
+                        "ph": "X", "ts": micros(start), "dur": micros(record.at) - micros(start),
+                        "pid": 1, "tid": record.future, "args": args,
+                    }));
+                }
+            }
+            EventKind::ParkEnd => {
+                if let Some(start) = started.remove(&record.future) {
+                    events.push(json!({
+                        "name": "parked", "cat": "park",
+                        "ph": "X", "ts": micros(start), "dur": micros(record.at) - micros(start),
+                        "pid": 1, "tid": record.future, "args": args,
+                    }));
+                }
+            }
+            EventKind::Wake { source } => {
+                events.push(json!({
+                    "name": format!("wake from {}", source), "cat": "wake",
+                    "ph": "i", "s": "t", "ts": micros(record.at),
+                    "pid": 1, "tid": record.future, "args": args,
+                }));
+            }
+        }
+    }
+    json!({ "traceEvents": events })
+}
@@ -0,0 +1,447 @@
+use super::spawn_blocking;
+use std::collections::HashMap;
+use std::future::Future;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+#[cfg(test)]
+use super::executor::{spawn, Executor};
+
+#[test]
+fn test_echo_over_reactor() {
+    let reply = Executor::new().block_on(async {
+        let listener = Async::<TcpListener>::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0_u8; 64];
+            loop {
+                match stream.read(&mut buf).await.unwrap() {
+                    0 => break,
+                    len => stream.write_all(&buf[..len]).await.unwrap(),
+                }
+            }
+        });
+
+        let client = Async::<TcpStream>::connect(addr).await.unwrap();
+        client.write_all(b"Life before death").await.unwrap();
+        let mut reply = [0_u8; 17];
+        client.read_exact(&mut reply).await.unwrap();
+        reply
+    });
+    assert_eq!(&reply, b"Life before death");
+}
+
+#[test]
+fn test_read_waits_for_data() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let writer = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        thread::sleep(std::time::Duration::from_millis(100));
+        stream.write_all(b"late").unwrap();
+    });
+
+    let received = super::block_on(async {
+        let client = Async::<TcpStream>::connect(addr).await.unwrap();
+        let mut buf = vec![0_u8; 4];
+        client.read_exact(&mut buf).await.unwrap();
+        // The writer hung up so we read end of file
+        let eof = client.read(&mut [0_u8; 1]).await.unwrap();
+        (buf, eof)
+    });
+    assert_eq!(received, (b"late".to_vec(), 0));
+    writer.join().unwrap();
+}
+
+#[test]
+fn test_many_connections_on_one_thread() {
+    let total = Executor::new().block_on(async {
+        let listener = Async::<TcpListener>::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                spawn(async move {
+                    let mut buf = [0_u8; 8];
+                    let len = stream.read(&mut buf).await.unwrap();
+                    stream.write_all(&buf[..len]).await.unwrap();
+                });
+            }
+        });
+
+        let clients = (0..50_u8)
+            .map(|n| {
+                spawn(async move {
+                    let client = Async::<TcpStream>::connect(addr).await.unwrap();
+                    client.write_all(&[n]).await.unwrap();
+                    let mut reply = [0_u8; 1];
+                    client.read_exact(&mut reply).await.unwrap();
+                    reply[0] as u32
+                })
+            })
+            .collect::<Vec<_>>();
+        super::executor::join_all(clients)
+            .await
+            .into_iter()
+            .sum::<u32>()
+    });
+    assert_eq!(total, (0..50).sum::<u32>());
+}
+
+#[test]
+fn test_two_tasks_accepting() {
+    let accepted = Executor::new().block_on(async {
+        let listener = Arc::new(Async::<TcpListener>::bind("127.0.0.1:0").unwrap());
+        let addr = listener.local_addr().unwrap();
+        let accepts = (0..2)
+            .map(|_| {
+                let listener = listener.clone();
+                spawn(async move { listener.accept().await.is_ok() })
+            })
+            .collect::<Vec<_>>();
+        // Both tasks are waiting on the listener by the time the clients connect
+        let clients = spawn_blocking(move || {
+            thread::sleep(std::time::Duration::from_millis(50));
+            [TcpStream::connect(addr), TcpStream::connect(addr)]
+        })
+        .await;
+        let accepted = super::executor::join_all(accepts).await;
+        drop(clients);
+        accepted
+    });
+    assert_eq!(accepted, [true, true]);
+}
+
+/// Readiness of one registered file descriptor, and the tasks waiting on it. Several tasks can
+/// wait in the same direction, say two accepting on one listener, so all of them are kept.
+#[derive(Default)]
+struct SourceState {
+    readable: bool,
+    writable: bool,
+    readers: Vec<Waker>,
+    writers: Vec<Waker>,
+}
+
+struct Source {
+    fd: RawFd,
+    token: u64,
+    state: Mutex<SourceState>,
+}
+
+#[derive(Clone, Copy)]
+enum Direction {
+    Read,
+    Write,
+}
+
+impl Source {
+    /// Try `op` until it either succeeds or would block with no readiness event outstanding,
+    /// in which case the task's waker is stored and the reactor wakes it on the next event
+    fn poll_io<R>(
+        &self,
+        direction: Direction,
+        cx: &mut Context<'_>,
+        mut op: impl FnMut() -> io::Result<R>,
+    ) -> Poll<io::Result<R>> {
+        loop {
+            match op() {
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                result => return Poll::Ready(result),
+            }
+            let mut guard = self.state.lock().expect("Failed to lock");
+            let state = &mut *guard;
+            let (ready, wakers) = match direction {
+                Direction::Read => (&mut state.readable, &mut state.readers),
+                Direction::Write => (&mut state.writable, &mut state.writers),
+            };
+            // An event may have arrived since `op` failed, so consume it and try again
+            if std::mem::take(ready) {
+                continue;
+            }
+            // A task polled again before being woken only needs to be woken once
+            if !wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                wakers.push(cx.waker().clone());
+            }
+            return Poll::Pending;
+        }
+    }
+
+    fn wake(&self, events: u32) {
+        let readable = libc::EPOLLIN | libc::EPOLLRDHUP | libc::EPOLLHUP | libc::EPOLLERR;
+        let writable = libc::EPOLLOUT | libc::EPOLLHUP | libc::EPOLLERR;
+        let mut wakers = vec![];
+        {
+            let mut state = self.state.lock().expect("Failed to lock");
+            if events & readable as u32 != 0 {
+                state.readable = true;
+                wakers.append(&mut state.readers);
+            }
+            if events & writable as u32 != 0 {
+                state.writable = true;
+                wakers.append(&mut state.writers);
+            }
+        }
+        wakers.into_iter().for_each(Waker::wake);
+    }
+}
+
+/// An epoll instance plus the background thread which waits on it
+struct Reactor {
+    epoll: OwnedFd,
+    sources: Mutex<HashMap<u64, Arc<Source>>>,
+    next_token: AtomicU64,
+}
+
+impl Reactor {
+    fn get() -> &'static Reactor {
+        static REACTOR: OnceLock<Reactor> = OnceLock::new();
+        REACTOR.get_or_init(|| {
+            let fd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+            if fd < 0 {
+                panic!("Failed to create epoll: {}", io::Error::last_os_error());
+            }
+            thread::Builder::new()
+                .name("prim-reactor".to_string())
+                .spawn(|| Reactor::get().run())
+                .expect("Failed to spawn reactor thread");
+            Reactor {
+                epoll: unsafe { OwnedFd::from_raw_fd(fd) },
+                sources: Mutex::new(HashMap::new()),
+                next_token: AtomicU64::new(0),
+            }
+        })
+    }
+
+    /// Watch `fd` for both directions, edge-triggered so that each event is reported once
+    fn register(&self, fd: RawFd) -> io::Result<Arc<Source>> {
+        let token = self.next_token.fetch_add(1, Ordering::Relaxed);
+        let source = Arc::new(Source {
+            fd,
+            token,
+            state: Mutex::new(SourceState::default()),
+        });
+        self.sources
+            .lock()
+            .expect("Failed to lock")
+            .insert(token, source.clone());
+
+        let mut event = libc::epoll_event {
+            events: (libc::EPOLLIN | libc::EPOLLOUT | libc::EPOLLRDHUP | libc::EPOLLET) as u32,
+            u64: token,
+        };
+        let result =
+            unsafe { libc::epoll_ctl(self.epoll.as_raw_fd(), libc::EPOLL_CTL_ADD, fd, &mut event) };
+        if result < 0 {
+            self.sources.lock().expect("Failed to lock").remove(&token);
+            return Err(io::Error::last_os_error());
+        }
+        Ok(source)
+    }
+
+    fn deregister(&self, source: &Source) {
+        unsafe {
+            libc::epoll_ctl(
+                self.epoll.as_raw_fd(),
+                libc::EPOLL_CTL_DEL,
+                source.fd,
+                std::ptr::null_mut(),
+            )
+        };
+        self.sources
+            .lock()
+            .expect("Failed to lock")
+            .remove(&source.token);
+    }
+
+    fn run(&self) {
+        let mut events = vec![libc::epoll_event { events: 0, u64: 0 }; 1024];
+        loop {
+            let count = unsafe {
+                libc::epoll_wait(
+                    self.epoll.as_raw_fd(),
+                    events.as_mut_ptr(),
+                    events.len() as i32,
+                    -1,
+                )
+            };
+            if count < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                panic!("epoll_wait failed: {}", err);
+            }
+            for event in &events[..count as usize] {
+                let token = event.u64;
+                let source = self
+                    .sources
+                    .lock()
+                    .expect("Failed to lock")
+                    .get(&token)
+                    .cloned();
+                if let Some(source) = source {
+                    source.wake(event.events);
+                }
+            }
+        }
+    }
+}
+
+/// A non-blocking socket registered with the reactor. Its futures park the task rather than
+/// the thread while the socket isn't ready.
+pub struct Async<T: AsRawFd> {
+    io: T,
+    source: Arc<Source>,
+}
+
+impl<T: AsRawFd> Async<T> {
+    /// Register `io` with the reactor. It must already be in non-blocking mode.
+    fn register(io: T) -> io::Result<Self> {
+        let source = Reactor::get().register(io.as_raw_fd())?;
+        Ok(Async { io, source })
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.io
+    }
+}
+
+impl<T: AsRawFd> Drop for Async<T> {
+    fn drop(&mut self) {
+        Reactor::get().deregister(&self.source);
+    }
+}
+
+impl Async<TcpListener> {
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        Async::try_from(TcpListener::bind(addr)?)
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.io.local_addr()
+    }
+
+    pub fn accept(&self) -> Accept<'_> {
+        Accept { listener: self }
+    }
+}
+
+impl TryFrom<TcpListener> for Async<TcpListener> {
+    type Error = io::Error;
+
+    fn try_from(listener: TcpListener) -> io::Result<Self> {
+        listener.set_nonblocking(true)?;
+        Async::register(listener)
+    }
+}
+
+impl Async<TcpStream> {
+    /// Connect to `addr`. The connect itself runs on the blocking pool, so only the I/O
+    /// afterwards goes through the reactor.
+    pub async fn connect(addr: SocketAddr) -> io::Result<Self> {
+        let stream = spawn_blocking(move || TcpStream::connect(addr)).await?;
+        Async::try_from(stream)
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.io.peer_addr()
+    }
+
+    /// Read whatever is available into `buf`, returning 0 at end of file
+    pub fn read<'a>(&'a self, buf: &'a mut [u8]) -> ReadFuture<'a> {
+        ReadFuture { stream: self, buf }
+    }
+
+    pub fn write<'a>(&'a self, buf: &'a [u8]) -> WriteFuture<'a> {
+        WriteFuture { stream: self, buf }
+    }
+
+    pub async fn read_exact(&self, mut buf: &mut [u8]) -> io::Result<()> {
+        while !buf.is_empty() {
+            match self.read(buf).await? {
+                0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+                len => buf = &mut buf[len..],
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn write_all(&self, mut buf: &[u8]) -> io::Result<()> {
+        while !buf.is_empty() {
+            match self.write(buf).await? {
+                0 => return Err(io::ErrorKind::WriteZero.into()),
+                len => buf = &buf[len..],
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<TcpStream> for Async<TcpStream> {
+    type Error = io::Error;
+
+    fn try_from(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        Async::register(stream)
+    }
+}
+
+pub struct Accept<'a> {
+    listener: &'a Async<TcpListener>,
+}
+
+impl Future for Accept<'_> {
+    type Output = io::Result<(Async<TcpStream>, SocketAddr)>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let listener = self.listener;
+        listener
+            .source
+            .poll_io(Direction::Read, cx, || listener.io.accept())
+            .map(|result| {
+                let (stream, addr) = result?;
+                Ok((Async::try_from(stream)?, addr))
+            })
+    }
+}
+
+pub struct ReadFuture<'a> {
+    stream: &'a Async<TcpStream>,
+    buf: &'a mut [u8],
+}
+
+impl Future for ReadFuture<'_> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let stream = this.stream;
+        stream
+            .source
+            .poll_io(Direction::Read, cx, || (&stream.io).read(this.buf))
+    }
+}
+
+pub struct WriteFuture<'a> {
+    stream: &'a Async<TcpStream>,
+    buf: &'a [u8],
+}
+
+impl Future for WriteFuture<'_> {
+    type Output = io::Result<usize>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let stream = self.stream;
+        let buf = self.buf;
+        stream
+            .source
+            .poll_io(Direction::Write, cx, || (&stream.io).write(buf))
+    }
+}
@@ -0,0 +1,704 @@
+//! Locks which can be held across `.await` points. Waiting tasks queue up in FIFO order and
+//! park on their `Waker` rather than blocking the thread. Dropping a pending acquire future
+//! gives up its place in the queue without losing any permits.
+
+use std::cell::UnsafeCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll, Waker};
+
+#[cfg(test)]
+use super::executor::{spawn, Executor};
+#[cfg(test)]
+use super::work_stealing::{self, ThreadPool};
+#[cfg(test)]
+use futures_lite::future::poll_once;
+#[cfg(test)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(test)]
+use std::sync::Arc;
+
+/// Gives other tasks a turn, so the stress tests interleave while holding a lock
+#[cfg(test)]
+async fn yield_now() {
+    let mut yielded = false;
+    std::future::poll_fn(|cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+    .await
+}
+
+#[test]
+fn test_mutex_under_contention() {
+    let pool = ThreadPool::new(4);
+    let counter = Arc::new(AsyncMutex::new(0_u64));
+    let holders = Arc::new(AtomicUsize::new(0));
+    pool.block_on(async {
+        let tasks = (0..200)
+            .map(|_| {
+                let counter = counter.clone();
+                let holders = holders.clone();
+                work_stealing::spawn(async move {
+                    let mut guard = counter.lock().await;
+                    assert_eq!(holders.fetch_add(1, Ordering::SeqCst), 0);
+                    let seen = *guard;
+                    yield_now().await;
+                    *guard = seen + 1;
+                    holders.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect::<Vec<_>>();
+        super::executor::join_all(tasks).await;
+    });
+    assert_eq!(*pool.block_on(counter.lock()), 200);
+}
+
+#[test]
+fn test_rwlock_readers_share_writers_exclude() {
+    let pool = ThreadPool::new(4);
+    let lock = Arc::new(AsyncRwLock::new(vec![]));
+    let readers = Arc::new(AtomicUsize::new(0));
+    let max_readers = Arc::new(AtomicUsize::new(0));
+    pool.block_on(async {
+        let tasks = (0..100)
+            .map(|n| {
+                let (lock, readers, max_readers) =
+                    (lock.clone(), readers.clone(), max_readers.clone());
+                work_stealing::spawn(async move {
+                    if n % 10 == 0 {
+                        let mut guard = lock.write().await;
+                        assert_eq!(readers.load(Ordering::SeqCst), 0);
+                        guard.push(n);
+                        yield_now().await;
+                    } else {
+                        let _guard = lock.read().await;
+                        let now = readers.fetch_add(1, Ordering::SeqCst) + 1;
+                        max_readers.fetch_max(now, Ordering::SeqCst);
+                        yield_now().await;
+                        readers.fetch_sub(1, Ordering::SeqCst);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        super::executor::join_all(tasks).await;
+    });
+    assert_eq!(pool.block_on(lock.read()).len(), 10);
+    assert!(max_readers.load(Ordering::SeqCst) >= 1);
+}
+
+#[test]
+fn test_semaphore_limits_concurrency() {
+    let pool = ThreadPool::new(4);
+    let semaphore = Arc::new(Semaphore::new(3));
+    let active = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+    pool.block_on(async {
+        let tasks = (0..100)
+            .map(|_| {
+                let (semaphore, active, peak) = (semaphore.clone(), active.clone(), peak.clone());
+                work_stealing::spawn(async move {
+                    let _permit = semaphore.acquire().await;
+                    let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    yield_now().await;
+                    active.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect::<Vec<_>>();
+        super::executor::join_all(tasks).await;
+    });
+    assert!(peak.load(Ordering::SeqCst) <= 3);
+    assert_eq!(semaphore.available_permits(), 3);
+}
+
+#[test]
+fn test_waiters_are_served_in_order() {
+    let order = Executor::new().block_on(async {
+        let mutex = Arc::new(AsyncMutex::new(vec![]));
+        let guard = mutex.lock().await;
+        let tasks = (0..5)
+            .map(|n| {
+                let mutex = mutex.clone();
+                spawn(async move { mutex.lock().await.push(n) })
+            })
+            .collect::<Vec<_>>();
+        // Let every task queue up behind the guard
+        yield_now().await;
+        drop(guard);
+        super::executor::join_all(tasks).await;
+        let order = mutex.lock().await.clone();
+        order
+    });
+    assert_eq!(order, vec![0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn test_cancelled_acquire_gives_up_its_place() {
+    super::block_on(async {
+        let semaphore = Semaphore::new(2);
+        let held = semaphore.acquire_many(2).await;
+
+        let mut first = Box::pin(semaphore.acquire());
+        let mut second = Box::pin(semaphore.acquire_many(2));
+        assert!(poll_once(first.as_mut()).await.is_none());
+        assert!(poll_once(second.as_mut()).await.is_none());
+
+        // The first waiter is granted its permit on release, but is dropped before noticing
+        drop(held);
+        drop(first);
+        assert!(poll_once(second.as_mut()).await.is_some());
+        assert_eq!(semaphore.available_permits(), 2);
+
+        // A writer which gives up lets the readers queued behind it through
+        let lock = AsyncRwLock::new(1);
+        let reader = lock.read().await;
+        let mut writer = Box::pin(lock.write());
+        assert!(poll_once(writer.as_mut()).await.is_none());
+        assert!(lock.try_read().is_none());
+        drop(writer);
+        assert_eq!(*lock.try_read().unwrap() + *reader, 2);
+    });
+}
+
+#[test]
+#[should_panic(expected = "Can't acquire 4 permits from a semaphore with 3")]
+fn test_acquire_more_than_there_are() {
+    let semaphore = Semaphore::new(2);
+    semaphore.add_permits(1);
+    drop(semaphore.acquire_many(3));
+    semaphore.try_acquire().unwrap().forget();
+    drop(semaphore.acquire_many(2));
+    semaphore.add_permits(1);
+    drop(semaphore.acquire_many(4));
+}
+
+#[test]
+fn test_mutex_guard_is_send() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    fn assert_send<T: Send>() {}
+    assert_send_sync::<AsyncMutexGuard<'static, Vec<u8>>>();
+    assert_send::<AsyncMutexGuard<'static, std::cell::Cell<u8>>>();
+}
+
+#[test]
+fn test_notify() {
+    let notify = Arc::new(Notify::new());
+    // A notification with nobody waiting is kept for the next one to wait
+    notify.notify_one();
+    super::block_on(notify.notified());
+
+    let woken = ThreadPool::new(2).block_on({
+        let notify = notify.clone();
+        async move {
+            let waiters = (0..3)
+                .map(|_| {
+                    let notify = notify.clone();
+                    work_stealing::spawn(async move { notify.notified().await })
+                })
+                .collect::<Vec<_>>();
+            while notify.waiting() < 3 {
+                yield_now().await;
+            }
+            notify.notify_waiters();
+            super::executor::join_all(waiters).await.len()
+        }
+    });
+    assert_eq!(woken, 3);
+}
+
+struct SemaphoreState {
+    permits: usize,
+    /// Every permit there is, free or handed out
+    total: usize,
+    /// Tasks waiting for permits: their ticket, how many they need, and how to wake them
+    waiters: VecDeque<(u64, usize, Waker)>,
+    /// Tickets which were handed their permits but haven't been polled since
+    granted: HashSet<u64>,
+    next_ticket: u64,
+}
+
+impl SemaphoreState {
+    /// Hand permits to the front of the queue for as long as they go around. A waiter which
+    /// needs more than what's left holds up everybody behind it, which keeps things fair.
+    fn dispatch(&mut self) -> Vec<Waker> {
+        let mut wakers = vec![];
+        while let Some(&(ticket, needed, _)) = self.waiters.front() {
+            if needed > self.permits {
+                break;
+            }
+            self.permits -= needed;
+            self.granted.insert(ticket);
+            let (_, _, waker) = self.waiters.pop_front().expect("Checked above");
+            wakers.push(waker);
+        }
+        wakers
+    }
+}
+
+/// A pool of permits which tasks wait on in FIFO order
+pub struct Semaphore {
+    state: Mutex<SemaphoreState>,
+}
+
+impl Semaphore {
+    pub fn new(permits: usize) -> Self {
+        Semaphore {
+            state: Mutex::new(SemaphoreState {
+                permits,
+                total: permits,
+                waiters: VecDeque::new(),
+                granted: HashSet::new(),
+                next_ticket: 0,
+            }),
+        }
+    }
+
+    pub fn available_permits(&self) -> usize {
+        self.state.lock().expect("Failed to lock").permits
+    }
+
+    pub fn acquire(&self) -> Acquire<'_> {
+        self.acquire_many(1)
+    }
+
+    /// # Panics
+    /// If `permits` is more than the semaphore has in total, since waiting for them would never
+    /// end and would hold up everybody queued behind.
+    pub fn acquire_many(&self, permits: usize) -> Acquire<'_> {
+        let total = self.state.lock().expect("Failed to lock").total;
+        assert!(
+            permits <= total,
+            "Can't acquire {} permits from a semaphore with {}",
+            permits,
+            total
+        );
+        Acquire {
+            semaphore: self,
+            permits,
+            ticket: None,
+        }
+    }
+
+    /// Take a permit if one is free and nobody is queued ahead of us
+    pub fn try_acquire(&self) -> Option<SemaphorePermit<'_>> {
+        self.try_acquire_many(1)
+    }
+
+    pub fn try_acquire_many(&self, permits: usize) -> Option<SemaphorePermit<'_>> {
+        let mut state = self.state.lock().expect("Failed to lock");
+        if !state.waiters.is_empty() || state.permits < permits {
+            return None;
+        }
+        state.permits -= permits;
+        Some(SemaphorePermit {
+            semaphore: self,
+            permits,
+        })
+    }
+
+    /// Add `permits` new permits to the semaphore, waking whoever can now go ahead
+    pub fn add_permits(&self, permits: usize) {
+        self.state.lock().expect("Failed to lock").total += permits;
+        self.release(permits);
+    }
+
+    /// Give back permits which were handed out
+    fn release(&self, permits: usize) {
+        let wakers = {
+            let mut state = self.state.lock().expect("Failed to lock");
+            state.permits += permits;
+            state.dispatch()
+        };
+        wakers.into_iter().for_each(Waker::wake);
+    }
+}
+
+pub struct Acquire<'a> {
+    semaphore: &'a Semaphore,
+    permits: usize,
+    /// Set once we've joined the queue
+    ticket: Option<u64>,
+}
+
+impl<'a> Future for Acquire<'a> {
+    type Output = SemaphorePermit<'a>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let semaphore = self.semaphore;
+        let permits = self.permits;
+        let mut state = semaphore.state.lock().expect("Failed to lock");
+        let acquired = match self.ticket {
+            Some(ticket) => {
+                let granted = state.granted.remove(&ticket);
+                if !granted {
+                    if let Some(entry) = state.waiters.iter_mut().find(|(t, _, _)| *t == ticket) {
+                        entry.2 = cx.waker().clone();
+                    }
+                }
+                granted
+            }
+            None if state.waiters.is_empty() && state.permits >= permits => {
+                state.permits -= permits;
+                true
+            }
+            None => {
+                let ticket = state.next_ticket;
+                state.next_ticket += 1;
+                state
+                    .waiters
+                    .push_back((ticket, permits, cx.waker().clone()));
+                self.ticket = Some(ticket);
+                false
+            }
+        };
+        if !acquired {
+            return Poll::Pending;
+        }
+        self.ticket = None;
+        Poll::Ready(SemaphorePermit { semaphore, permits })
+    }
+}
+
+impl Drop for Acquire<'_> {
+    fn drop(&mut self) {
+        let Some(ticket) = self.ticket else { return };
+        let wakers = {
+            let mut state = self.semaphore.state.lock().expect("Failed to lock");
+            if state.granted.remove(&ticket) {
+                // We were handed permits we'll never use, so pass them on
+                state.permits += self.permits;
+            } else {
+                state.waiters.retain(|(t, _, _)| *t != ticket);
+            }
+            // Either way the queue may now be able to move
+            state.dispatch()
+        };
+        wakers.into_iter().for_each(Waker::wake);
+    }
+}
+
+/// Permits which go back to the semaphore when this is dropped
+pub struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+    permits: usize,
+}
+
+impl SemaphorePermit<'_> {
+    /// Keep the permits out of circulation for good
+    pub fn forget(mut self) {
+        self.semaphore.state.lock().expect("Failed to lock").total -= self.permits;
+        self.permits = 0;
+    }
+
+    /// Keep holding the permits once this is dropped; the locks give them back with `release`
+    /// when their guards go
+    fn detach(mut self) {
+        self.permits = 0;
+    }
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        if self.permits > 0 {
+            self.semaphore.release(self.permits);
+        }
+    }
+}
+
+/// A mutex whose guard can be held across `.await`
+pub struct AsyncMutex<T> {
+    semaphore: Semaphore,
+    value: UnsafeCell<T>,
+}
+
+// Access to the value is serialised by the semaphore, just as with `std::sync::Mutex`
+unsafe impl<T: Send> Send for AsyncMutex<T> {}
+unsafe impl<T: Send> Sync for AsyncMutex<T> {}
+
+impl<T> AsyncMutex<T> {
+    pub fn new(value: T) -> Self {
+        AsyncMutex {
+            semaphore: Semaphore::new(1),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    pub async fn lock(&self) -> AsyncMutexGuard<'_, T> {
+        self.semaphore.acquire().await.detach();
+        AsyncMutexGuard(self, PhantomData)
+    }
+
+    pub fn try_lock(&self) -> Option<AsyncMutexGuard<'_, T>> {
+        self.semaphore.try_acquire().map(|permit| {
+            permit.detach();
+            AsyncMutexGuard(self, PhantomData)
+        })
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+/// Shared access to the guard hands out `&T`, so unlike the mutex itself, it's only `Sync` when
+/// `T` is. It stays `Send`, so that it can be held across an `.await` in a spawned task.
+///
+/// ```compile_fail
+/// use programming_rust::prim_future::sync::AsyncMutexGuard;
+/// fn assert_sync<T: Sync>() {}
+/// assert_sync::<AsyncMutexGuard<'static, std::cell::Cell<u8>>>();
+/// ```
+pub struct AsyncMutexGuard<'a, T>(&'a AsyncMutex<T>, PhantomData<*const ()>);
+
+unsafe impl<T: Send> Send for AsyncMutexGuard<'_, T> {}
+unsafe impl<T: Sync> Sync for AsyncMutexGuard<'_, T> {}
+
+impl<T> Deref for AsyncMutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.0.value.get() }
+    }
+}
+
+impl<T> DerefMut for AsyncMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.0.value.get() }
+    }
+}
+
+impl<T> Drop for AsyncMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.0.semaphore.release(1);
+    }
+}
+
+/// Readers take one permit each and a writer takes all of them. A queued writer holds back the
+/// readers which arrive after it, so writers don't starve.
+const MAX_READERS: usize = usize::MAX >> 3;
+
+/// A reader-writer lock whose guards can be held across `.await`
+pub struct AsyncRwLock<T> {
+    semaphore: Semaphore,
+    value: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Send for AsyncRwLock<T> {}
+unsafe impl<T: Send + Sync> Sync for AsyncRwLock<T> {}
+
+impl<T> AsyncRwLock<T> {
+    pub fn new(value: T) -> Self {
+        AsyncRwLock {
+            semaphore: Semaphore::new(MAX_READERS),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    pub async fn read(&self) -> AsyncReadGuard<'_, T> {
+        self.semaphore.acquire().await.detach();
+        AsyncReadGuard(self)
+    }
+
+    pub async fn write(&self) -> AsyncWriteGuard<'_, T> {
+        self.semaphore.acquire_many(MAX_READERS).await.detach();
+        AsyncWriteGuard(self)
+    }
+
+    pub fn try_read(&self) -> Option<AsyncReadGuard<'_, T>> {
+        self.semaphore.try_acquire().map(|permit| {
+            permit.detach();
+            AsyncReadGuard(self)
+        })
+    }
+
+    pub fn try_write(&self) -> Option<AsyncWriteGuard<'_, T>> {
+        self.semaphore.try_acquire_many(MAX_READERS).map(|permit| {
+            permit.detach();
+            AsyncWriteGuard(self)
+        })
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+pub struct AsyncReadGuard<'a, T>(&'a AsyncRwLock<T>);
+
+impl<T> Deref for AsyncReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.0.value.get() }
+    }
+}
+
+impl<T> Drop for AsyncReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.0.semaphore.release(1);
+    }
+}
+
+pub struct AsyncWriteGuard<'a, T>(&'a AsyncRwLock<T>);
+
+impl<T> Deref for AsyncWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.0.value.get() }
+    }
+}
+
+impl<T> DerefMut for AsyncWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.0.value.get() }
+    }
+}
+
+impl<T> Drop for AsyncWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.0.semaphore.release(MAX_READERS);
+    }
+}
+
+struct NotifyState {
+    /// A `notify_one` which arrived while nobody was waiting
+    stored: bool,
+    waiters: VecDeque<(u64, Waker)>,
+    /// Tickets which were notified but haven't been polled since, and whether it was by
+    /// `notify_one`
+    notified: HashMap<u64, bool>,
+    next_ticket: u64,
+}
+
+/// Wakes up tasks waiting on `notified`, either one at a time in FIFO order or all at once
+pub struct Notify {
+    state: Mutex<NotifyState>,
+}
+
+impl Notify {
+    pub fn new() -> Self {
+        Notify {
+            state: Mutex::new(NotifyState {
+                stored: false,
+                waiters: VecDeque::new(),
+                notified: HashMap::new(),
+                next_ticket: 0,
+            }),
+        }
+    }
+
+    /// Wait for a notification. The future starts waiting when it's first polled.
+    pub fn notified(&self) -> Notified<'_> {
+        Notified {
+            notify: self,
+            ticket: None,
+        }
+    }
+
+    /// Wake the longest waiting task, or if there is none, let the next `notified` complete
+    /// straight away
+    pub fn notify_one(&self) {
+        let waker = {
+            let mut state = self.state.lock().expect("Failed to lock");
+            match state.waiters.pop_front() {
+                Some((ticket, waker)) => {
+                    state.notified.insert(ticket, true);
+                    Some(waker)
+                }
+                None => {
+                    state.stored = true;
+                    None
+                }
+            }
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Wake every task which is currently waiting
+    pub fn notify_waiters(&self) {
+        let wakers = {
+            let mut state = self.state.lock().expect("Failed to lock");
+            let waiters = std::mem::take(&mut state.waiters);
+            waiters
+                .into_iter()
+                .map(|(ticket, waker)| {
+                    state.notified.insert(ticket, false);
+                    waker
+                })
+                .collect::<Vec<_>>()
+        };
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    /// How many tasks are waiting right now
+    pub fn waiting(&self) -> usize {
+        self.state.lock().expect("Failed to lock").waiters.len()
+    }
+}
+
+impl Default for Notify {
+    fn default() -> Self {
+        Notify::new()
+    }
+}
+
+pub struct Notified<'a> {
+    notify: &'a Notify,
+    ticket: Option<u64>,
+}
+
+impl Future for Notified<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.notify.state.lock().expect("Failed to lock");
+        match self.ticket {
+            Some(ticket) if state.notified.remove(&ticket).is_some() => {}
+            Some(ticket) => {
+                if let Some(entry) = state.waiters.iter_mut().find(|(t, _)| *t == ticket) {
+                    entry.1 = cx.waker().clone();
+                }
+                return Poll::Pending;
+            }
+            None if std::mem::take(&mut state.stored) => {}
+            None => {
+                let ticket = state.next_ticket;
+                state.next_ticket += 1;
+                state.waiters.push_back((ticket, cx.waker().clone()));
+                drop(state);
+                self.ticket = Some(ticket);
+                return Poll::Pending;
+            }
+        }
+        drop(state);
+        self.ticket = None;
+        Poll::Ready(())
+    }
+}
+
+impl Drop for Notified<'_> {
+    fn drop(&mut self) {
+        let Some(ticket) = self.ticket else { return };
+        let by_notify_one = {
+            let mut state = self.notify.state.lock().expect("Failed to lock");
+            state.waiters.retain(|(t, _)| *t != ticket);
+            state.notified.remove(&ticket)
+        };
+        // Don't swallow a notification which was meant for one task in particular
+        if by_notify_one == Some(true) {
+            self.notify.notify_one();
+        }
+    }
+}
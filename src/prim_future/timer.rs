@@ -0,0 +1,403 @@
+use crossbeam_utils::sync::{Parker, Unparker};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(test)]
+use super::block_on;
+
+/// Slots per wheel level; each level covers `SLOTS` times the span of the one below it
+const SLOTS: usize = 64;
+const SLOT_BITS: u32 = 6;
+/// Six levels of 64 one-millisecond slots reach 64^6 ms, about 795 days, out. Timers further off
+/// than that wait in the top level until they come within range.
+const LEVELS: usize = 6;
+const TICK: Duration = Duration::from_millis(1);
+/// Stands in for deadlines too far off for an `Instant` to hold, roughly thirty years
+const FAR_FUTURE: Duration = Duration::from_secs(86_400 * 365 * 30);
+
+#[test]
+fn test_wheel_expires_in_order() {
+    let mut wheel = TimerWheel::new();
+    wheel.insert(5, "five");
+    wheel.insert(1, "one");
+    wheel.insert(63, "sixty-three");
+    assert_eq!(wheel.len(), 3);
+
+    assert_eq!(wheel.advance_to(4), vec!["one"]);
+    assert_eq!(wheel.advance_to(5), vec!["five"]);
+    assert_eq!(wheel.next_expiry(), Some(63));
+    assert_eq!(wheel.advance_to(100), vec!["sixty-three"]);
+    assert!(wheel.is_empty());
+    assert_eq!(wheel.next_expiry(), None);
+}
+
+#[test]
+fn test_wheel_cascades_between_levels() {
+    let mut wheel = TimerWheel::new();
+    let far = 64 * 64 * 3 + 17;
+    wheel.insert(far, "far");
+    wheel.insert(64 * 2 + 1, "near");
+
+    assert!(wheel.advance_to(64 * 2).is_empty());
+    assert_eq!(wheel.advance_to(64 * 2 + 1), vec!["near"]);
+    assert!(wheel.advance_to(far - 1).is_empty());
+    assert_eq!(wheel.advance_to(far), vec!["far"]);
+}
+
+#[test]
+fn test_wheel_remove() {
+    let mut wheel = TimerWheel::new();
+    let id = wheel.insert(10_000, 1);
+    wheel.insert(10_001, 2);
+    assert_eq!(wheel.remove(id), Some(1));
+    assert_eq!(wheel.remove(id), None);
+    assert_eq!(wheel.advance_to(20_000), vec![2]);
+}
+
+#[test]
+fn test_sleep() {
+    let started = Instant::now();
+    block_on(sleep(Duration::from_millis(50)));
+    assert!(started.elapsed() >= Duration::from_millis(50));
+}
+
+#[test]
+fn test_many_sleeps_share_one_thread() {
+    use super::executor::{join_all, spawn, Executor};
+
+    let started = Instant::now();
+    let order = Executor::new().block_on(async {
+        let handles = (0..100_u64)
+            .rev()
+            .map(|n| {
+                spawn(async move {
+                    // Absolute deadlines, so that how long spawning takes can't reorder them
+                    sleep_until(started + Duration::from_millis(n * 2)).await;
+                    Instant::now()
+                })
+            })
+            .collect::<Vec<_>>();
+        join_all(handles).await
+    });
+    assert!(order.windows(2).all(|w| w[0] >= w[1]));
+    assert!(started.elapsed() < Duration::from_millis(1000));
+}
+
+#[test]
+fn test_timeout() {
+    let slow = timeout(Duration::from_millis(20), sleep(Duration::from_secs(10)));
+    assert_eq!(block_on(slow), Err(Elapsed));
+
+    let fast = timeout(Duration::from_secs(10), async { 12 });
+    assert_eq!(block_on(fast), Ok(12));
+
+    let forever = timeout(Duration::from_millis(20), sleep(Duration::MAX));
+    assert_eq!(block_on(forever), Err(Elapsed));
+    assert_eq!(block_on(timeout(Duration::MAX, async { 3 })), Ok(3));
+}
+
+/// A hierarchical timing wheel keyed on ticks. Level 0 has one slot per tick; a slot at level
+/// `n` spans `64^n` ticks, and its timers are cascaded down a level when the wheel reaches it.
+pub(crate) struct TimerWheel<T> {
+    levels: Vec<Vec<Vec<Entry<T>>>>,
+    /// Where each live timer currently sits, as `(level, slot)`
+    locations: HashMap<u64, (usize, usize)>,
+    now: u64,
+    next_id: u64,
+}
+
+struct Entry<T> {
+    id: u64,
+    deadline: u64,
+    value: T,
+}
+
+impl<T> TimerWheel<T> {
+    pub(crate) fn new() -> Self {
+        TimerWheel {
+            levels: (0..LEVELS)
+                .map(|_| (0..SLOTS).map(|_| Vec::new()).collect())
+                .collect(),
+            locations: HashMap::new(),
+            now: 0,
+            next_id: 0,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.locations.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.locations.is_empty()
+    }
+
+    /// Add a timer firing at tick `deadline`; deadlines in the past fire on the next tick
+    pub(crate) fn insert(&mut self, deadline: u64, value: T) -> u64 {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.place(Entry {
+            id,
+            deadline: deadline.max(self.now + 1),
+            value,
+        });
+        id
+    }
+
+    pub(crate) fn remove(&mut self, id: u64) -> Option<T> {
+        let (level, slot) = self.locations.remove(&id)?;
+        let entries = &mut self.levels[level][slot];
+        let index = entries.iter().position(|e| e.id == id)?;
+        Some(entries.swap_remove(index).value)
+    }
+
+    /// Move the wheel forward to `tick`, returning the timers which fired in deadline order
+    pub(crate) fn advance_to(&mut self, tick: u64) -> Vec<T> {
+        let mut expired = vec![];
+        while self.now < tick {
+            if self.is_empty() {
+                self.now = tick;
+                break;
+            }
+            self.now += 1;
+            for level in (1..LEVELS).rev() {
+                let span_bits = SLOT_BITS * level as u32;
+                if self.now & ((1 << span_bits) - 1) == 0 {
+                    let slot = Self::slot_for(self.now, level);
+                    for entry in std::mem::take(&mut self.levels[level][slot]) {
+                        self.place(entry);
+                    }
+                }
+            }
+            let slot = Self::slot_for(self.now, 0);
+            for entry in std::mem::take(&mut self.levels[0][slot]) {
+                self.locations.remove(&entry.id);
+                expired.push(entry.value);
+            }
+        }
+        expired
+    }
+
+    /// The earliest tick at which `advance_to` could return something
+    pub(crate) fn next_expiry(&self) -> Option<u64> {
+        if self.is_empty() {
+            return None;
+        }
+        let rotation_end = (self.now | (SLOTS as u64 - 1)) + 1;
+        (self.now + 1..rotation_end)
+            .find(|&tick| !self.levels[0][Self::slot_for(tick, 0)].is_empty())
+            .or(Some(rotation_end))
+    }
+
+    fn place(&mut self, entry: Entry<T>) {
+        let level = Self::level_for(self.now, entry.deadline);
+        let slot = Self::slot_for(entry.deadline, level);
+        self.locations.insert(entry.id, (level, slot));
+        self.levels[level][slot].push(entry);
+    }
+
+    /// The lowest level whose slots still tell `now` and `deadline` apart
+    fn level_for(now: u64, deadline: u64) -> usize {
+        let differing = now ^ deadline;
+        if differing == 0 {
+            return 0;
+        }
+        let highest_bit = 63 - differing.leading_zeros();
+        ((highest_bit / SLOT_BITS) as usize).min(LEVELS - 1)
+    }
+
+    fn slot_for(tick: u64, level: usize) -> usize {
+        ((tick >> (SLOT_BITS * level as u32)) & (SLOTS as u64 - 1)) as usize
+    }
+}
+
+/// The waker of a pending timer, and whether it has fired yet
+struct TimerState {
+    fired: bool,
+    waker: Option<Waker>,
+}
+
+/// Owns the wheel and the background thread which advances it in real time
+struct Driver {
+    wheel: Mutex<TimerWheel<Arc<Mutex<TimerState>>>>,
+    started: Instant,
+    unparker: Unparker,
+}
+
+impl Driver {
+    fn get() -> &'static Driver {
+        static DRIVER: OnceLock<Driver> = OnceLock::new();
+        DRIVER.get_or_init(|| {
+            let parker = Parker::new();
+            let driver = Driver {
+                wheel: Mutex::new(TimerWheel::new()),
+                started: Instant::now(),
+                unparker: parker.unparker().clone(),
+            };
+            thread::Builder::new()
+                .name("prim-timer".to_string())
+                .spawn(move || Driver::run(parker))
+                .expect("Failed to spawn timer thread");
+            driver
+        })
+    }
+
+    /// Ticks are rounded up so that a timer never fires before its deadline
+    fn tick_for(&self, deadline: Instant) -> u64 {
+        let since = deadline.saturating_duration_since(self.started);
+        since.as_nanos().div_ceil(TICK.as_nanos()) as u64
+    }
+
+    fn current_tick(&self) -> u64 {
+        (self.started.elapsed().as_nanos() / TICK.as_nanos()) as u64
+    }
+
+    fn register(&self, deadline: Instant, state: Arc<Mutex<TimerState>>) -> u64 {
+        let id = self
+            .wheel
+            .lock()
+            .expect("Failed to lock")
+            .insert(self.tick_for(deadline), state);
+        // The driver may be parked until some later deadline
+        self.unparker.unpark();
+        id
+    }
+
+    fn cancel(&self, id: u64) {
+        self.wheel.lock().expect("Failed to lock").remove(id);
+    }
+
+    fn run(parker: Parker) {
+        let driver = Driver::get();
+        loop {
+            let (expired, next) = {
+                let mut wheel = driver.wheel.lock().expect("Failed to lock");
+                let expired = wheel.advance_to(driver.current_tick());
+                (expired, wheel.next_expiry())
+            };
+            for state in expired {
+                let waker = {
+                    let mut guard = state.lock().expect("Failed to lock");
+                    guard.fired = true;
+                    guard.waker.take()
+                };
+                if let Some(waker) = waker {
+                    waker.wake();
+                }
+            }
+            match next {
+                Some(tick) => {
+                    let wake_at = driver.started + Duration::from_millis(tick);
+                    parker.park_timeout(wake_at.saturating_duration_since(Instant::now()));
+                }
+                None => parker.park(),
+            }
+        }
+    }
+}
+
+/// Complete after `duration` has passed
+pub fn sleep(duration: Duration) -> Sleep {
+    let now = Instant::now();
+    sleep_until(
+        now.checked_add(duration)
+            .unwrap_or_else(|| now + FAR_FUTURE),
+    )
+}
+
+/// Complete once `deadline` has been reached
+pub fn sleep_until(deadline: Instant) -> Sleep {
+    Sleep {
+        deadline,
+        registration: None,
+    }
+}
+
+pub struct Sleep {
+    deadline: Instant,
+    registration: Option<(u64, Arc<Mutex<TimerState>>)>,
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if Instant::now() >= self.deadline {
+            return Poll::Ready(());
+        }
+        match &self.registration {
+            Some((_, state)) => {
+                let mut guard = state.lock().expect("Failed to lock");
+                if guard.fired {
+                    return Poll::Ready(());
+                }
+                guard.waker = Some(cx.waker().clone());
+            }
+            None => {
+                let state = Arc::new(Mutex::new(TimerState {
+                    fired: false,
+                    waker: Some(cx.waker().clone()),
+                }));
+                let id = Driver::get().register(self.deadline, state.clone());
+                self.registration = Some((id, state));
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Some((id, state)) = self.registration.take() {
+            if !state.lock().expect("Failed to lock").fired {
+                Driver::get().cancel(id);
+            }
+        }
+    }
+}
+
+/// The error returned by `timeout` when the deadline passes first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Elapsed;
+
+impl Display for Elapsed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "deadline has elapsed")
+    }
+}
+
+impl Error for Elapsed {}
+
+/// Run `future`, giving up on it if it hasn't completed within `duration`
+pub fn timeout<F: Future>(duration: Duration, future: F) -> Timeout<F> {
+    Timeout {
+        future: Box::pin(future),
+        sleep: sleep(duration),
+    }
+}
+
+pub struct Timeout<F> {
+    future: Pin<Box<F>>,
+    sleep: Sleep,
+}
+
+impl<F: Future> Future for Timeout<F> {
+    type Output = Result<F::Output, Elapsed>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Poll::Ready(value) = self.future.as_mut().poll(cx) {
+            return Poll::Ready(Ok(value));
+        }
+        match Pin::new(&mut self.sleep).poll(cx) {
+            Poll::Ready(()) => Poll::Ready(Err(Elapsed)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
@@ -0,0 +1,365 @@
+use super::channel::oneshot;
+use super::executor::JoinHandle;
+use crossbeam_deque::{Injector, Steal, Stealer, Worker};
+use crossbeam_utils::sync::{Parker, Unparker};
+use std::cell::RefCell;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread;
+
+#[cfg(test)]
+use super::spawn_blocking;
+#[cfg(test)]
+use std::time::Duration;
+
+#[test]
+fn test_pool_spawn() {
+    let pool = ThreadPool::new(4);
+    let total = pool.block_on(async {
+        let handles = (1..=1000_u64)
+            .map(|n| spawn(async move { n }))
+            .collect::<Vec<_>>();
+        let mut total = 0;
+        for handle in handles {
+            total += handle.await;
+        }
+        total
+    });
+    assert_eq!(total, 500_500);
+}
+
+#[test]
+fn test_tasks_run_on_many_threads() {
+    let pool = ThreadPool::new(4);
+    let names = pool.block_on(async {
+        let handles = (0..4)
+            .map(|_| {
+                spawn(async {
+                    // Hold each worker busy long enough for its siblings to steal the rest
+                    thread::sleep(Duration::from_millis(100));
+                    thread::current().id()
+                })
+            })
+            .collect::<Vec<_>>();
+        super::executor::join_all(handles).await
+    });
+    let distinct = names.iter().collect::<std::collections::HashSet<_>>().len();
+    assert!(distinct > 1);
+}
+
+#[test]
+fn test_nested_spawn_and_wakeups() {
+    let pool = ThreadPool::new(2);
+    let value = pool.block_on(async {
+        spawn(async {
+            let inner = spawn(async { spawn_blocking(|| 21).await });
+            inner.await * 2
+        })
+        .await
+    });
+    assert_eq!(value, 42);
+}
+
+#[test]
+fn test_shutdown_drains_tasks() {
+    let pool = ThreadPool::new(2);
+    let done = Arc::new(AtomicUsize::new(0));
+    for _ in 0..50 {
+        let done = done.clone();
+        drop(pool.spawn(async move {
+            spawn_blocking(|| thread::sleep(Duration::from_millis(10))).await;
+            done.fetch_add(1, Ordering::SeqCst);
+        }));
+    }
+    pool.shutdown();
+    assert_eq!(done.load(Ordering::SeqCst), 50);
+}
+
+#[test]
+fn test_panicking_task() {
+    let pool = ThreadPool::new(1);
+    drop(pool.spawn(async { panic!("task failed") }));
+    // The only worker is still around to run this, and shutting down doesn't wait for the
+    // panicked task
+    assert_eq!(pool.block_on(async { spawn(async { 7 }).await }), 7);
+    pool.shutdown();
+}
+
+type BoxFuture = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+struct PoolShared {
+    injector: Injector<Arc<Task>>,
+    stealers: Vec<Stealer<Arc<Task>>>,
+    unparkers: Vec<Unparker>,
+    /// Indices of the workers which are parked, or about to park
+    sleepers: Mutex<Vec<usize>>,
+    /// Tasks which have been spawned but haven't completed yet
+    outstanding: AtomicUsize,
+    shutting_down: AtomicBool,
+}
+
+impl PoolShared {
+    fn schedule(self: &Arc<Self>, task: Arc<Task>) {
+        let leftover = WORKER.with(|worker| match &*worker.borrow() {
+            Some(ctx) if Arc::ptr_eq(&ctx.pool, self) => {
+                ctx.local.push(task);
+                None
+            }
+            _ => Some(task),
+        });
+        if let Some(task) = leftover {
+            self.injector.push(task);
+        }
+        self.notify_one();
+    }
+
+    fn notify_one(&self) {
+        let sleeper = self.sleepers.lock().expect("Failed to lock").pop();
+        if let Some(index) = sleeper {
+            self.unparkers[index].unpark();
+        }
+    }
+
+    fn notify_all(&self) {
+        self.sleepers.lock().expect("Failed to lock").clear();
+        for unparker in &self.unparkers {
+            unparker.unpark();
+        }
+    }
+
+    fn has_work(&self) -> bool {
+        !self.injector.is_empty() || self.stealers.iter().any(|s| !s.is_empty())
+    }
+
+    fn is_drained(&self) -> bool {
+        self.shutting_down.load(Ordering::SeqCst) && self.outstanding.load(Ordering::SeqCst) == 0
+    }
+
+    fn spawn<F>(self: &Arc<Self>, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        let task = Arc::new(Task {
+            future: Mutex::new(Some(Box::pin(async move {
+                // Nobody minds if the handle has been dropped
+                let _ = sender.send(future.await);
+            }))),
+            pool: self.clone(),
+            scheduled: AtomicBool::new(true),
+        });
+        self.outstanding.fetch_add(1, Ordering::SeqCst);
+        self.schedule(task);
+        JoinHandle(receiver)
+    }
+}
+
+struct Task {
+    future: Mutex<Option<BoxFuture>>,
+    pool: Arc<PoolShared>,
+    /// Set while the task sits in a queue, so that repeated wake-ups queue it only once
+    scheduled: AtomicBool,
+}
+
+impl Wake for Task {
+    fn wake(self: Arc<Self>) {
+        if !self.scheduled.swap(true, Ordering::SeqCst) {
+            self.pool.clone().schedule(self);
+        }
+    }
+}
+
+impl Task {
+    fn run(self: Arc<Self>) {
+        let waker = Waker::from(self.clone());
+        let mut context = Context::from_waker(&waker);
+        let mut slot = self.future.lock().expect("Failed to lock");
+        self.scheduled.store(false, Ordering::SeqCst);
+        if let Some(future) = slot.as_mut() {
+            // A panic finishes the task just like completing does, without taking the worker
+            // down with it or leaving the task counted as outstanding
+            let polled =
+                panic::catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(&mut context)));
+            if !matches!(polled, Ok(Poll::Pending)) {
+                *slot = None;
+                if self.pool.outstanding.fetch_sub(1, Ordering::SeqCst) == 1
+                    && self.pool.shutting_down.load(Ordering::SeqCst)
+                {
+                    self.pool.notify_all();
+                }
+            }
+        }
+    }
+}
+
+struct WorkerContext {
+    pool: Arc<PoolShared>,
+    local: Worker<Arc<Task>>,
+}
+
+thread_local! {
+    static WORKER: RefCell<Option<WorkerContext>> = const { RefCell::new(None) };
+    static CURRENT: RefCell<Option<Arc<PoolShared>>> = const { RefCell::new(None) };
+}
+
+/// A fixed pool of worker threads, each with its own task queue. Idle workers first look at the
+/// global injector queue and then steal from their siblings before parking.
+pub struct ThreadPool {
+    shared: Arc<PoolShared>,
+    workers: Vec<thread::JoinHandle<()>>,
+}
+
+impl ThreadPool {
+    pub fn new(size: usize) -> Self {
+        let size = size.max(1);
+        let locals = (0..size).map(|_| Worker::new_fifo()).collect::<Vec<_>>();
+        let parkers = (0..size).map(|_| Parker::new()).collect::<Vec<_>>();
+        let shared = Arc::new(PoolShared {
+            injector: Injector::new(),
+            stealers: locals.iter().map(Worker::stealer).collect(),
+            unparkers: parkers.iter().map(|p| p.unparker().clone()).collect(),
+            sleepers: Mutex::new(Vec::with_capacity(size)),
+            outstanding: AtomicUsize::new(0),
+            shutting_down: AtomicBool::new(false),
+        });
+
+        let workers = locals
+            .into_iter()
+            .zip(parkers)
+            .enumerate()
+            .map(|(index, (local, parker))| {
+                let pool = shared.clone();
+                thread::Builder::new()
+                    .name(format!("prim-worker-{}", index))
+                    .spawn(move || run_worker(index, pool, local, parker))
+                    .expect("Failed to spawn worker thread")
+            })
+            .collect();
+        ThreadPool { shared, workers }
+    }
+
+    /// Spawn a task onto the pool. The task runs even if the returned handle is dropped. Should it
+    /// panic, awaiting the handle panics too.
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.shared.spawn(future)
+    }
+
+    /// Run `future` on the current thread while the workers take care of whatever it spawns
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        let previous = CURRENT.with(|current| current.borrow_mut().replace(self.shared.clone()));
+
+        let parker = Parker::new();
+        let unparker = parker.unparker().clone();
+        let waker = waker_fn::waker_fn(move || unparker.unpark());
+        let mut context = Context::from_waker(&waker);
+        let mut future = std::pin::pin!(future);
+        let value = loop {
+            match future.as_mut().poll(&mut context) {
+                Poll::Ready(value) => break value,
+                Poll::Pending => parker.park(),
+            }
+        };
+
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+        value
+    }
+
+    /// Wait for every outstanding task to complete and then stop the workers. Tasks which never
+    /// get woken up again will keep the pool from shutting down.
+    pub fn shutdown(self) {
+        drop(self)
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        self.shared.shutting_down.store(true, Ordering::SeqCst);
+        self.shared.notify_all();
+        for worker in self.workers.drain(..) {
+            if worker.join().is_err() {
+                eprintln!("Worker thread panicked");
+            }
+        }
+    }
+}
+
+/// Spawn a task onto the pool this code is running on, be it from one of its workers or from
+/// within `ThreadPool::block_on`.
+///
+/// # Panics
+/// When called from any other thread.
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let pool = CURRENT
+        .with(|current| current.borrow().clone())
+        .expect("spawn called outside of a thread pool");
+    pool.spawn(future)
+}
+
+fn run_worker(index: usize, pool: Arc<PoolShared>, local: Worker<Arc<Task>>, parker: Parker) {
+    CURRENT.with(|current| *current.borrow_mut() = Some(pool.clone()));
+    WORKER.with(|worker| {
+        *worker.borrow_mut() = Some(WorkerContext {
+            pool: pool.clone(),
+            local,
+        })
+    });
+
+    loop {
+        // The borrow must end before the task runs, since it may well spawn or wake others
+        let task = WORKER.with(|worker| find_task(worker.borrow().as_ref().expect("No worker")));
+        if let Some(task) = task {
+            task.run();
+            continue;
+        }
+        if pool.is_drained() {
+            break;
+        }
+
+        // Announce that we're going to sleep and then look once more, so that a task scheduled
+        // in between is either seen here or its notification unparks us
+        pool.sleepers.lock().expect("Failed to lock").push(index);
+        if pool.has_work() || pool.is_drained() {
+            pool.sleepers
+                .lock()
+                .expect("Failed to lock")
+                .retain(|&i| i != index);
+            continue;
+        }
+        parker.park();
+        pool.sleepers
+            .lock()
+            .expect("Failed to lock")
+            .retain(|&i| i != index);
+    }
+
+    WORKER.with(|worker| worker.borrow_mut().take());
+    CURRENT.with(|current| current.borrow_mut().take());
+}
+
+fn find_task(ctx: &WorkerContext) -> Option<Arc<Task>> {
+    ctx.local.pop().or_else(|| loop {
+        let stolen = ctx
+            .pool
+            .injector
+            .steal_batch_and_pop(&ctx.local)
+            .or_else(|| ctx.pool.stealers.iter().map(Stealer::steal).collect());
+        match stolen {
+            Steal::Success(task) => break Some(task),
+            Steal::Empty => break None,
+            Steal::Retry => continue,
+        }
+    })
+}
@@ -0,0 +1,329 @@
+//! Places to send output to, grown out of `open` and `Sink` in `chap_11`. Every one of them is a
+//! plain `Write`, so they can be stacked and handed to `write`.
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{stdout, BufWriter, Stdout, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+#[cfg(test)]
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("sink-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_gzip_by_suffix() {
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    let dir = scratch_dir("gzip");
+    let path = dir.join("out.txt.gz");
+    let mut out = open(path.to_str().unwrap()).unwrap();
+    write(&"Life before death. ".repeat(100), &mut out).unwrap();
+    out.finish().unwrap();
+    let compressed = std::fs::read(&path).unwrap();
+    assert!(compressed.len() < 1900);
+    let mut text = String::new();
+    GzDecoder::new(&compressed[..])
+        .read_to_string(&mut text)
+        .unwrap();
+    assert_eq!(text, "Life before death. ".repeat(100));
+
+    let plain = dir.join("out.txt");
+    let mut out = open(plain.to_str().unwrap()).unwrap();
+    write("plain", &mut out).unwrap();
+    out.finish().unwrap();
+    assert_eq!(std::fs::read_to_string(plain).unwrap(), "plain");
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_tee_and_count() {
+    let mut tee = Tee::new(vec![Counting::new(vec![]), Counting::new(vec![])]);
+    write("Strength before weakness", &mut tee).unwrap();
+    tee.push(Counting::new(vec![]));
+    write("!", &mut tee).unwrap();
+
+    let counts = tee
+        .into_inner()
+        .iter()
+        .map(Counting::bytes_written)
+        .collect::<Vec<_>>();
+    assert_eq!(counts, [25, 25, 1]);
+
+    let mut counting = Counting::new(Sink);
+    write("Journey before destination", &mut counting).unwrap();
+    assert_eq!(counting.bytes_written(), 26);
+}
+
+#[test]
+fn test_rotate_by_size() {
+    let dir = scratch_dir("size");
+    let path = dir.join("app.log");
+    let mut log = RotatingFile::new(&path, Rotation::Size(10), 2).unwrap();
+    for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+        write(line, &mut log).unwrap();
+    }
+    log.flush().unwrap();
+    let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+    assert_eq!(read("app.log"), "fourth\n");
+    assert_eq!(read("app.log.1"), "third\n");
+    assert_eq!(read("app.log.2"), "second\n");
+    // Only two old files are kept
+    assert!(!dir.join("app.log.3").exists());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_rotate_by_time() {
+    let dir = scratch_dir("time");
+    let path = dir.join("app.log");
+    let mut log =
+        RotatingFile::new(&path, Rotation::Interval(Duration::from_millis(50)), 5).unwrap();
+    write("before\n", &mut log).unwrap();
+    write("still before\n", &mut log).unwrap();
+    std::thread::sleep(Duration::from_millis(60));
+    write("after\n", &mut log).unwrap();
+    drop(log);
+    let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+    assert_eq!(read("app.log"), "after\n");
+    assert_eq!(read("app.log.1"), "before\nstill before\n");
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+pub fn write<T: Write>(data: &str, writer: &mut T) -> io::Result<()> {
+    writer.write_all(data.as_bytes())
+}
+
+/// Open `-` as stdout, or else create a file. Paths ending in `.gz` are gzip compressed.
+pub fn open(path: &str) -> io::Result<Output> {
+    match path {
+        "-" => Ok(Output::Stdout(BufWriter::new(stdout()))),
+        _ if path.ends_with(".gz") => {
+            let file = BufWriter::new(File::create(path)?);
+            Ok(Output::Gzip(GzEncoder::new(file, Compression::default())))
+        }
+        _ => Ok(Output::File(BufWriter::new(File::create(path)?))),
+    }
+}
+
+/// What `open` returns. Call `finish` once everything is written: dropping it instead still
+/// flushes, but has to ignore any error, and that includes writing the gzip trailer.
+#[derive(Debug)]
+pub enum Output {
+    Stdout(BufWriter<Stdout>),
+    File(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+}
+
+impl Output {
+    /// Flush everything through, ending the gzip stream if there is one
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            Output::Stdout(mut out) => out.flush(),
+            Output::File(mut file) => file.flush(),
+            Output::Gzip(encoder) => encoder.finish()?.flush(),
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Stdout(out) => out.write(buf),
+            Output::File(file) => file.write(buf),
+            Output::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Stdout(out) => out.flush(),
+            Output::File(file) => file.flush(),
+            Output::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// A writer which throws everything away
+#[derive(Debug)]
+pub struct Sink;
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes everything to each of its writers in turn. The first error stops the write, so the
+/// writers before it will have been given more than the ones after it.
+#[derive(Debug)]
+pub struct Tee<W> {
+    writers: Vec<W>,
+}
+
+impl<W: Write> Tee<W> {
+    pub fn new(writers: Vec<W>) -> Self {
+        Tee { writers }
+    }
+
+    pub fn push(&mut self, writer: W) {
+        self.writers.push(writer);
+    }
+
+    pub fn into_inner(self) -> Vec<W> {
+        self.writers
+    }
+}
+
+impl<W: Write> Write for Tee<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for writer in &mut self.writers {
+            writer.write_all(buf)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writers.iter_mut().try_for_each(Write::flush)
+    }
+}
+
+/// Passes writes through, keeping count of how many bytes the inner writer accepted
+#[derive(Debug)]
+pub struct Counting<W> {
+    inner: W,
+    bytes: u64,
+}
+
+impl<W: Write> Counting<W> {
+    pub fn new(inner: W) -> Self {
+        Counting { inner, bytes: 0 }
+    }
+
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for Counting<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.bytes += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// When a `RotatingFile` moves on to a fresh file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    /// Before a write would take the file past this many bytes. A single write bigger than this
+    /// still goes into one file.
+    Size(u64),
+    /// On the first write after the file has been open this long
+    Interval(Duration),
+}
+
+/// A log file which is rotated like `logrotate` does: `app.log` is renamed to `app.log.1`,
+/// `app.log.1` to `app.log.2` and so on, keeping at most `keep` old files.
+#[derive(Debug)]
+pub struct RotatingFile {
+    path: PathBuf,
+    rotation: Rotation,
+    keep: usize,
+    file: BufWriter<File>,
+    size: u64,
+    opened: Instant,
+}
+
+impl RotatingFile {
+    /// Append to `path`, which is created if need be
+    pub fn new<P: AsRef<Path>>(path: P, rotation: Rotation, keep: usize) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(RotatingFile {
+            size: file.metadata()?.len(),
+            file: BufWriter::new(file),
+            path,
+            rotation,
+            keep,
+            opened: Instant::now(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn numbered(&self, n: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", n));
+        name.into()
+    }
+
+    fn should_rotate(&self, incoming: usize) -> bool {
+        match self.rotation {
+            Rotation::Size(limit) => self.size > 0 && self.size + incoming as u64 > limit,
+            Rotation::Interval(interval) => self.opened.elapsed() >= interval,
+        }
+    }
+
+    /// Move the current file out of the way and start a new one
+    pub fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        match std::fs::remove_file(self.numbered(self.keep)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        for n in (1..self.keep).rev() {
+            let from = self.numbered(n);
+            if from.exists() {
+                std::fs::rename(from, self.numbered(n + 1))?;
+            }
+        }
+        if self.keep > 0 {
+            std::fs::rename(&self.path, self.numbered(1))?;
+        }
+        self.file = BufWriter::new(File::create(&self.path)?);
+        self.size = 0;
+        self.opened = Instant::now();
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.should_rotate(buf.len()) {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
@@ -0,0 +1,182 @@
+//! The pieces of the `spellcheck` binary: splitting text into words, looking them up in a
+//! `StringSet`, and suggesting corrections for the ones which aren't there
+
+use crate::string_set::StringSet;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+#[cfg(test)]
+use crate::string_set::Trie;
+
+#[test]
+fn test_words() {
+    let words = words("Don't panic, it's 42 o'clock\u{2014}naïve café!").collect::<Vec<_>>();
+    assert_eq!(
+        words,
+        [
+            (1, "Don't"),
+            (7, "panic"),
+            (14, "it's"),
+            (22, "o'clock"),
+            (30, "naïve"),
+            (36, "café")
+        ]
+    );
+}
+
+#[test]
+fn test_check_normalizes() {
+    let dictionary = ["the", "café", "in", "london"];
+    let mut set = Trie::new();
+    for word in dictionary {
+        set.add(word);
+    }
+    // A decomposed é, a capitalised word and a capitalised misspelling
+    let text = "The cafe\u{301} in London\nteh Cafë";
+    let unknown = check(text, &set);
+    assert_eq!(
+        unknown,
+        [
+            Unknown {
+                line: 2,
+                column: 1,
+                word: "teh".to_string()
+            },
+            Unknown {
+                line: 2,
+                column: 5,
+                word: "Cafë".to_string()
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_edit_distance() {
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+    assert_eq!(edit_distance("", "abc"), 3);
+    assert_eq!(edit_distance("café", "cafe"), 1);
+    assert_eq!(edit_distance("same", "same"), 0);
+    // A swap of neighbouring letters is a single edit
+    assert_eq!(edit_distance("teh", "the"), 1);
+    assert_eq!(edit_distance("ca", "abc"), 3);
+}
+
+#[test]
+fn test_suggest() {
+    let dictionary = ["the", "then", "ten", "tea", "hello", "eth"];
+    assert_eq!(suggest("teh", &dictionary, 3), ["eth", "tea", "ten"]);
+    assert_eq!(
+        suggest("Teh", &dictionary, 5),
+        ["eth", "tea", "ten", "the", "then"]
+    );
+    assert_eq!(suggest("helo", &dictionary, 3), ["hello"]);
+    assert!(suggest("zzzzzz", &dictionary, 3).is_empty());
+}
+
+/// Suggestions further away than this aren't worth showing
+pub const MAX_DISTANCE: usize = 2;
+
+/// A word which wasn't in the dictionary, with its 1-based line and column in characters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unknown {
+    pub line: usize,
+    pub column: usize,
+    pub word: String,
+}
+
+/// Compose any combining characters, so that "cafe\u{301}" and "café" are the same word
+pub fn normalize(word: &str) -> String {
+    word.nfc().collect()
+}
+
+/// The words in `line`, using the Unicode word boundary rules, with the column each starts at.
+/// Anything without a letter in it, or with a digit, isn't counted as a word.
+pub fn words(line: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut column = 1;
+    let mut last = 0;
+    line.split_word_bound_indices()
+        .map(move |(offset, segment)| {
+            column += line[last..offset].chars().count();
+            last = offset;
+            (column, segment)
+        })
+        .filter(|(_, segment)| {
+            segment.chars().any(char::is_alphabetic) && !segment.chars().any(char::is_numeric)
+        })
+}
+
+/// Whether `word` is in the set, either as it is or in lower case. The dictionary is expected
+/// to be normalized already.
+pub fn is_known<'a, S: StringSet<'a>>(word: &str, set: &S) -> bool {
+    let word = normalize(word);
+    set.contains(&word) || set.contains(&word.to_lowercase())
+}
+
+/// Every word in `text` which isn't in the set, in order
+pub fn check<'a, S: StringSet<'a>>(text: &str, set: &S) -> Vec<Unknown> {
+    let mut unknown = vec![];
+    for (index, line) in text.lines().enumerate() {
+        for (column, word) in words(line) {
+            if !is_known(word, set) {
+                unknown.push(Unknown {
+                    line: index + 1,
+                    column,
+                    word: word.to_string(),
+                });
+            }
+        }
+    }
+    unknown
+}
+
+/// The number of single-character insertions, deletions, substitutions and swaps of
+/// neighbouring characters needed to turn `a` into `b`. Swaps are counted because they're such a
+/// common typo. This is the "optimal string alignment" distance, so no substring is edited
+/// twice.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = distances[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            let mut best = substitution
+                .min(distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = best;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+/// Up to `limit` dictionary words within `MAX_DISTANCE` edits of `word`, closest first and
+/// alphabetically among equals
+pub fn suggest<S: AsRef<str>>(word: &str, dictionary: &[S], limit: usize) -> Vec<String> {
+    let word = normalize(word).to_lowercase();
+    let length = word.chars().count();
+    let mut candidates = dictionary
+        .iter()
+        .map(AsRef::as_ref)
+        // The length difference is a lower bound on the distance, and much cheaper
+        .filter(|candidate| candidate.chars().count().abs_diff(length) <= MAX_DISTANCE)
+        .map(|candidate| (edit_distance(&word, candidate), candidate))
+        .filter(|&(distance, _)| distance <= MAX_DISTANCE)
+        .collect::<Vec<_>>();
+    candidates.sort_unstable();
+    candidates.dedup();
+    candidates
+        .into_iter()
+        .take(limit)
+        .map(|(_, candidate)| candidate.to_string())
+        .collect()
+}
@@ -0,0 +1,318 @@
+//! The `StringSet` trait from `chap_11`, with a few more ways of storing the strings
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+#[cfg(test)]
+const WORDS: [&str; 6] = [
+    "stormlight",
+    "storm",
+    "stone",
+    "spren",
+    "shard",
+    "shardblade",
+];
+
+#[cfg(test)]
+fn check_membership<'a, S: StringSet<'a>>() {
+    let mut set = S::new();
+    for word in WORDS {
+        set.add(word);
+    }
+    for word in WORDS {
+        assert!(set.contains(word), "{} should be in the set", word);
+    }
+    let unknown = find_unknown(&["storm", "sto", "shardplate", "spren"], &set);
+    assert!(unknown.contains("sto"));
+    assert!(unknown.contains("shardplate"));
+    assert!(!unknown.contains("storm"));
+}
+
+#[test]
+fn test_every_set_finds_unknown() {
+    check_membership::<StringSetHash>();
+    check_membership::<Trie>();
+    check_membership::<SortedVec>();
+    check_membership::<BloomFilter>();
+}
+
+#[test]
+fn test_trie_prefixes() {
+    let mut trie = Trie::new();
+    for word in WORDS {
+        trie.add(word);
+    }
+    assert!(!trie.contains("sto"));
+    assert!(trie.starts_with("sto"));
+    assert!(!trie.starts_with("x"));
+    assert_eq!(trie.with_prefix("st"), ["stone", "storm", "stormlight"]);
+    assert_eq!(trie.with_prefix("storm"), ["storm", "stormlight"]);
+    assert!(trie.with_prefix("q").is_empty());
+    assert_eq!(trie.autocomplete("s", 3), ["shard", "spren", "stone"]);
+    assert_eq!(trie.len(), WORDS.len());
+}
+
+#[test]
+fn test_sorted_vec() {
+    let set = SortedVec::from_unsorted(vec!["b", "a", "c", "a"]);
+    assert_eq!(set.as_slice(), ["a", "b", "c"]);
+    let mut set = SortedVec::new();
+    set.add("kaladin").add("adolin").add("kaladin");
+    assert_eq!(set.as_slice(), ["adolin", "kaladin"]);
+    assert!(!set.contains("shallan"));
+}
+
+#[test]
+fn test_bloom_false_positive_rate() {
+    let words = (0..10_000)
+        .map(|n| format!("word{}", n))
+        .collect::<Vec<_>>();
+    let mut bloom = BloomFilter::with_rate(words.len(), 0.01);
+    for word in &words {
+        bloom.add(word);
+    }
+    assert!(words.iter().all(|w| bloom.contains(w)));
+    let false_positives = (0..10_000)
+        .filter(|n| bloom.contains(&format!("other{}", n)))
+        .count();
+    // 1% of 10,000 is 100; allow some slack for an unlucky hash
+    assert!(false_positives < 200, "{} false positives", false_positives);
+}
+
+pub trait StringSet<'a> {
+    /// Create a new string set
+    fn new() -> Self;
+
+    /// Add a new item to the existing string set
+    fn add(&mut self, name: &'a str) -> &mut Self;
+
+    /// Check if the string set contains a given string
+    fn contains(&self, name: &str) -> bool;
+}
+
+/// The words which aren't in `set`
+pub fn find_unknown<'a, S: StringSet<'a>>(words: &[&'a str], set: &S) -> S {
+    let mut u = S::new();
+    for w in words {
+        if !set.contains(w) {
+            u.add(w);
+        }
+    }
+    u
+}
+
+#[derive(Debug, Default)]
+pub struct StringSetHash<'a> {
+    names: HashSet<&'a str>,
+}
+
+impl<'a> StringSet<'a> for StringSetHash<'a> {
+    fn new() -> Self {
+        StringSetHash {
+            names: HashSet::new(),
+        }
+    }
+
+    fn add(&mut self, name: &'a str) -> &mut Self {
+        self.names.insert(name);
+        self
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.names.contains(name)
+    }
+}
+
+#[derive(Debug, Default)]
+struct TrieNode {
+    /// A `BTreeMap` so that completions come out in order
+    children: BTreeMap<char, TrieNode>,
+    is_word: bool,
+}
+
+/// A prefix tree, which can also find every word starting with a given prefix
+#[derive(Debug, Default)]
+pub struct Trie {
+    root: TrieNode,
+    len: usize,
+}
+
+impl Trie {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn find(&self, prefix: &str) -> Option<&TrieNode> {
+        prefix
+            .chars()
+            .try_fold(&self.root, |node, c| node.children.get(&c))
+    }
+
+    /// Whether any word starts with `prefix`
+    pub fn starts_with(&self, prefix: &str) -> bool {
+        self.find(prefix).is_some()
+    }
+
+    /// Every word starting with `prefix`, in alphabetical order
+    pub fn with_prefix(&self, prefix: &str) -> Vec<String> {
+        fn collect(node: &TrieNode, word: &mut String, out: &mut Vec<String>) {
+            if node.is_word {
+                out.push(word.clone());
+            }
+            for (&c, child) in &node.children {
+                word.push(c);
+                collect(child, word, out);
+                word.pop();
+            }
+        }
+
+        let mut out = vec![];
+        if let Some(node) = self.find(prefix) {
+            collect(node, &mut prefix.to_string(), &mut out);
+        }
+        out
+    }
+
+    /// Up to `limit` words starting with `prefix`, shortest first
+    pub fn autocomplete(&self, prefix: &str, limit: usize) -> Vec<String> {
+        let mut out = vec![];
+        let mut queue = VecDeque::from_iter(self.find(prefix).map(|n| (n, prefix.to_string())));
+        while let Some((node, word)) = queue.pop_front() {
+            if out.len() == limit {
+                break;
+            }
+            if node.is_word {
+                out.push(word.clone());
+            }
+            for (&c, child) in &node.children {
+                let mut longer = word.clone();
+                longer.push(c);
+                queue.push_back((child, longer));
+            }
+        }
+        out
+    }
+}
+
+impl<'a> StringSet<'a> for Trie {
+    fn new() -> Self {
+        Trie::default()
+    }
+
+    fn add(&mut self, name: &'a str) -> &mut Self {
+        let node = name.chars().fold(&mut self.root, |node, c| {
+            node.children.entry(c).or_default()
+        });
+        if !node.is_word {
+            node.is_word = true;
+            self.len += 1;
+        }
+        self
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.find(name).is_some_and(|node| node.is_word)
+    }
+}
+
+/// A sorted, deduplicated `Vec`, searched with a binary search. Adding is O(n), so
+/// `from_unsorted` is the way to build a big one.
+#[derive(Debug, Default)]
+pub struct SortedVec<'a> {
+    names: Vec<&'a str>,
+}
+
+impl<'a> SortedVec<'a> {
+    pub fn from_unsorted(mut names: Vec<&'a str>) -> Self {
+        names.sort_unstable();
+        names.dedup();
+        SortedVec { names }
+    }
+
+    pub fn as_slice(&self) -> &[&'a str] {
+        &self.names
+    }
+}
+
+impl<'a> StringSet<'a> for SortedVec<'a> {
+    fn new() -> Self {
+        SortedVec { names: vec![] }
+    }
+
+    fn add(&mut self, name: &'a str) -> &mut Self {
+        if let Err(index) = self.names.binary_search(&name) {
+            self.names.insert(index, name);
+        }
+        self
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.names.binary_search(&name).is_ok()
+    }
+}
+
+/// A probabilistic set: `contains` never misses a string which was added, but may claim to have
+/// ones which weren't. Only the hashes are kept, so it's much smaller than the other sets.
+#[derive(Debug)]
+pub struct BloomFilter<'a> {
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+    names: PhantomData<&'a str>,
+}
+
+impl BloomFilter<'_> {
+    /// Size the filter so that, once `capacity` strings have been added, the chance of a false
+    /// positive is about `false_positive_rate`
+    pub fn with_rate(capacity: usize, false_positive_rate: f64) -> Self {
+        let capacity = capacity.max(1) as f64;
+        let rate = false_positive_rate.clamp(f64::MIN_POSITIVE, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let num_bits = (-capacity * rate.ln() / (ln2 * ln2)).ceil().max(64.0) as u64;
+        let num_hashes = ((num_bits as f64 / capacity) * ln2).round().max(1.0) as u32;
+        BloomFilter {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            num_hashes,
+            names: PhantomData,
+        }
+    }
+
+    /// The bits for `name`, using double hashing to get `num_hashes` of them from two hashes
+    fn positions(&self, name: &str) -> impl Iterator<Item = u64> + '_ {
+        let hash = |seed: u64| {
+            let mut hasher = DefaultHasher::new();
+            seed.hash(&mut hasher);
+            name.hash(&mut hasher);
+            hasher.finish()
+        };
+        let (h1, h2) = (hash(0), hash(1) | 1);
+        (0..self.num_hashes as u64)
+            .map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % self.num_bits)
+    }
+}
+
+impl<'a> StringSet<'a> for BloomFilter<'a> {
+    /// Sized for a thousand strings with a 1% false-positive rate
+    fn new() -> Self {
+        BloomFilter::with_rate(1000, 0.01)
+    }
+
+    fn add(&mut self, name: &'a str) -> &mut Self {
+        for bit in self.positions(name).collect::<Vec<_>>() {
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+        self
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.positions(name)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+}
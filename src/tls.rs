@@ -0,0 +1,228 @@
+use crate::echo_server::{Close, Connection, Received};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::{ClientConfig, ClientConnection, RootCertStore, ServerConfig, ServerConnection};
+use rustls::{Stream, StreamOwned};
+use std::io::ErrorKind::UnexpectedEof;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::sync::Arc;
+
+#[cfg(test)]
+fn self_signed() -> (CertificateDer<'static>, PrivateKeyDer<'static>) {
+    let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let key = PrivateKeyDer::Pkcs8(certified.key_pair.serialize_der().into());
+    (certified.cert.der().clone(), key)
+}
+
+#[cfg(test)]
+fn spawn_tls_echo(
+    cert: CertificateDer<'static>,
+    key: PrivateKeyDer<'static>,
+) -> (
+    std::net::SocketAddr,
+    crate::echo_server::ShutdownHandle,
+    std::thread::JoinHandle<io::Result<()>>,
+) {
+    use crate::echo_server::EchoServer;
+
+    let config = server_config(vec![cert], key).unwrap();
+    let server = EchoServer::bind("127.0.0.1:0", Default::default()).unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = server.shutdown_handle();
+    let runner = std::thread::spawn(move || server.run_tls(config));
+    (addr, handle, runner)
+}
+
+#[test]
+fn test_tls_echo() {
+    let (cert, key) = self_signed();
+    let (addr, handle, runner) = spawn_tls_echo(cert.clone(), key);
+
+    let config = client_config(&[cert]).unwrap();
+    let mut client = connect(addr, "localhost", config).unwrap();
+    client.write_all(b"Journey before destination").unwrap();
+    let mut reply = [0_u8; 26];
+    client.read_exact(&mut reply).unwrap();
+    assert_eq!(&reply, b"Journey before destination");
+    client.conn.send_close_notify();
+    client.flush().unwrap();
+
+    handle.shutdown();
+    runner.join().unwrap().unwrap();
+}
+
+#[test]
+fn test_tls_echo_async() {
+    use async_std::io::{ReadExt, WriteExt};
+
+    let (cert, key) = self_signed();
+    let (addr, handle, runner) = spawn_tls_echo(cert.clone(), key);
+
+    let config = client_config(&[cert]).unwrap();
+    async_std::task::block_on(async {
+        let mut client = connect_async(addr, "localhost", config).await.unwrap();
+        client.write_all(b"Strength before weakness").await.unwrap();
+        let mut reply = [0_u8; 24];
+        client.read_exact(&mut reply).await.unwrap();
+        assert_eq!(&reply, b"Strength before weakness");
+    });
+
+    handle.shutdown();
+    runner.join().unwrap().unwrap();
+}
+
+#[test]
+fn test_untrusted_certificate() {
+    let (cert, key) = self_signed();
+    let (addr, handle, runner) = spawn_tls_echo(cert, key);
+
+    // Only the public roots are trusted so the self-signed certificate is refused
+    let config = client_config(&[]).unwrap();
+    let mut client = connect(addr, "localhost", config).unwrap();
+    let err = client.write_all(b"hi").and_then(|_| client.flush());
+    let err = err.and_then(|_| client.read(&mut [0_u8; 2]).map(|_| ()));
+    assert!(err.is_err());
+
+    handle.shutdown();
+    runner.join().unwrap().unwrap();
+}
+
+#[test]
+fn test_load_pem_files() {
+    let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let dir = std::env::temp_dir().join(format!("tls-pem-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let cert_path = dir.join("cert.pem");
+    let key_path = dir.join("key.pem");
+    std::fs::write(&cert_path, certified.cert.pem()).unwrap();
+    std::fs::write(&key_path, certified.key_pair.serialize_pem()).unwrap();
+
+    let certs = load_certs(&cert_path).unwrap();
+    assert_eq!(certs, vec![certified.cert.der().clone()]);
+    let key = load_private_key(&key_path).unwrap();
+    assert!(server_config(certs, key).is_ok());
+
+    assert!(load_certs(dir.join("missing.pem")).is_err());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// Read every certificate out of a PEM file
+pub fn load_certs<P: AsRef<Path>>(path: P) -> io::Result<Vec<CertificateDer<'static>>> {
+    CertificateDer::pem_file_iter(path)
+        .map_err(io::Error::other)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(io::Error::other)
+}
+
+/// Read the first private key out of a PEM file
+pub fn load_private_key<P: AsRef<Path>>(path: P) -> io::Result<PrivateKeyDer<'static>> {
+    PrivateKeyDer::from_pem_file(path).map_err(io::Error::other)
+}
+
+/// Build the configuration a TLS listener uses to present `certs` to its clients
+pub fn server_config(
+    certs: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+) -> io::Result<Arc<ServerConfig>> {
+    let config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(io::Error::other)?;
+    Ok(Arc::new(config))
+}
+
+/// Build a client configuration trusting the usual web roots plus any `extra_roots`, which is
+/// how self-signed certificates get accepted
+pub fn client_config(extra_roots: &[CertificateDer<'static>]) -> io::Result<Arc<ClientConfig>> {
+    let mut roots = RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    for cert in extra_roots {
+        roots.add(cert.clone()).map_err(io::Error::other)?;
+    }
+    let config = ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(Arc::new(config))
+}
+
+fn server_name(name: &str) -> io::Result<ServerName<'static>> {
+    ServerName::try_from(name.to_string())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+/// Open a TLS connection to `addr`, verifying that it presents a certificate for `name`. The
+/// handshake happens lazily on the first read or write.
+pub fn connect<A: ToSocketAddrs>(
+    addr: A,
+    name: &str,
+    config: Arc<ClientConfig>,
+) -> io::Result<StreamOwned<ClientConnection, TcpStream>> {
+    let conn = ClientConnection::new(config, server_name(name)?).map_err(io::Error::other)?;
+    let socket = TcpStream::connect(addr)?;
+    socket.set_nodelay(true)?;
+    Ok(StreamOwned::new(conn, socket))
+}
+
+/// The async-std counterpart of `connect`; the handshake completes before this returns
+pub async fn connect_async<A: async_std::net::ToSocketAddrs>(
+    addr: A,
+    name: &str,
+    config: Arc<ClientConfig>,
+) -> io::Result<futures_rustls::client::TlsStream<async_std::net::TcpStream>> {
+    let name = server_name(name)?;
+    let socket = async_std::net::TcpStream::connect(addr).await?;
+    socket.set_nodelay(true)?;
+    futures_rustls::TlsConnector::from(config)
+        .connect(name, socket)
+        .await
+}
+
+/// Adapts a server `Connection` to `Read`/`Write` so that rustls can drive it, remembering
+/// why the connection ended
+struct ConnectionIo<'a, 'b> {
+    conn: &'a Connection<'b>,
+    closed: Option<Close>,
+}
+
+impl Read for ConnectionIo<'_, '_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.conn.receive(buf)? {
+            Received::Bytes(len) => Ok(len),
+            Received::Closed(reason) => {
+                self.closed = Some(reason);
+                Ok(0)
+            }
+        }
+    }
+}
+
+impl Write for ConnectionIo<'_, '_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.conn.send(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Echo handler for `EchoServer::run_tls`. The stats count the encrypted bytes on the wire.
+pub(crate) fn echo(conn: &Connection, config: Arc<ServerConfig>) -> io::Result<Close> {
+    let mut tls = ServerConnection::new(config).map_err(io::Error::other)?;
+    let mut io = ConnectionIo { conn, closed: None };
+    let mut stream = Stream::new(&mut tls, &mut io);
+    let mut buf = vec![0_u8; conn.config().buffer_size.max(1)];
+    loop {
+        match stream.read(&mut buf) {
+            Ok(0) => break,
+            Ok(len) => stream.write_all(&buf[..len])?,
+            // The client went away without a close_notify, or we gave up on it
+            Err(ref e) if e.kind() == UnexpectedEof => break,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(io.closed.unwrap_or(Close::Finished))
+}